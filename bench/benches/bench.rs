@@ -3,11 +3,11 @@ use std::{env, fs, hint::black_box, path::PathBuf, time::Duration};
 use criterion::{Criterion, criterion_group, criterion_main};
 use filego::{
     check::{Check, CheckResult},
-    merge::Merge,
+    merge::{Merge, MergeResult},
     split::{Split, SplitResult},
 };
 
-const CHUNK_SIZE: usize = 1 * 1024 * 1024;
+const CHUNK_SIZE: usize = 1024 * 1024;
 const FILE_NAME: &str = "test.jpg";
 
 struct Configs {
@@ -33,7 +33,7 @@ fn pre_split() -> SplitResult {
 
     Split::new()
         .in_file(&configs.in_file)
-        .out_dir(&configs.cache_dir.join("-1"))
+        .out_dir(configs.cache_dir.join("-1"))
         .chunk_size(CHUNK_SIZE)
         .run()
         .unwrap()
@@ -83,7 +83,7 @@ fn bench_check(c: &mut Criterion) {
     group.bench_function("FileGo", |b| {
         b.iter(|| {
             let result: CheckResult = Check::new()
-                .in_dir(&configs.cache_dir.join("0"))
+                .in_dir(configs.cache_dir.join("0"))
                 .file_size(split.file_size)
                 .total_chunks(split.total_chunks)
                 .run()
@@ -114,8 +114,8 @@ fn bench_merge(c: &mut Criterion) {
         b.iter(|| {
             let out_file: PathBuf = configs.out_dir.join(format!("{}.jpg", i));
 
-            let result: bool = Merge::new()
-                .in_dir(&configs.cache_dir.join("0"))
+            let result: MergeResult = Merge::new()
+                .in_dir(configs.cache_dir.join("0"))
                 .out_file(out_file)
                 .run()
                 .unwrap();
@@ -126,6 +126,26 @@ fn bench_merge(c: &mut Criterion) {
         });
     });
 
+    group.bench_function("FileGo (buffered)", |b| {
+        let mut i: usize = 0;
+
+        b.iter(|| {
+            let out_file: PathBuf =
+                configs.out_dir.join(format!("buffered-{}.jpg", i));
+
+            let result: MergeResult = Merge::new()
+                .in_dir(configs.cache_dir.join("0"))
+                .out_file(out_file)
+                .kernel_copy(false)
+                .run()
+                .unwrap();
+
+            black_box(result);
+
+            i += 1;
+        });
+    });
+
     group.finish();
 }
 
@@ -0,0 +1,54 @@
+use std::fs;
+
+/// Flag of `CreateFileW` optimizing the cache for a sequential scan.
+#[cfg(windows)]
+const FILE_FLAG_SEQUENTIAL_SCAN: u32 = 0x0800_0000;
+
+/// Hint that the file is read sequentially, so more of it is read ahead.
+#[cfg(target_os = "linux")]
+pub(crate) fn sequential<F: std::os::fd::AsRawFd>(file: &F) {
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_SEQUENTIAL)
+    };
+}
+
+/// Hint that the file is read sequentially, so more of it is read ahead.
+///
+/// Only supported on Linux, it does nothing on other platforms.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn sequential<F>(_file: &F) {}
+
+/// Hint that the cached pages of the file are not needed anymore.
+///
+/// The pages not written back yet are kept, so it mostly frees the pages of
+/// the files read.
+#[cfg(target_os = "linux")]
+pub(crate) fn dont_need<F: std::os::fd::AsRawFd>(file: &F) {
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED)
+    };
+}
+
+/// Hint that the cached pages of the file are not needed anymore.
+///
+/// Only supported on Linux, it does nothing on other platforms.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn dont_need<F>(_file: &F) {}
+
+/// Open the file for a sequential scan.
+///
+/// Only supported on Windows, as the other platforms are hinted once the
+/// file is opened, see [`sequential`].
+#[cfg(windows)]
+pub(crate) fn sequential_scan(options: &mut fs::OpenOptions) {
+    use std::os::windows::fs::OpenOptionsExt as _;
+
+    options.custom_flags(FILE_FLAG_SEQUENTIAL_SCAN);
+}
+
+/// Open the file for a sequential scan.
+///
+/// Only supported on Windows, as the other platforms are hinted once the
+/// file is opened, see [`sequential`].
+#[cfg(not(windows))]
+pub(crate) fn sequential_scan(_options: &mut fs::OpenOptions) {}
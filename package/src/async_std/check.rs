@@ -1,10 +1,23 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::ErrorKind,
+};
+
 use async_std::{
     fs,
+    io::ReadExt as _,
     path::{Path, PathBuf},
 };
 
-use crate::check::{
-    Check, CheckError, CheckResult, CheckResultError, CheckResultErrorType,
+use crate::{
+    check::{
+        COMPARE_BUFFER_SIZE, Check, CheckError, CheckResult, CheckResultError,
+        CheckResultErrorType,
+    },
+    control::{Control, ControlGuard},
+    hash::{HashAlgorithm, Hasher},
+    lock::{DirLock, LOCK_FILE_NAME},
+    manifest::Manifest,
 };
 
 /// Trait for running the check process.
@@ -36,36 +49,107 @@ impl CheckAsyncExt for Check {
             | None => return Err(CheckError::InDirNotSet),
         };
 
-        let file_size: usize = match self.file_size {
-            | Some(s) => s,
-            | None => return Err(CheckError::FileSizeNotSet),
-        };
+        // the names of the chunks may depend on their content
+        super::scan(&self.namer, in_dir.as_ref()).await;
+
+        let manifest: Option<Manifest> = self.load_manifest()?;
 
-        let total_chunks: usize = match self.total_chunks {
+        let metadata: BTreeMap<String, String> =
+            manifest.as_ref().map(|m| m.metadata.clone()).unwrap_or_default();
+
+        let file_size: usize =
+            match self.file_size.or(manifest.as_ref().map(|m| m.file_size)) {
+                | Some(s) => s,
+                | None => return Err(CheckError::FileSizeNotSet),
+            };
+
+        let total_chunks: usize = match self
+            .total_chunks
+            .or(manifest.as_ref().map(|m| m.total_chunks))
+        {
             | Some(s) => s,
             | None => return Err(CheckError::TotalChunksNotSet),
         };
 
+        if self.max_chunks.is_some_and(|max| total_chunks > max) {
+            return Err(CheckError::TooManyChunks);
+        }
+
+        let _lock: Option<DirLock> = if self.lock {
+            match DirLock::acquire(in_dir) {
+                | Ok(l) => Some(l),
+                | Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    return Err(CheckError::Locked);
+                },
+                | Err(e) => {
+                    return Err(CheckError::LockNotCreated {
+                        path: in_dir.join(LOCK_FILE_NAME).into(),
+                        source: e.into(),
+                    });
+                },
+            }
+        } else {
+            None
+        };
+
+        let _control: Option<ControlGuard> =
+            self.control.as_ref().map(Control::run);
+
         let mut actual_size: usize = 0;
         let mut missing: Vec<usize> = Vec::new();
+        let mut resized: Vec<usize> = Vec::new();
+        let mut sizes: Vec<usize> = Vec::with_capacity(total_chunks);
+
+        // the chunks may vary in size, so each one is compared to the
+        // manifest rather than to a uniform chunk size
+        let expected_sizes: HashMap<usize, usize> = manifest
+            .iter()
+            .flat_map(|m| &m.chunks)
+            .map(|c| (c.index, c.size))
+            .collect();
 
         for i in 0..total_chunks {
-            let target_file: PathBuf = in_dir.join(i.to_string());
+            if let Some(ref c) = self.control {
+                if !c.wait_async().await {
+                    return Err(CheckError::Cancelled);
+                }
+            }
+
+            let target_file: PathBuf = in_dir.join(self.namer.name(i));
 
             if !target_file.exists().await || !target_file.is_file().await {
                 missing.push(i);
                 continue;
             }
 
-            actual_size +=
-                match fs::OpenOptions::new().read(true).open(&target_file).await
-                {
-                    | Ok(f) => match f.metadata().await {
-                        | Ok(m) => m.len() as usize,
-                        | Err(_) => return Err(CheckError::InFileNotRead),
+            let size: usize = match fs::OpenOptions::new()
+                .read(true)
+                .open(&target_file)
+                .await
+            {
+                | Ok(f) => match f.metadata().await {
+                    | Ok(m) => m.len() as usize,
+                    | Err(e) => {
+                        return Err(CheckError::InFileNotRead {
+                            path: target_file.into(),
+                            source: e.into(),
+                        });
                     },
-                    | Err(_) => return Err(CheckError::InFileNotOpened),
-                }
+                },
+                | Err(e) => {
+                    return Err(CheckError::InFileNotOpened {
+                        path: target_file.into(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if expected_sizes.get(&i).is_some_and(|s| *s != size) {
+                resized.push(i);
+            }
+
+            actual_size += size;
+            sizes.push(size);
         }
 
         if !missing.is_empty() {
@@ -75,7 +159,22 @@ impl CheckAsyncExt for Check {
                     error_type: CheckResultErrorType::Missing,
                     message: "Missing chunk(s)".to_string(),
                     missing: Some(missing),
+                    mismatched: None,
                 }),
+                metadata,
+            });
+        }
+
+        if !resized.is_empty() {
+            return Ok(CheckResult {
+                success: false,
+                error: Some(CheckResultError {
+                    error_type: CheckResultErrorType::Size,
+                    message: "Mismatched chunk size(s)".to_string(),
+                    missing: None,
+                    mismatched: Some(resized),
+                }),
+                metadata,
             });
         }
 
@@ -88,10 +187,179 @@ impl CheckAsyncExt for Check {
                         "the size of chunks is not equal to file_size parameter"
                             .to_string(),
                     missing: None,
+                    mismatched: None,
+                }),
+                metadata,
+            });
+        }
+
+        let mut mismatched: Vec<usize> = Vec::new();
+
+        for chunk in manifest.iter().flat_map(|m| &m.chunks) {
+            if let Some(ref c) = self.control {
+                if !c.wait_async().await {
+                    return Err(CheckError::Cancelled);
+                }
+            }
+
+            let (algorithm, expected) = match chunk.hash() {
+                | Some(h) if chunk.index < total_chunks => h,
+                | _ => continue,
+            };
+
+            let chunk_path: PathBuf = in_dir.join(self.namer.name(chunk.index));
+
+            let content: Vec<u8> = match fs::read(&chunk_path).await {
+                | Ok(c) => c,
+                | Err(e) => {
+                    return Err(CheckError::InFileNotRead {
+                        path: chunk_path.into(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if !algorithm.hex(&content).eq_ignore_ascii_case(expected) {
+                mismatched.push(chunk.index);
+            }
+        }
+
+        if !mismatched.is_empty() {
+            return Ok(CheckResult {
+                success: false,
+                error: Some(CheckResultError {
+                    error_type: CheckResultErrorType::Hash,
+                    message: "Mismatched chunk hash(es)".to_string(),
+                    missing: None,
+                    mismatched: Some(mismatched),
                 }),
+                metadata,
             });
         }
 
-        Ok(CheckResult { success: true, error: None })
+        // compare the merged file without rewriting it
+        if let Some(ref out_file) = self.out_file {
+            if !fs::metadata(out_file)
+                .await
+                .is_ok_and(|m| m.is_file() && m.len() == file_size as u64)
+            {
+                return Ok(CheckResult {
+                    success: false,
+                    error: Some(CheckResultError {
+                        error_type: CheckResultErrorType::Output,
+                        message: "Mismatched output file size".to_string(),
+                        missing: None,
+                        mismatched: None,
+                    }),
+                    metadata,
+                });
+            }
+
+            let mut output: fs::File = match fs::File::open(out_file).await {
+                | Ok(f) => f,
+                | Err(e) => {
+                    return Err(CheckError::OutFileNotRead {
+                        path: out_file.clone(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            let hashes: HashMap<usize, (HashAlgorithm, &str)> = manifest
+                .iter()
+                .flat_map(|m| &m.chunks)
+                .filter_map(|c| c.hash().map(|h| (c.index, h)))
+                .collect();
+
+            let mut output_buffer: Vec<u8> = vec![0; COMPARE_BUFFER_SIZE];
+            let mut chunk_buffer: Vec<u8> = vec![0; COMPARE_BUFFER_SIZE];
+            let mut differing: Vec<usize> = Vec::new();
+
+            for (i, size) in sizes.iter().enumerate() {
+                if let Some(ref c) = self.control {
+                    if !c.wait_async().await {
+                        return Err(CheckError::Cancelled);
+                    }
+                }
+
+                let chunk_path: PathBuf = in_dir.join(self.namer.name(i));
+
+                let mut hasher: Option<Hasher> =
+                    hashes.get(&i).map(|(a, _)| a.hasher());
+
+                let mut chunk: Option<fs::File> = match hasher {
+                    | Some(_) => None,
+                    | None => match fs::File::open(&chunk_path).await {
+                        | Ok(f) => Some(f),
+                        | Err(e) => {
+                            return Err(CheckError::InFileNotOpened {
+                                path: chunk_path.into(),
+                                source: e.into(),
+                            });
+                        },
+                    },
+                };
+
+                let mut remaining: usize = *size;
+                let mut equal: bool = true;
+
+                while remaining > 0 {
+                    let n: usize = remaining.min(COMPARE_BUFFER_SIZE);
+
+                    if let Err(e) =
+                        output.read_exact(&mut output_buffer[..n]).await
+                    {
+                        return Err(CheckError::OutFileNotRead {
+                            path: out_file.clone(),
+                            source: e.into(),
+                        });
+                    }
+
+                    if let Some(ref mut h) = hasher {
+                        h.update(&output_buffer[..n]);
+                    }
+
+                    if let Some(ref mut f) = chunk {
+                        if let Err(e) =
+                            f.read_exact(&mut chunk_buffer[..n]).await
+                        {
+                            return Err(CheckError::InFileNotRead {
+                                path: chunk_path.into(),
+                                source: e.into(),
+                            });
+                        }
+
+                        equal &= output_buffer[..n] == chunk_buffer[..n];
+                    }
+
+                    remaining -= n;
+                }
+
+                if let (Some(h), Some((_, expected))) = (hasher, hashes.get(&i))
+                {
+                    equal = h.finalize_hex().eq_ignore_ascii_case(expected);
+                }
+
+                if !equal {
+                    differing.push(i);
+                }
+            }
+
+            if !differing.is_empty() {
+                return Ok(CheckResult {
+                    success: false,
+                    error: Some(CheckResultError {
+                        error_type: CheckResultErrorType::Output,
+                        message: "Mismatched output file section(s)"
+                            .to_string(),
+                        missing: None,
+                        mismatched: Some(differing),
+                    }),
+                    metadata,
+                });
+            }
+        }
+
+        Ok(CheckResult { success: true, error: None, metadata })
     }
 }
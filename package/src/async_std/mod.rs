@@ -1,5 +1,30 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_std::task;
+
+use crate::naming::ChunkNamer;
+
 pub mod split;
 
 pub mod check;
 
 pub mod merge;
+
+/// Scan the names of the chunks in the directory on a blocking thread, as
+/// the whole directory may be read.
+pub(crate) async fn scan(
+    namer: &Arc<dyn ChunkNamer>,
+    dir: &Path,
+) {
+    if !namer.uses_content() {
+        return;
+    }
+
+    let namer: Arc<dyn ChunkNamer> = namer.clone();
+    let dir: PathBuf = dir.to_path_buf();
+
+    task::spawn_blocking(move || namer.scan(&dir)).await;
+}
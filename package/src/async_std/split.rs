@@ -1,10 +1,27 @@
+use std::{collections::VecDeque, sync::Arc};
+
 use async_std::{
     fs::{self, File},
-    io::{self, BufWriter, ReadExt as _, WriteExt as _},
+    io::{self, BufWriter, Read as AsyncRead, ReadExt as _, WriteExt as _},
     path::{Path, PathBuf},
+    task,
 };
 
-use crate::split::{Split, SplitError, SplitResult};
+#[cfg(feature = "manifest")]
+use crate::manifest::{MANIFEST_FILE_NAME, Manifest};
+use crate::{
+    advise,
+    check::COMPARE_BUFFER_SIZE,
+    clock::SystemClock,
+    control::{Control, ControlGuard},
+    device,
+    durability::Durability,
+    follow::Follow,
+    hash::{self, HashAlgorithm, Hasher},
+    plan,
+    split::{InputStamp, Split, SplitError, SplitResult},
+    throttle::Throttle,
+};
 
 /// Trait for running the split process.
 pub trait SplitAsyncExt {
@@ -12,6 +29,14 @@ pub trait SplitAsyncExt {
     fn run_async(
         &self
     ) -> impl std::future::Future<Output = Result<SplitResult, SplitError>> + Send;
+
+    /// Run the split process asynchronously with a reader as the input.
+    ///
+    /// See [`Split::run_reader`] for more details.
+    fn run_reader_async<R: AsyncRead + Unpin + Send>(
+        &self,
+        reader: R,
+    ) -> impl std::future::Future<Output = Result<SplitResult, SplitError>> + Send;
 }
 
 impl SplitAsyncExt for Split {
@@ -35,97 +60,484 @@ impl SplitAsyncExt for Split {
             | None => return Err(SplitError::InFileNotSet),
         };
 
-        let out_dir: &Path = match self.out_dir {
-            | Some(ref p) => {
-                let p: &Path = p.as_ref();
+        let out_dir: &Path = prepare_out_dir(self).await?;
 
-                // if out_dir not exists
-                if !p.exists().await {
-                    if fs::create_dir_all(p).await.is_err() {
-                        return Err(SplitError::OutDirNotDir);
-                    }
-                } else {
-                    // if out_dir not a directory
-                    if p.is_file().await {
-                        return Err(SplitError::OutDirNotDir);
-                    }
-                }
-
-                p
-            },
-            | None => return Err(SplitError::OutDirNotSet),
-        };
+        let _control: Option<ControlGuard> =
+            self.control.as_ref().map(Control::run);
 
         let chunk_size: usize = self.chunk_size;
 
-        let buffer_capacity: usize = chunk_size.min(self.cap_max);
-
         let input: fs::File =
             match fs::OpenOptions::new().read(true).open(in_file).await {
                 | Ok(f) => f,
-                | Err(_) => return Err(SplitError::InFileNotOpened),
+                | Err(e) => {
+                    return Err(SplitError::InFileNotOpened {
+                        path: in_file.to_path_buf().into(),
+                        source: e.into(),
+                    });
+                },
             };
 
-        let file_size: usize = match input.metadata().await {
-            | Ok(m) => m.len() as usize,
-            | Err(_) => return Err(SplitError::InFileNotRead),
+        if self.cache_hints {
+            advise::sequential(&input);
+        }
+
+        let metadata: fs::Metadata = match input.metadata().await {
+            | Ok(m) => m,
+            | Err(e) => {
+                return Err(SplitError::InFileNotRead {
+                    path: in_file.to_path_buf().into(),
+                    source: e.into(),
+                });
+            },
         };
 
-        let mut reader: io::BufReader<fs::File> =
-            io::BufReader::with_capacity(buffer_capacity, input);
+        let file_size: usize = metadata.len() as usize;
+
+        // refuse to create more chunks than allowed
+        if let Some(max) = self.max_chunks {
+            let total: usize = plan::plan_split(file_size, chunk_size)
+                .map_or(0, |p| p.total_chunks());
+
+            if total > max {
+                return Err(SplitError::TooManyChunks);
+            }
+        }
+
+        // refuse the names before any chunk is written
+        self.check_names(file_size)?;
+        self.ensure_space(out_dir, file_size)?;
+
+        // link the input as the only chunk instead of copying it
+        if self.passthrough
+            && !self.hash
+            && self.hash_algorithm.is_none()
+            && self.follow.is_none()
+            && !self.namer.uses_content()
+            && file_size > 0
+            && file_size <= chunk_size
+            && device::same_device(in_file, out_dir)
+        {
+            let output_path: PathBuf = out_dir.join(self.namer.name(0));
+
+            let _ = fs::remove_file(&output_path).await;
+
+            if fs::hard_link(in_file, &output_path).await.is_ok() {
+                // describe the chunks next to them
+                #[cfg(feature = "manifest")]
+                if self.manifest {
+                    let manifest: Manifest =
+                        self.to_manifest(file_size, &[file_size], None);
+
+                    let manifest_path: PathBuf =
+                        out_dir.join(MANIFEST_FILE_NAME);
+
+                    if let Err(e) =
+                        fs::write(&manifest_path, manifest.to_json()).await
+                    {
+                        return Err(SplitError::ManifestNotWritten {
+                            path: manifest_path.into(),
+                            source: e.into(),
+                        });
+                    }
+                }
+
+                if let Some(ref c) = self.control {
+                    c.record(0, file_size as u64);
+                }
+
+                persist(self, out_dir, &[output_path.clone().into()]).await?;
 
-        let mut buffer: Vec<u8> = vec![0; chunk_size];
+                return Ok(SplitResult {
+                    file_size,
+                    total_chunks: 1,
+                    hashes: None,
+                    file_hash: None,
+                    chunk_paths: vec![output_path.into()],
+                    chunk_sizes: vec![file_size],
+                });
+            }
+        }
 
-        let mut total_chunks: usize = 0;
+        let split_result: SplitResult = split_from(
+            self,
+            input,
+            out_dir,
+            Some(file_size),
+            self.follow.as_ref(),
+        )
+        .await?;
 
-        loop {
-            let mut offset: usize = 0;
+        persist(self, out_dir, &split_result.chunk_paths).await?;
 
-            while offset < chunk_size {
-                let bytes_read: usize =
-                    match reader.read(&mut buffer[offset..]).await {
-                        | Ok(n) => n,
-                        | Err(_) => return Err(SplitError::InFileNotRead),
-                    };
+        // the chunks may mix old and new content
+        if self.guard_input && self.follow.is_none() {
+            let changed: bool = match fs::metadata(in_file).await {
+                | Ok(m) => InputStamp::new(&m) != InputStamp::new(&metadata),
+                | Err(e) => {
+                    return Err(SplitError::InFileNotRead {
+                        path: in_file.to_path_buf().into(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if changed {
+                return Err(SplitError::InFileChanged);
+            }
 
-                if bytes_read == 0 {
-                    break;
+            if let (Some(algorithm), Some(expected)) =
+                (self.hash_algorithm, split_result.file_hash.as_ref())
+            {
+                match hash_file(in_file, algorithm, self.cap_max).await {
+                    | Ok(h) if h == *expected => {},
+                    | Ok(_) => return Err(SplitError::InFileChanged),
+                    | Err(e) => {
+                        return Err(SplitError::InFileNotRead {
+                            path: in_file.to_path_buf().into(),
+                            source: e.into(),
+                        });
+                    },
                 }
+            }
+        }
+
+        // the input is not read again
+        if self.cache_hints {
+            if let Ok(f) = fs::File::open(in_file).await {
+                advise::dont_need(&f);
+            }
+        }
+
+        Ok(split_result)
+    }
+
+    async fn run_reader_async<R: AsyncRead + Unpin + Send>(
+        &self,
+        reader: R,
+    ) -> Result<SplitResult, SplitError> {
+        let out_dir: &Path = prepare_out_dir(self).await?;
+
+        let _control: Option<ControlGuard> =
+            self.control.as_ref().map(Control::run);
+
+        let split_result: SplitResult =
+            split_from(self, reader, out_dir, None, None).await?;
+
+        persist(self, out_dir, &split_result.chunk_paths).await?;
+
+        Ok(split_result)
+    }
+}
+
+/// Get the output directory, creating it if it does not exist.
+async fn prepare_out_dir(process: &Split) -> Result<&Path, SplitError> {
+    process.check_namer()?;
+
+    match process.out_dir {
+        | Some(ref p) => {
+            let p: &Path = p.as_ref();
+
+            // if out_dir not exists
+            if !p.exists().await {
+                if let Err(e) = fs::create_dir_all(p).await {
+                    return Err(SplitError::OutDirNotCreated {
+                        path: p.to_path_buf().into(),
+                        source: e.into(),
+                    });
+                }
+            } else {
+                // if out_dir not a directory
+                if p.is_file().await {
+                    return Err(SplitError::OutDirNotDir);
+                }
+
+                process.handle_entries(p.as_ref())?;
+            }
+
+            Ok(p)
+        },
+        | None => Err(SplitError::OutDirNotSet),
+    }
+}
+
+/// Persist the chunks and the manifest as set by the durability.
+async fn persist(
+    process: &Split,
+    out_dir: &Path,
+    chunk_paths: &[std::path::PathBuf],
+) -> Result<(), SplitError> {
+    if process.durability == Durability::Flush {
+        return Ok(());
+    }
+
+    let process: Split = process.clone();
+    let dir: std::path::PathBuf = out_dir.to_path_buf().into();
+    let chunk_paths: Vec<std::path::PathBuf> = chunk_paths.to_vec();
 
-                offset += bytes_read;
+    task::spawn_blocking(move || process.persist(&dir, &chunk_paths)).await
+}
+
+/// Split the input into chunks.
+///
+/// The `file_size` is reported unless the input is followed or unknown, then
+/// the size read is reported instead.
+async fn split_from<R: AsyncRead + Unpin + Send>(
+    process: &Split,
+    input: R,
+    out_dir: &Path,
+    file_size: Option<usize>,
+    follow: Option<&Follow>,
+) -> Result<SplitResult, SplitError> {
+    let chunk_size: usize = process.chunk_size;
+
+    let buffer_capacity: usize = chunk_size.min(process.cap_max);
+
+    let mut reader: io::BufReader<R> =
+        io::BufReader::with_capacity(buffer_capacity, input);
+
+    let mut buffer: Vec<u8> = vec![0; chunk_size];
+
+    let mut total_chunks: usize = 0;
+    let mut bytes_done: usize = 0;
+    let mut sizes: Vec<usize> = Vec::new();
+    let mut paths: Vec<std::path::PathBuf> = Vec::new();
+    let mut throttle: Option<Throttle> =
+        process.throttle.map(|r| Throttle::new(r, Arc::new(SystemClock)));
+    let mut file_hasher: Option<Hasher> =
+        process.hash_algorithm.map(|a| a.hasher());
+
+    let workers: usize =
+        process.hash_workers.unwrap_or_else(hash::default_workers).max(1);
+    let mut pending: VecDeque<task::JoinHandle<String>> = VecDeque::new();
+    let mut hashes: Vec<String> = Vec::new();
+
+    loop {
+        if let Some(ref c) = process.control {
+            if !c.wait_async().await {
+                return Err(SplitError::Cancelled);
             }
+        }
 
-            if offset == 0 {
-                break;
+        let mut offset: usize = 0;
+
+        while offset < chunk_size {
+            // checked while the chunk is filled, not only after it
+            if process.control.as_ref().is_some_and(Control::is_cancelled) {
+                return Err(SplitError::Cancelled);
             }
 
-            let output_path: PathBuf = out_dir.join(total_chunks.to_string());
+            // checked before reading, so no data written before the end
+            // is missed
+            let ended: bool = follow.is_none_or(Follow::is_ended);
+
+            let bytes_read: usize =
+                match reader.read(&mut buffer[offset..]).await {
+                    | Ok(n) => n,
+                    | Err(e) => {
+                        return Err(SplitError::InFileNotRead {
+                            path: process.in_file.clone().unwrap_or_default(),
+                            source: e.into(),
+                        });
+                    },
+                };
+
+            if bytes_read == 0 {
+                let f: &Follow = match follow {
+                    | Some(f) if !ended => f,
+                    | _ => break,
+                };
+
+                if process.control.as_ref().is_some_and(Control::is_cancelled) {
+                    return Err(SplitError::Cancelled);
+                }
+
+                task::sleep(f.interval).await;
+
+                continue;
+            }
 
+            offset += bytes_read;
+        }
+
+        // an empty file produces no chunk unless `empty_chunk` is set
+        if offset == 0 && (total_chunks > 0 || !process.empty_chunk) {
+            break;
+        }
+
+        // the size of a followed file or a reader is not known upfront
+        if process.max_chunks.is_some_and(|max| total_chunks >= max) {
+            return Err(SplitError::TooManyChunks);
+        }
+
+        bytes_done += offset;
+
+        if let Some(ref mut h) = file_hasher {
+            h.update(&buffer[..offset]);
+        }
+
+        sizes.push(offset);
+
+        let output_path: PathBuf =
+            out_dir.join(process.chunk_name(total_chunks, &buffer[..offset])?);
+
+        // keep an identical chunk from a prior run
+        if !(process.dedup
+            && is_duplicate(&output_path, &buffer[..offset]).await)
+        {
             let output: File = match fs::OpenOptions::new()
                 .create(true)
                 .truncate(true)
                 .write(true)
-                .open(output_path)
+                .open(&output_path)
                 .await
             {
                 | Ok(f) => f,
-                | Err(_) => return Err(SplitError::OutFileNotOpened),
+                | Err(e) => {
+                    return Err(SplitError::OutFileNotOpened {
+                        path: output_path.into(),
+                        source: e.into(),
+                    });
+                },
             };
 
             let mut writer: BufWriter<File> =
                 io::BufWriter::with_capacity(buffer_capacity, output);
 
-            if writer.write_all(&buffer[..offset]).await.is_err() {
-                return Err(SplitError::OutFileNotWritten);
+            if let Err(e) = writer.write_all(&buffer[..offset]).await {
+                return Err(SplitError::OutFileNotWritten {
+                    path: output_path.into(),
+                    source: e.into(),
+                });
+            }
+
+            if let Err(e) = writer.flush().await {
+                return Err(SplitError::OutFileNotWritten {
+                    path: output_path.into(),
+                    source: e.into(),
+                });
             }
+        }
+
+        paths.push(output_path.into());
+
+        if let Some(ref mut t) = throttle {
+            task::sleep(t.take(offset as u64)).await;
+        }
 
-            if writer.flush().await.is_err() {
-                return Err(SplitError::OutFileNotWritten);
+        if process.hash {
+            if pending.len() >= workers {
+                if let Some(handle) = pending.pop_front() {
+                    hashes.push(handle.await);
+                }
             }
 
-            total_chunks += 1;
+            let chunk: Vec<u8> = buffer[..offset].to_vec();
+            let algorithm: HashAlgorithm =
+                process.hash_algorithm.unwrap_or_default();
+
+            pending
+                .push_back(task::spawn_blocking(move || algorithm.hex(&chunk)));
+        }
+
+        if let Some(ref c) = process.control {
+            c.record(total_chunks, offset as u64);
         }
 
-        Ok(SplitResult { file_size, total_chunks })
+        total_chunks += 1;
     }
+
+    while let Some(handle) = pending.pop_front() {
+        hashes.push(handle.await);
+    }
+
+    let hashes: Option<Vec<String>> = process.hash.then_some(hashes);
+
+    // a followed file has grown since it was opened
+    let file_size: usize = match file_size {
+        | Some(size) if follow.is_none() => size,
+        | _ => bytes_done,
+    };
+
+    // describe the chunks next to them
+    #[cfg(feature = "manifest")]
+    if process.manifest {
+        let manifest: Manifest =
+            process.to_manifest(file_size, &sizes, hashes.as_deref());
+
+        let manifest_path: PathBuf = out_dir.join(MANIFEST_FILE_NAME);
+
+        if let Err(e) = fs::write(&manifest_path, manifest.to_json()).await {
+            return Err(SplitError::ManifestNotWritten {
+                path: manifest_path.into(),
+                source: e.into(),
+            });
+        }
+    }
+
+    Ok(SplitResult {
+        file_size,
+        total_chunks,
+        hashes,
+        file_hash: file_hasher.map(Hasher::finalize_hex),
+        chunk_paths: paths,
+        chunk_sizes: sizes,
+    })
+}
+
+/// Check whether the chunk at the path has the same content.
+///
+/// The sizes are compared first, so a changed chunk is usually detected
+/// without reading it.
+async fn is_duplicate(
+    path: &Path,
+    content: &[u8],
+) -> bool {
+    if !fs::metadata(path).await.is_ok_and(|m| m.len() == content.len() as u64)
+    {
+        return false;
+    }
+
+    let mut chunk: File = match File::open(path).await {
+        | Ok(f) => f,
+        | Err(_) => return false,
+    };
+
+    // compared a block at a time, so a huge chunk is not held in memory
+    let mut buffer: Vec<u8> = vec![0; COMPARE_BUFFER_SIZE];
+
+    for expected in content.chunks(COMPARE_BUFFER_SIZE) {
+        let n: usize = expected.len();
+
+        if chunk.read_exact(&mut buffer[..n]).await.is_err()
+            || buffer[..n] != *expected
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Hash the whole file in hex.
+async fn hash_file(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    buffer_capacity: usize,
+) -> io::Result<String> {
+    let mut input: File = File::open(path).await?;
+    let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+    let mut hasher: Hasher = algorithm.hasher();
+
+    loop {
+        let n: usize = input.read(&mut buffer).await?;
+
+        if n == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(hasher.finalize_hex())
 }
@@ -0,0 +1,369 @@
+use std::{
+    panic,
+    thread::{self, ScopedJoinHandle},
+};
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c,
+    0x1f83d9ab, 0x5be0cd19,
+];
+
+const MSG_PERMUTATION: [usize; 16] =
+    [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+
+/// The minimum size of the data hashed by each thread, smaller data is not
+/// worth a thread.
+const PARALLEL_MIN_LEN: usize = 128 * CHUNK_LEN;
+
+/// Hash data with up to `threads` threads and get the digest.
+///
+/// The data is split into subtrees of whole chunks, which are hashed in
+/// parallel and merged as the incremental hasher would.
+pub(crate) fn hash_parallel(
+    data: &[u8],
+    threads: usize,
+) -> [u8; 32] {
+    if threads <= 1 || data.len() < 2 * PARALLEL_MIN_LEN {
+        let mut hasher: Blake3 = Blake3::new();
+
+        hasher.update(data);
+
+        return hasher.finalize();
+    }
+
+    subtree(data, 0, threads).root_digest()
+}
+
+/// Get the output of the subtree of the data starting at the chunk
+/// `counter`.
+fn subtree(
+    data: &[u8],
+    counter: u64,
+    threads: usize,
+) -> Output {
+    if data.len() <= CHUNK_LEN {
+        let mut chunk: ChunkState = ChunkState::new(counter);
+
+        chunk.update(data);
+
+        return chunk.output();
+    }
+
+    // the left subtree holds the largest power of two of chunks leaving some
+    // data to the right
+    let chunks: usize = data.len().div_ceil(CHUNK_LEN);
+    let left_chunks: usize =
+        1 << (usize::BITS - 1 - (chunks - 1).leading_zeros());
+
+    let (left, right) = data.split_at(left_chunks * CHUNK_LEN);
+    let right_counter: u64 = counter + left_chunks as u64;
+
+    let (left, right): (Output, Output) =
+        if threads > 1 && data.len() >= 2 * PARALLEL_MIN_LEN {
+            thread::scope(|s| {
+                let handle: ScopedJoinHandle<'_, Output> =
+                    s.spawn(|| subtree(left, counter, threads / 2));
+
+                let right: Output =
+                    subtree(right, right_counter, threads - threads / 2);
+
+                match handle.join() {
+                    | Ok(left) => (left, right),
+                    | Err(e) => panic::resume_unwind(e),
+                }
+            })
+        } else {
+            (subtree(left, counter, 1), subtree(right, right_counter, 1))
+        };
+
+    parent_output(&left.chaining_value(), &right.chaining_value())
+}
+
+/// Incremental BLAKE3 hasher.
+#[derive(Debug, Clone)]
+pub(crate) struct Blake3 {
+    chunk: ChunkState,
+    cv_stack: Vec<[u32; 8]>,
+}
+
+impl Blake3 {
+    /// Create a new hasher.
+    pub(crate) fn new() -> Self {
+        Self { chunk: ChunkState::new(0), cv_stack: Vec::new() }
+    }
+
+    /// Feed data into the hasher.
+    pub(crate) fn update(
+        &mut self,
+        mut data: &[u8],
+    ) {
+        while !data.is_empty() {
+            // the chunk is only completed once more data follows, as the
+            // last chunk is finalized as the root
+            if self.chunk.len() == CHUNK_LEN {
+                let cv: [u32; 8] = self.chunk.output().chaining_value();
+                let total_chunks: u64 = self.chunk.counter + 1;
+
+                self.push_chunk(cv, total_chunks);
+                self.chunk = ChunkState::new(total_chunks);
+            }
+
+            let take: usize = (CHUNK_LEN - self.chunk.len()).min(data.len());
+
+            self.chunk.update(&data[..take]);
+            data = &data[take..];
+        }
+    }
+
+    /// Complete the hashing and get the digest.
+    pub(crate) fn finalize(self) -> [u8; 32] {
+        let mut output: Output = self.chunk.output();
+
+        for cv in self.cv_stack.iter().rev() {
+            output = parent_output(cv, &output.chaining_value());
+        }
+
+        output.root_digest()
+    }
+
+    /// Merge the completed subtrees, one per trailing zero of the count.
+    fn push_chunk(
+        &mut self,
+        mut cv: [u32; 8],
+        mut total_chunks: u64,
+    ) {
+        while total_chunks & 1 == 0 {
+            if let Some(left) = self.cv_stack.pop() {
+                cv = parent_output(&left, &cv).chaining_value();
+            }
+
+            total_chunks >>= 1;
+        }
+
+        self.cv_stack.push(cv);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ChunkState {
+    cv: [u32; 8],
+    counter: u64,
+    block: [u8; BLOCK_LEN],
+    block_len: usize,
+    blocks_compressed: usize,
+}
+
+impl ChunkState {
+    fn new(counter: u64) -> Self {
+        Self {
+            cv: IV,
+            counter,
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 { CHUNK_START } else { 0 }
+    }
+
+    fn update(
+        &mut self,
+        mut data: &[u8],
+    ) {
+        while !data.is_empty() {
+            if self.block_len == BLOCK_LEN {
+                let words: [u32; 16] = compress(
+                    &self.cv,
+                    &to_words(&self.block),
+                    self.counter,
+                    BLOCK_LEN as u32,
+                    self.start_flag(),
+                );
+
+                self.cv.copy_from_slice(&words[..8]);
+                self.blocks_compressed += 1;
+                self.block = [0; BLOCK_LEN];
+                self.block_len = 0;
+            }
+
+            let take: usize = (BLOCK_LEN - self.block_len).min(data.len());
+
+            self.block[self.block_len..self.block_len + take]
+                .copy_from_slice(&data[..take]);
+            self.block_len += take;
+            data = &data[take..];
+        }
+    }
+
+    fn output(&self) -> Output {
+        Output {
+            cv: self.cv,
+            block: to_words(&self.block),
+            counter: self.counter,
+            block_len: self.block_len as u32,
+            flags: self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+/// Input of the last compression of a node.
+struct Output {
+    cv: [u32; 8],
+    block: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32; 8] {
+        let words: [u32; 16] = compress(
+            &self.cv,
+            &self.block,
+            self.counter,
+            self.block_len,
+            self.flags,
+        );
+
+        let mut cv: [u32; 8] = [0; 8];
+        cv.copy_from_slice(&words[..8]);
+        cv
+    }
+
+    /// Compress the node as the root and get the digest.
+    fn root_digest(&self) -> [u8; 32] {
+        let words: [u32; 16] = compress(
+            &self.cv,
+            &self.block,
+            0,
+            self.block_len,
+            self.flags | ROOT,
+        );
+
+        let mut digest: [u8; 32] = [0; 32];
+
+        for (i, word) in words[..8].iter().enumerate() {
+            digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+        }
+
+        digest
+    }
+}
+
+fn parent_output(
+    left: &[u32; 8],
+    right: &[u32; 8],
+) -> Output {
+    let mut block: [u32; 16] = [0; 16];
+    block[..8].copy_from_slice(left);
+    block[8..].copy_from_slice(right);
+
+    Output {
+        cv: IV,
+        block,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: PARENT,
+    }
+}
+
+fn to_words(block: &[u8; BLOCK_LEN]) -> [u32; 16] {
+    let mut words: [u32; 16] = [0; 16];
+
+    for (i, bytes) in block.chunks_exact(4).enumerate() {
+        words[i] = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+
+    words
+}
+
+fn g(
+    state: &mut [u32; 16],
+    [a, b, c, d]: [usize; 4],
+    x: u32,
+    y: u32,
+) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(x);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(y);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+fn round(
+    state: &mut [u32; 16],
+    m: &[u32; 16],
+) {
+    // the columns
+    g(state, [0, 4, 8, 12], m[0], m[1]);
+    g(state, [1, 5, 9, 13], m[2], m[3]);
+    g(state, [2, 6, 10, 14], m[4], m[5]);
+    g(state, [3, 7, 11, 15], m[6], m[7]);
+
+    // the diagonals
+    g(state, [0, 5, 10, 15], m[8], m[9]);
+    g(state, [1, 6, 11, 12], m[10], m[11]);
+    g(state, [2, 7, 8, 13], m[12], m[13]);
+    g(state, [3, 4, 9, 14], m[14], m[15]);
+}
+
+fn compress(
+    cv: &[u32; 8],
+    block: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
+    let mut state: [u32; 16] = [
+        cv[0],
+        cv[1],
+        cv[2],
+        cv[3],
+        cv[4],
+        cv[5],
+        cv[6],
+        cv[7],
+        IV[0],
+        IV[1],
+        IV[2],
+        IV[3],
+        counter as u32,
+        (counter >> 32) as u32,
+        block_len,
+        flags,
+    ];
+
+    let mut m: [u32; 16] = *block;
+
+    for i in 0..7 {
+        round(&mut state, &m);
+
+        if i < 6 {
+            m = MSG_PERMUTATION.map(|p| m[p]);
+        }
+    }
+
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= cv[i];
+    }
+
+    state
+}
@@ -1,6 +1,24 @@
 use std::{
-    fs,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt, fs,
+    io::{ErrorKind, Read as _},
     path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "manifest")]
+use crate::manifest::MANIFEST_SIZE_MAX_DEFAULT;
+use crate::{
+    control::{Control, ControlGuard},
+    error::IoError,
+    hash::{HashAlgorithm, Hasher},
+    lock::{DirLock, LOCK_FILE_NAME},
+    manifest::Manifest,
+    naming::{ChunkNamer, Plain},
 };
 
 /// Run asynchronously with `async_std` feature.
@@ -29,13 +47,23 @@ pub mod tokio {
     pub use crate::tokio::check::CheckAsyncExt;
 }
 
+/// The size of the buffers comparing the merged file with the chunks.
+pub(crate) const COMPARE_BUFFER_SIZE: usize = 64 * 1024;
+
 /// Error type of the result from the check process.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum CheckResultErrorType {
     /// Some of the chunks are missing to merge the file.
     Missing,
-    /// The actual file size is not equal the input file size.
+    /// The actual file size is not equal the input file size, or the size
+    /// of some of the chunks is not equal the manifest.
     Size,
+    /// The hash of some of the chunks is not equal the manifest.
+    Hash,
+    /// The merged file is missing or not equal the chunks.
+    Output,
 }
 
 impl CheckResultErrorType {
@@ -44,6 +72,8 @@ impl CheckResultErrorType {
         match code.as_ref() {
             | "missing" => Some(Self::Missing),
             | "size" => Some(Self::Size),
+            | "hash" => Some(Self::Hash),
+            | "output" => Some(Self::Output),
             | _ => None,
         }
     }
@@ -53,6 +83,8 @@ impl CheckResultErrorType {
         match self {
             | Self::Missing => "missing",
             | Self::Size => "size",
+            | Self::Hash => "hash",
+            | Self::Output => "output",
         }
     }
 
@@ -64,6 +96,7 @@ impl CheckResultErrorType {
 
 /// Error of the result from the check process.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CheckResultError {
     /// Type of error of the check.
     pub error_type: CheckResultErrorType,
@@ -71,26 +104,41 @@ pub struct CheckResultError {
     pub message: String,
     /// Missing chunk(s) to merge the file.
     pub missing: Option<Vec<usize>>,
+    /// Chunk(s) with a size or hash not equal the manifest, or not equal
+    /// their section of the merged file.
+    pub mismatched: Option<Vec<usize>>,
 }
 
 /// Result of the check process.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CheckResult {
     /// Successful / Failed check.
     pub success: bool,
     /// Error details of the check.
     pub error: Option<CheckResultError>,
+    /// Metadata of the chunk set from the manifest, empty without one.
+    pub metadata: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum CheckError {
     InDirNotFound,
     InDirNotDir,
     InDirNotSet,
-    InFileNotOpened,
-    InFileNotRead,
+    InFileNotOpened { path: PathBuf, source: IoError },
+    InFileNotRead { path: PathBuf, source: IoError },
     FileSizeNotSet,
     TotalChunksNotSet,
+    TooManyChunks,
+    Locked,
+    LockNotCreated { path: PathBuf, source: IoError },
+    ManifestNotRead { path: PathBuf, source: IoError },
+    ManifestTooLarge,
+    Cancelled,
+    OutFileNotRead { path: PathBuf, source: IoError },
 }
 
 impl CheckError {
@@ -100,10 +148,17 @@ impl CheckError {
             | Self::InDirNotFound => "in_dir_not_found",
             | Self::InDirNotDir => "in_dir_not_dir",
             | Self::InDirNotSet => "in_dir_not_set",
-            | Self::InFileNotOpened => "in_file_not_opened",
-            | Self::InFileNotRead => "in_file_not_read",
+            | Self::InFileNotOpened { .. } => "in_file_not_opened",
+            | Self::InFileNotRead { .. } => "in_file_not_read",
             | Self::FileSizeNotSet => "file_size_not_set",
             | Self::TotalChunksNotSet => "total_chunks_not_set",
+            | Self::TooManyChunks => "too_many_chunks",
+            | Self::Locked => "locked",
+            | Self::LockNotCreated { .. } => "lock_not_created",
+            | Self::ManifestNotRead { .. } => "manifest_not_read",
+            | Self::ManifestTooLarge => "manifest_too_large",
+            | Self::Cancelled => "cancelled",
+            | Self::OutFileNotRead { .. } => "out_file_not_read",
         }
     }
 
@@ -118,10 +173,29 @@ impl CheckError {
             | Self::InDirNotFound => "The input directory not found.",
             | Self::InDirNotDir => "The input directory is not a directory.",
             | Self::InDirNotSet => "The input directory is not set.",
-            | Self::InFileNotOpened => "The input file could not be opened.",
-            | Self::InFileNotRead => "The input file could not be read.",
+            | Self::InFileNotOpened { .. } => {
+                "The input file could not be opened."
+            },
+            | Self::InFileNotRead { .. } => "The input file could not be read.",
             | Self::FileSizeNotSet => "The `file_size` is not set.",
             | Self::TotalChunksNotSet => "The `total_chunks` is not set.",
+            | Self::TooManyChunks => "The number of chunks exceeds the limit.",
+            | Self::Locked => {
+                "The input directory is locked by another process."
+            },
+            | Self::LockNotCreated { .. } => {
+                "The lock file could not be created."
+            },
+            | Self::ManifestNotRead { .. } => {
+                "The manifest file could not be read."
+            },
+            | Self::ManifestTooLarge => {
+                "The manifest file exceeds the size limit."
+            },
+            | Self::Cancelled => "The process was cancelled.",
+            | Self::OutFileNotRead { .. } => {
+                "The output file could not be read."
+            },
         }
     }
 
@@ -129,6 +203,47 @@ impl CheckError {
     pub fn to_message(&self) -> String {
         self.as_message().to_string()
     }
+
+    /// Get the path involved in the error, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.io().map(|(path, _)| path)
+    }
+
+    /// Get the path and the I/O error of the error, if any.
+    fn io(&self) -> Option<(&Path, &IoError)> {
+        match self {
+            | Self::InFileNotOpened { path, source }
+            | Self::InFileNotRead { path, source }
+            | Self::LockNotCreated { path, source }
+            | Self::ManifestNotRead { path, source }
+            | Self::OutFileNotRead { path, source } => Some((path, source)),
+            | _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self.io() {
+            | Some((path, source)) => write!(
+                f,
+                "{} ({}: {})",
+                self.as_message(),
+                path.display(),
+                source
+            ),
+            | None => f.write_str(self.as_message()),
+        }
+    }
+}
+
+impl Error for CheckError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.io().map(|(_, source)| source.as_ref() as &(dyn Error + 'static))
+    }
 }
 
 /// Process to check the file integrity.
@@ -156,12 +271,36 @@ pub struct Check {
     pub in_dir: Option<PathBuf>,
     pub file_size: Option<usize>,
     pub total_chunks: Option<usize>,
+    pub namer: Arc<dyn ChunkNamer>,
+    pub max_chunks: Option<usize>,
+    pub lock: bool,
+    #[cfg(feature = "manifest")]
+    pub manifest: Option<PathBuf>,
+    pub manifest_data: Option<Manifest>,
+    #[cfg(feature = "manifest")]
+    pub max_manifest_size: u64,
+    pub out_file: Option<PathBuf>,
+    pub control: Option<Control>,
 }
 
 impl Check {
     /// Create a new check process.
     pub fn new() -> Self {
-        Self { in_dir: None, file_size: None, total_chunks: None }
+        Self {
+            in_dir: None,
+            file_size: None,
+            total_chunks: None,
+            namer: Arc::new(Plain),
+            max_chunks: None,
+            lock: false,
+            #[cfg(feature = "manifest")]
+            manifest: None,
+            manifest_data: None,
+            #[cfg(feature = "manifest")]
+            max_manifest_size: MANIFEST_SIZE_MAX_DEFAULT,
+            out_file: None,
+            control: None,
+        }
     }
 
     /// Create a new check process from an existing one.
@@ -196,6 +335,141 @@ impl Check {
         self
     }
 
+    /// Set the naming convention of the chunks.
+    ///
+    /// By default, the chunks are named by their index with [`Plain`].
+    pub fn namer<N: ChunkNamer + 'static>(
+        mut self,
+        namer: N,
+    ) -> Self {
+        self.namer = Arc::new(namer);
+        self
+    }
+
+    /// Set the maximum number of chunks to check.
+    ///
+    /// By default, the number of chunks is unlimited. When `total_chunks`
+    /// exceeds it, [`CheckError::TooManyChunks`] is returned, which protects
+    /// against untrusted values of `total_chunks`.
+    pub fn max_chunks(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.max_chunks = Some(chunks);
+        self
+    }
+
+    /// Set whether the input directory is locked during the check process.
+    ///
+    /// When enabled, a [`DirLock`] is held while running, and
+    /// [`CheckError::Locked`] is returned if another process holds it, e.g. an
+    /// uploader still writing chunks.
+    pub fn lock(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.lock = enabled;
+        self
+    }
+
+    #[cfg(feature = "manifest")]
+    /// Set the manifest to verify the chunks against.
+    ///
+    /// When set, the size of each chunk is compared to the size recorded in
+    /// the [`Manifest`], so the chunks may vary in size, and the check fails
+    /// with [`CheckResultErrorType::Size`] on a mismatch. The hash of each
+    /// chunk recorded with a hash is compared as well, and the check fails
+    /// with [`CheckResultErrorType::Hash`] on a mismatch. BLAKE3
+    /// hashes are verified with the `blake3` feature only. The `file_size`
+    /// and `total_chunks` are taken from the manifest when they are not set,
+    /// and its metadata is returned in the [`CheckResult`].
+    pub fn manifest<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> Self {
+        self.manifest = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the manifest to verify the chunks against from its content.
+    ///
+    /// It is used as the manifest read by [`manifest`](Self::manifest), and
+    /// takes precedence over it, e.g. for a manifest received from the
+    /// sender with [`Manifest::from_json`] rather than written next to the
+    /// chunks.
+    pub fn manifest_data(
+        mut self,
+        manifest: &Manifest,
+    ) -> Self {
+        self.manifest_data = Some(manifest.clone());
+        self
+    }
+
+    #[cfg(feature = "manifest")]
+    /// Set the maximum size of the manifest file in bytes.
+    ///
+    /// By default, it is [`MANIFEST_SIZE_MAX_DEFAULT`]. A larger manifest
+    /// set by [`manifest`](Self::manifest) returns
+    /// [`CheckError::ManifestTooLarge`] before it is read. A manifest set by
+    /// [`manifest_data`](Self::manifest_data) is already parsed and is not
+    /// limited.
+    pub fn max_manifest_size(
+        mut self,
+        size: u64,
+    ) -> Self {
+        self.max_manifest_size = size;
+        self
+    }
+
+    /// Get the manifest set by its content or read from its path.
+    pub(crate) fn load_manifest(&self) -> Result<Option<Manifest>, CheckError> {
+        if let Some(ref m) = self.manifest_data {
+            return Ok(Some(m.clone()));
+        }
+
+        #[cfg(feature = "manifest")]
+        if let Some(ref p) = self.manifest {
+            return match Manifest::read_max(p, self.max_manifest_size) {
+                | Ok(m) => Ok(Some(m)),
+                | Err(e) if e.kind() == ErrorKind::FileTooLarge => {
+                    Err(CheckError::ManifestTooLarge)
+                },
+                | Err(e) => Err(CheckError::ManifestNotRead {
+                    path: p.clone(),
+                    source: e.into(),
+                }),
+            };
+        }
+
+        Ok(None)
+    }
+
+    /// Set the merged file to compare with the chunks.
+    ///
+    /// When set, the merged file is read along with the chunks without
+    /// rewriting anything, so a merge whose output is already correct can be
+    /// skipped. The check fails with [`CheckResultErrorType::Output`] when
+    /// it is missing, its size is not equal `file_size`, or some of its
+    /// sections are not equal their chunks. A section whose chunk is recorded
+    /// with a hash in the [`manifest`](Self::manifest) is hashed instead, so
+    /// the chunk is not read.
+    pub fn out_file<OutFile: AsRef<Path>>(
+        mut self,
+        path: OutFile,
+    ) -> Self {
+        self.out_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Attach a control handle to pause, resume and cancel the check process.
+    pub fn control(
+        mut self,
+        control: &Control,
+    ) -> Self {
+        self.control = Some(control.clone());
+        self
+    }
+
     /// Run the check process.
     pub fn run(&self) -> Result<CheckResult, CheckError> {
         let in_dir: &Path = match self.in_dir {
@@ -217,35 +491,104 @@ impl Check {
             | None => return Err(CheckError::InDirNotSet),
         };
 
-        let file_size: usize = match self.file_size {
-            | Some(s) => s,
-            | None => return Err(CheckError::FileSizeNotSet),
-        };
+        // the names of the chunks may depend on their content
+        self.namer.scan(in_dir);
 
-        let total_chunks: usize = match self.total_chunks {
+        let manifest: Option<Manifest> = self.load_manifest()?;
+
+        let metadata: BTreeMap<String, String> =
+            manifest.as_ref().map(|m| m.metadata.clone()).unwrap_or_default();
+
+        let file_size: usize =
+            match self.file_size.or(manifest.as_ref().map(|m| m.file_size)) {
+                | Some(s) => s,
+                | None => return Err(CheckError::FileSizeNotSet),
+            };
+
+        let total_chunks: usize = match self
+            .total_chunks
+            .or(manifest.as_ref().map(|m| m.total_chunks))
+        {
             | Some(s) => s,
             | None => return Err(CheckError::TotalChunksNotSet),
         };
 
+        if self.max_chunks.is_some_and(|max| total_chunks > max) {
+            return Err(CheckError::TooManyChunks);
+        }
+
+        let _lock: Option<DirLock> = if self.lock {
+            match DirLock::acquire(in_dir) {
+                | Ok(l) => Some(l),
+                | Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    return Err(CheckError::Locked);
+                },
+                | Err(e) => {
+                    return Err(CheckError::LockNotCreated {
+                        path: in_dir.join(LOCK_FILE_NAME),
+                        source: e.into(),
+                    });
+                },
+            }
+        } else {
+            None
+        };
+
+        let _control: Option<ControlGuard> =
+            self.control.as_ref().map(Control::run);
+
         let mut actual_size: usize = 0;
         let mut missing: Vec<usize> = Vec::new();
+        let mut resized: Vec<usize> = Vec::new();
+        let mut sizes: Vec<usize> = Vec::with_capacity(total_chunks);
+
+        // the chunks may vary in size, so each one is compared to the
+        // manifest rather than to a uniform chunk size
+        let expected_sizes: HashMap<usize, usize> = manifest
+            .iter()
+            .flat_map(|m| &m.chunks)
+            .map(|c| (c.index, c.size))
+            .collect();
 
         for i in 0..total_chunks {
-            let target_file: PathBuf = in_dir.join(i.to_string());
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(CheckError::Cancelled);
+                }
+            }
+
+            let target_file: PathBuf = in_dir.join(self.namer.name(i));
 
             if !target_file.exists() || !target_file.is_file() {
                 missing.push(i);
                 continue;
             }
 
-            actual_size +=
+            let size: usize =
                 match fs::OpenOptions::new().read(true).open(&target_file) {
                     | Ok(f) => match f.metadata() {
                         | Ok(m) => m.len() as usize,
-                        | Err(_) => return Err(CheckError::InFileNotRead),
+                        | Err(e) => {
+                            return Err(CheckError::InFileNotRead {
+                                path: target_file,
+                                source: e.into(),
+                            });
+                        },
                     },
-                    | Err(_) => return Err(CheckError::InFileNotOpened),
-                }
+                    | Err(e) => {
+                        return Err(CheckError::InFileNotOpened {
+                            path: target_file,
+                            source: e.into(),
+                        });
+                    },
+                };
+
+            if expected_sizes.get(&i).is_some_and(|s| *s != size) {
+                resized.push(i);
+            }
+
+            actual_size += size;
+            sizes.push(size);
         }
 
         if !missing.is_empty() {
@@ -255,7 +598,22 @@ impl Check {
                     error_type: CheckResultErrorType::Missing,
                     message: "Missing chunk(s)".to_string(),
                     missing: Some(missing),
+                    mismatched: None,
                 }),
+                metadata,
+            });
+        }
+
+        if !resized.is_empty() {
+            return Ok(CheckResult {
+                success: false,
+                error: Some(CheckResultError {
+                    error_type: CheckResultErrorType::Size,
+                    message: "Mismatched chunk size(s)".to_string(),
+                    missing: None,
+                    mismatched: Some(resized),
+                }),
+                metadata,
             });
         }
 
@@ -268,11 +626,175 @@ impl Check {
                         "the size of chunks is not equal to file_size parameter"
                             .to_string(),
                     missing: None,
+                    mismatched: None,
+                }),
+                metadata,
+            });
+        }
+
+        let mut mismatched: Vec<usize> = Vec::new();
+
+        for chunk in manifest.iter().flat_map(|m| &m.chunks) {
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(CheckError::Cancelled);
+                }
+            }
+
+            let (algorithm, expected) = match chunk.hash() {
+                | Some(h) if chunk.index < total_chunks => h,
+                | _ => continue,
+            };
+
+            let chunk_path: PathBuf = in_dir.join(self.namer.name(chunk.index));
+
+            let content: Vec<u8> = match fs::read(&chunk_path) {
+                | Ok(c) => c,
+                | Err(e) => {
+                    return Err(CheckError::InFileNotRead {
+                        path: chunk_path,
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if !algorithm.hex(&content).eq_ignore_ascii_case(expected) {
+                mismatched.push(chunk.index);
+            }
+        }
+
+        if !mismatched.is_empty() {
+            return Ok(CheckResult {
+                success: false,
+                error: Some(CheckResultError {
+                    error_type: CheckResultErrorType::Hash,
+                    message: "Mismatched chunk hash(es)".to_string(),
+                    missing: None,
+                    mismatched: Some(mismatched),
                 }),
+                metadata,
             });
         }
 
-        Ok(CheckResult { success: true, error: None })
+        // compare the merged file without rewriting it
+        if let Some(ref out_file) = self.out_file {
+            if !fs::metadata(out_file)
+                .is_ok_and(|m| m.is_file() && m.len() == file_size as u64)
+            {
+                return Ok(CheckResult {
+                    success: false,
+                    error: Some(CheckResultError {
+                        error_type: CheckResultErrorType::Output,
+                        message: "Mismatched output file size".to_string(),
+                        missing: None,
+                        mismatched: None,
+                    }),
+                    metadata,
+                });
+            }
+
+            let mut output: fs::File = match fs::File::open(out_file) {
+                | Ok(f) => f,
+                | Err(e) => {
+                    return Err(CheckError::OutFileNotRead {
+                        path: out_file.clone(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            let hashes: HashMap<usize, (HashAlgorithm, &str)> = manifest
+                .iter()
+                .flat_map(|m| &m.chunks)
+                .filter_map(|c| c.hash().map(|h| (c.index, h)))
+                .collect();
+
+            let mut output_buffer: Vec<u8> = vec![0; COMPARE_BUFFER_SIZE];
+            let mut chunk_buffer: Vec<u8> = vec![0; COMPARE_BUFFER_SIZE];
+            let mut differing: Vec<usize> = Vec::new();
+
+            for (i, size) in sizes.iter().enumerate() {
+                if let Some(ref c) = self.control {
+                    if !c.wait() {
+                        return Err(CheckError::Cancelled);
+                    }
+                }
+
+                let chunk_path: PathBuf = in_dir.join(self.namer.name(i));
+
+                let mut hasher: Option<Hasher> =
+                    hashes.get(&i).map(|(a, _)| a.hasher());
+
+                let mut chunk: Option<fs::File> = match hasher {
+                    | Some(_) => None,
+                    | None => match fs::File::open(&chunk_path) {
+                        | Ok(f) => Some(f),
+                        | Err(e) => {
+                            return Err(CheckError::InFileNotOpened {
+                                path: chunk_path,
+                                source: e.into(),
+                            });
+                        },
+                    },
+                };
+
+                let mut remaining: usize = *size;
+                let mut equal: bool = true;
+
+                while remaining > 0 {
+                    let n: usize = remaining.min(COMPARE_BUFFER_SIZE);
+
+                    if let Err(e) = output.read_exact(&mut output_buffer[..n]) {
+                        return Err(CheckError::OutFileNotRead {
+                            path: out_file.clone(),
+                            source: e.into(),
+                        });
+                    }
+
+                    if let Some(ref mut h) = hasher {
+                        h.update(&output_buffer[..n]);
+                    }
+
+                    if let Some(ref mut f) = chunk {
+                        if let Err(e) = f.read_exact(&mut chunk_buffer[..n]) {
+                            return Err(CheckError::InFileNotRead {
+                                path: chunk_path,
+                                source: e.into(),
+                            });
+                        }
+
+                        equal &= output_buffer[..n] == chunk_buffer[..n];
+                    }
+
+                    remaining -= n;
+                }
+
+                if let (Some(h), Some((_, expected))) = (hasher, hashes.get(&i))
+                {
+                    equal = h.finalize_hex().eq_ignore_ascii_case(expected);
+                }
+
+                if !equal {
+                    differing.push(i);
+                }
+            }
+
+            if !differing.is_empty() {
+                return Ok(CheckResult {
+                    success: false,
+                    error: Some(CheckResultError {
+                        error_type: CheckResultErrorType::Output,
+                        message: "Mismatched output file section(s)"
+                            .to_string(),
+                        missing: None,
+                        mismatched: Some(differing),
+                    }),
+                    metadata,
+                });
+            }
+        }
+
+        Ok(CheckResult { success: true, error: None, metadata })
     }
 }
 
@@ -0,0 +1,43 @@
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::naming::ChunkNamer;
+
+/// A chunk of a file.
+///
+/// It is the common description of a chunk returned by the planning, the
+/// manifests and the pipeline, e.g.
+/// [`Manifest::chunk_infos`](crate::manifest::Manifest::chunk_infos).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChunkInfo {
+    /// Index of the chunk.
+    pub index: usize,
+    /// Offset of the chunk in the original file in bytes.
+    pub offset: usize,
+    /// Size of the chunk in bytes.
+    pub size: usize,
+    /// File name of the chunk in the chunk directory.
+    pub name: String,
+    /// Hash of the chunk in hex, if it was hashed.
+    pub hash: Option<String>,
+}
+
+impl ChunkInfo {
+    /// Create a new chunk without hash, named with the naming convention.
+    pub fn new(
+        index: usize,
+        offset: usize,
+        size: usize,
+        namer: &dyn ChunkNamer,
+    ) -> Self {
+        Self { index, offset, size, name: namer.name(index), hash: None }
+    }
+
+    /// Get the range of the chunk in the original file.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    lock::LOCK_FILE_NAME,
+};
+
+/// The prefix of every temporary file created by the processes.
+///
+/// Files with this prefix are ignored by the merge process and can be
+/// removed by [`cleanup_orphans`] once they are old enough.
+pub const TEMP_PREFIX: &str = ".filego.";
+
+/// Get the path of a temporary file for a target path.
+///
+/// The name is derived from the target name and the current process id,
+/// so a leftover can be traced back to its target, and two processes never
+/// share a temporary file.
+///
+/// ## Example
+///
+/// ```
+/// use std::path::{Path, PathBuf};
+///
+/// use filego::cleanup;
+///
+/// let temp: PathBuf = cleanup::temp_path(Path::new("dir").join("file"));
+///
+/// assert!(temp.starts_with("dir"));
+/// ```
+pub fn temp_path<P: AsRef<Path>>(target: P) -> PathBuf {
+    let target: &Path = target.as_ref();
+
+    let name: String = format!(
+        "{}{}.{}.tmp",
+        TEMP_PREFIX,
+        target.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(),
+        std::process::id()
+    );
+
+    target.with_file_name(name)
+}
+
+/// Check whether a file name is a temporary or lock file of the processes.
+pub fn is_temp_name(name: &str) -> bool {
+    name == LOCK_FILE_NAME || name.starts_with(TEMP_PREFIX)
+}
+
+/// Remove the temporary and lock files left behind by crashed processes.
+///
+/// The base directory is walked recursively, and every file recognized by
+/// [`is_temp_name`] which was last modified more than `older_than` ago is
+/// removed. Symbolic links are not followed. Returns the number of removed
+/// files.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{path::PathBuf, time::Duration};
+///
+/// use filego::cleanup;
+///
+/// let removed: usize = cleanup::cleanup_orphans(
+///     PathBuf::from("path").join("to").join("dir"),
+///     Duration::from_secs(24 * 60 * 60),
+/// )
+/// .unwrap();
+/// ```
+pub fn cleanup_orphans<P: AsRef<Path>>(
+    base_dir: P,
+    older_than: Duration,
+) -> io::Result<usize> {
+    cleanup_orphans_with_clock(base_dir, older_than, &SystemClock)
+}
+
+/// Remove the temporary and lock files left behind by crashed processes,
+/// with their age measured by the clock.
+///
+/// See [`cleanup_orphans`] for more details.
+pub fn cleanup_orphans_with_clock<P: AsRef<Path>>(
+    base_dir: P,
+    older_than: Duration,
+    clock: &dyn Clock,
+) -> io::Result<usize> {
+    let now: SystemTime = clock.system_now();
+
+    let mut removed: usize = 0;
+    let mut dirs: Vec<PathBuf> = vec![base_dir.as_ref().to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry: fs::DirEntry = entry?;
+            let file_type: fs::FileType = entry.file_type()?;
+
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+
+            if !file_type.is_file()
+                || !entry.file_name().to_str().is_some_and(is_temp_name)
+            {
+                continue;
+            }
+
+            let age: Duration = entry
+                .metadata()?
+                .modified()
+                .ok()
+                .and_then(|m| now.duration_since(m).ok())
+                .unwrap_or_default();
+
+            if age < older_than {
+                continue;
+            }
+
+            match fs::remove_file(entry.path()) {
+                | Ok(()) => removed += 1,
+                // removed concurrently, e.g. a lock being released
+                | Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+                | Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(removed)
+}
@@ -0,0 +1,46 @@
+use std::{
+    fmt::Debug,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Source of time of the processes.
+///
+/// It is used to measure the progress, throttle the IO and age the files
+/// to clean up. Tests can replace the [`SystemClock`] with a clock they
+/// control, e.g. `MockClock` of the `test_util` feature, so waiting and
+/// expiry happen instantly and deterministically.
+pub trait Clock: Debug + Send + Sync {
+    /// Get the current monotonic time.
+    fn now(&self) -> Instant;
+
+    /// Get the current system time.
+    fn system_now(&self) -> SystemTime;
+
+    /// Block the current thread for the duration.
+    fn sleep(
+        &self,
+        duration: Duration,
+    );
+}
+
+/// The clock of the system, used by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(
+        &self,
+        duration: Duration,
+    ) {
+        thread::sleep(duration)
+    }
+}
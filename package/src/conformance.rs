@@ -0,0 +1,365 @@
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, Read as _},
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::hash::{self, Sha256};
+
+/// A chunk described by a test vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVectorChunk {
+    /// Size of the chunk in bytes.
+    pub size: usize,
+    /// Lowercase hex encoded SHA-256 digest of the chunk.
+    pub sha256: String,
+}
+
+/// A published description of the chunks expected from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    /// Size of the original file in bytes.
+    pub file_size: usize,
+    /// Maximum size of each chunk used to split the file.
+    pub chunk_size: usize,
+    /// The expected chunks, in order.
+    pub chunks: Vec<TestVectorChunk>,
+}
+
+/// Error type of the result from the conformance process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConformanceResultErrorType {
+    /// The file size is not equal to the test vector.
+    FileSize,
+    /// The number of chunks is not equal to the test vector.
+    TotalChunks,
+    /// The size of a chunk is not equal to the test vector.
+    ChunkSize,
+    /// The digest of a chunk is not equal to the test vector.
+    ChunkHash,
+}
+
+impl ConformanceResultErrorType {
+    /// Get the error type from code.
+    pub fn from_code<C: AsRef<str>>(code: C) -> Option<Self> {
+        match code.as_ref() {
+            | "file_size" => Some(Self::FileSize),
+            | "total_chunks" => Some(Self::TotalChunks),
+            | "chunk_size" => Some(Self::ChunkSize),
+            | "chunk_hash" => Some(Self::ChunkHash),
+            | _ => None,
+        }
+    }
+
+    /// Get the code of the error type as `&str`.
+    pub fn as_code(&self) -> &str {
+        match self {
+            | Self::FileSize => "file_size",
+            | Self::TotalChunks => "total_chunks",
+            | Self::ChunkSize => "chunk_size",
+            | Self::ChunkHash => "chunk_hash",
+        }
+    }
+
+    /// Get the code of the error type as `String`.
+    pub fn to_code(&self) -> String {
+        self.as_code().to_string()
+    }
+}
+
+/// Error of the result from the conformance process.
+#[derive(Debug, Clone)]
+pub struct ConformanceResultError {
+    /// Type of error of the conformance.
+    pub error_type: ConformanceResultErrorType,
+    /// Error message of the conformance.
+    pub message: String,
+    /// Index of the first chunk not matching the test vector.
+    pub index: Option<usize>,
+}
+
+/// Result of the conformance process.
+#[derive(Debug, Clone)]
+pub struct ConformanceResult {
+    /// Successful / Failed conformance.
+    pub success: bool,
+    /// Error details of the conformance.
+    pub error: Option<ConformanceResultError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ConformanceError {
+    InFileNotFound,
+    InFileNotFile,
+    InFileNotSet,
+    InFileNotOpened,
+    InFileNotRead,
+    TestVectorNotSet,
+    TestVectorChunkSizeZero,
+}
+
+impl ConformanceError {
+    /// Get the code of the error as `&str`.
+    pub fn as_code(&self) -> &str {
+        match self {
+            | Self::InFileNotFound => "in_file_not_found",
+            | Self::InFileNotFile => "in_file_not_file",
+            | Self::InFileNotSet => "in_file_not_set",
+            | Self::InFileNotOpened => "in_file_not_opened",
+            | Self::InFileNotRead => "in_file_not_read",
+            | Self::TestVectorNotSet => "test_vector_not_set",
+            | Self::TestVectorChunkSizeZero => "test_vector_chunk_size_zero",
+        }
+    }
+
+    /// Get the code of the error as `String`.
+    pub fn to_code(&self) -> String {
+        self.as_code().to_string()
+    }
+
+    /// Get the message of the error as `&str`.
+    pub fn as_message(&self) -> &str {
+        match self {
+            | Self::InFileNotFound => "The input file not found.",
+            | Self::InFileNotFile => "The input file is not a file.",
+            | Self::InFileNotSet => "The input file is not set.",
+            | Self::InFileNotOpened => "The input file could not be opened.",
+            | Self::InFileNotRead => "The input file could not be read.",
+            | Self::TestVectorNotSet => "The test vector is not set.",
+            | Self::TestVectorChunkSizeZero => {
+                "The chunk size of the test vector is zero."
+            },
+        }
+    }
+
+    /// Get the message of the error as `String`.
+    pub fn to_message(&self) -> String {
+        self.as_message().to_string()
+    }
+}
+
+impl fmt::Display for ConformanceError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(self.as_message())
+    }
+}
+
+impl Error for ConformanceError {}
+
+/// Process to verify the chunks of a file against a test vector.
+///
+/// The file is chunked in memory with the same algorithm as the split
+/// process, nothing is written to the disk. It allows alternative
+/// implementations to prove that they produce the same chunks.
+///
+/// The function will return [`ConformanceResult`] (that may come
+/// with `success:false`) when the process runs successfully.
+/// Otherwise, it will return Error.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::conformance::{Conformance, ConformanceResult, TestVector};
+///
+/// let vector: TestVector = TestVector {
+///     file_size: 0,
+///     chunk_size: 1024,
+///     chunks: Vec::new(), // published chunks...
+/// };
+///
+/// let result: ConformanceResult = Conformance::new()
+///     .in_file(PathBuf::from("path").join("to").join("file"))
+///     .test_vector(vector)
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Conformance {
+    pub in_file: Option<PathBuf>,
+    pub test_vector: Option<TestVector>,
+}
+
+impl Conformance {
+    /// Create a new conformance process.
+    pub fn new() -> Self {
+        Self { in_file: None, test_vector: None }
+    }
+
+    /// Create a new conformance process from an existing one.
+    pub fn from<P: Into<Conformance>>(process: P) -> Self {
+        process.into()
+    }
+
+    /// Set the input file.
+    pub fn in_file<InFile: AsRef<Path>>(
+        mut self,
+        path: InFile,
+    ) -> Self {
+        self.in_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the test vector to verify against.
+    pub fn test_vector(
+        mut self,
+        vector: TestVector,
+    ) -> Self {
+        self.test_vector = Some(vector);
+        self
+    }
+
+    /// Run the conformance process.
+    pub fn run(&self) -> Result<ConformanceResult, ConformanceError> {
+        let in_file: &Path = match self.in_file {
+            | Some(ref p) => {
+                let p: &Path = p.as_path();
+
+                // if in_file not exists
+                if !p.exists() {
+                    return Err(ConformanceError::InFileNotFound);
+                }
+
+                // if in_file not a file
+                if !p.is_file() {
+                    return Err(ConformanceError::InFileNotFile);
+                }
+
+                p
+            },
+            | None => return Err(ConformanceError::InFileNotSet),
+        };
+
+        let vector: &TestVector = match self.test_vector {
+            | Some(ref v) => v,
+            | None => return Err(ConformanceError::TestVectorNotSet),
+        };
+
+        if vector.chunk_size == 0 {
+            return Err(ConformanceError::TestVectorChunkSizeZero);
+        }
+
+        let input: fs::File =
+            match fs::OpenOptions::new().read(true).open(in_file) {
+                | Ok(f) => f,
+                | Err(_) => return Err(ConformanceError::InFileNotOpened),
+            };
+
+        let file_size: usize = match input.metadata() {
+            | Ok(m) => m.len() as usize,
+            | Err(_) => return Err(ConformanceError::InFileNotRead),
+        };
+
+        if file_size != vector.file_size {
+            return Ok(Self::failure(
+                ConformanceResultErrorType::FileSize,
+                "the file size is not equal to the test vector",
+                None,
+            ));
+        }
+
+        let mut reader: io::BufReader<fs::File> =
+            io::BufReader::with_capacity(vector.chunk_size, input);
+
+        let mut buffer: Vec<u8> = vec![0; vector.chunk_size];
+
+        let mut index: usize = 0;
+
+        loop {
+            let mut offset: usize = 0;
+
+            while offset < vector.chunk_size {
+                let bytes_read: usize = match reader.read(&mut buffer[offset..])
+                {
+                    | Ok(n) => n,
+                    | Err(_) => return Err(ConformanceError::InFileNotRead),
+                };
+
+                if bytes_read == 0 {
+                    break;
+                }
+
+                offset += bytes_read;
+            }
+
+            if offset == 0 {
+                break;
+            }
+
+            let expected: &TestVectorChunk = match vector.chunks.get(index) {
+                | Some(c) => c,
+                | None => {
+                    return Ok(Self::failure(
+                        ConformanceResultErrorType::TotalChunks,
+                        "the number of chunks is not equal to the test vector",
+                        Some(index),
+                    ));
+                },
+            };
+
+            if expected.size != offset {
+                return Ok(Self::failure(
+                    ConformanceResultErrorType::ChunkSize,
+                    "the size of the chunk is not equal to the test vector",
+                    Some(index),
+                ));
+            }
+
+            let mut hasher: Sha256 = Sha256::new();
+            hasher.update(&buffer[..offset]);
+
+            if !hash::to_hex(&hasher.finalize())
+                .eq_ignore_ascii_case(&expected.sha256)
+            {
+                return Ok(Self::failure(
+                    ConformanceResultErrorType::ChunkHash,
+                    "the digest of the chunk is not equal to the test vector",
+                    Some(index),
+                ));
+            }
+
+            index += 1;
+        }
+
+        if index != vector.chunks.len() {
+            return Ok(Self::failure(
+                ConformanceResultErrorType::TotalChunks,
+                "the number of chunks is not equal to the test vector",
+                None,
+            ));
+        }
+
+        Ok(ConformanceResult { success: true, error: None })
+    }
+
+    fn failure(
+        error_type: ConformanceResultErrorType,
+        message: &str,
+        index: Option<usize>,
+    ) -> ConformanceResult {
+        ConformanceResult {
+            success: false,
+            error: Some(ConformanceResultError {
+                error_type,
+                message: message.to_string(),
+                index,
+            }),
+        }
+    }
+}
+
+impl Default for Conformance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
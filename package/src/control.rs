@@ -0,0 +1,489 @@
+#[cfg(any(feature = "async_std", feature = "tokio"))]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::{
+    io,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    task::Waker,
+};
+
+/// Status of a process attached to a [`Control`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The process has not started yet.
+    Idle,
+    /// The process is running.
+    Running,
+    /// The process is running but parked until it is resumed.
+    Paused,
+    /// The process has finished, successfully or not.
+    Finished,
+    /// The process has stopped after being cancelled.
+    Cancelled,
+}
+
+/// Partial result of a process, recorded after each completed chunk.
+///
+/// It stays available after the process fails or is cancelled, so it can
+/// be resumed from the next chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Partial {
+    /// Number of chunks completed.
+    pub chunks_done: usize,
+    /// Number of bytes written.
+    pub bytes_done: u64,
+    /// Index of the last completed chunk.
+    pub last_index: Option<usize>,
+}
+
+#[derive(Debug)]
+struct State {
+    status: Status,
+    paused: bool,
+    cancelled: bool,
+    partial: Partial,
+    wakers: Vec<Waker>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+/// Handle to pause, resume, cancel and observe a running process.
+///
+/// The process parks between chunks while paused, and stops at the next
+/// buffer read when cancelled, so even a large chunk does not delay it. Only
+/// the completed chunks are reported by [`partial`](Self::partial). A handle
+/// can be cloned and shared between threads, all clones control the same
+/// process. It controls split, merge and check processes.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{control::Control, merge::Merge};
+///
+/// let control: Control = Control::new();
+///
+/// let merge: Merge = Merge::new()
+///     .in_dir(PathBuf::from("path").join("to").join("dir"))
+///     .out_file(PathBuf::from("path").join("to").join("file"))
+///     .control(&control);
+///
+/// // from another thread...
+/// control.pause();
+/// control.resume();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Control {
+    inner: Arc<Inner>,
+}
+
+impl Control {
+    /// Create a new control handle.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State {
+                    status: Status::Idle,
+                    paused: false,
+                    cancelled: false,
+                    partial: Partial::default(),
+                    wakers: Vec::new(),
+                }),
+                condvar: Condvar::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.inner.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pause the process at the next chunk boundary.
+    pub fn pause(&self) {
+        let mut state: MutexGuard<'_, State> = self.state();
+
+        state.paused = true;
+
+        if state.status == Status::Running {
+            state.status = Status::Paused;
+        }
+    }
+
+    /// Resume the paused process.
+    pub fn resume(&self) {
+        let mut state: MutexGuard<'_, State> = self.state();
+
+        state.paused = false;
+
+        if state.status == Status::Paused {
+            state.status = Status::Running;
+        }
+
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+
+        self.inner.condvar.notify_all();
+    }
+
+    /// Cancel the process at the next buffer read.
+    ///
+    /// The process returns a `Cancelled` error, and the chunks completed so
+    /// far are reported by [`partial`](Self::partial), a chunk interrupted
+    /// in the middle is not. A cancelled handle
+    /// stays cancelled, so a new one is needed to run again.
+    pub fn cancel(&self) {
+        let mut state: MutexGuard<'_, State> = self.state();
+
+        state.cancelled = true;
+
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+
+        self.inner.condvar.notify_all();
+    }
+
+    /// Cancel the process when the program receives `SIGINT` or `SIGTERM`.
+    ///
+    /// The signals are handled instead of terminating the program until the
+    /// returned guard is dropped, so the process stops at the next buffer
+    /// read and the chunks completed so far are reported by
+    /// [`partial`](Self::partial) to resume from, e.g. with the resume
+    /// option of the split or merge process. Each signal cancels the
+    /// processes registered at the time. It is only supported on Linux, an
+    /// error of kind [`Unsupported`](io::ErrorKind::Unsupported) is returned
+    /// on other platforms.
+    pub fn cancel_on_signal(&self) -> io::Result<SignalGuard> {
+        signal::register(self)?;
+
+        Ok(SignalGuard { control: self.clone() })
+    }
+
+    /// Get the status of the process.
+    pub fn status(&self) -> Status {
+        self.state().status
+    }
+
+    /// Check whether the process is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state().paused
+    }
+
+    /// Check whether the process is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.state().cancelled
+    }
+
+    /// Get the partial result of the last run of the process.
+    pub fn partial(&self) -> Partial {
+        self.state().partial
+    }
+
+    /// Mark the process as running until the returned guard is dropped.
+    pub(crate) fn run(&self) -> ControlGuard {
+        let mut state: MutexGuard<'_, State> = self.state();
+
+        state.status =
+            if state.paused { Status::Paused } else { Status::Running };
+        state.partial = Partial::default();
+
+        ControlGuard { control: self.clone() }
+    }
+
+    /// Block the current thread while the process is paused.
+    ///
+    /// Returns `false` if the process is cancelled.
+    pub(crate) fn wait(&self) -> bool {
+        let mut state: MutexGuard<'_, State> = self.state();
+
+        while state.paused && !state.cancelled {
+            state = self
+                .inner
+                .condvar
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+
+        !state.cancelled
+    }
+
+    /// Record a completed chunk in the partial result.
+    pub(crate) fn record(
+        &self,
+        index: usize,
+        bytes: u64,
+    ) {
+        let mut state: MutexGuard<'_, State> = self.state();
+
+        state.partial.chunks_done += 1;
+        state.partial.bytes_done += bytes;
+        state.partial.last_index = Some(index);
+    }
+
+    /// Wait asynchronously while the process is paused.
+    ///
+    /// Resolves to `false` if the process is cancelled.
+    #[cfg(any(feature = "async_std", feature = "tokio"))]
+    pub(crate) fn wait_async(&self) -> Resumed<'_> {
+        Resumed { control: self }
+    }
+}
+
+impl Default for Control {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Guard marking the process as finished on drop.
+pub(crate) struct ControlGuard {
+    control: Control,
+}
+
+impl Drop for ControlGuard {
+    fn drop(&mut self) {
+        let mut state: MutexGuard<'_, State> = self.control.state();
+
+        state.status =
+            if state.cancelled { Status::Cancelled } else { Status::Finished };
+    }
+}
+
+/// Future resolving once the process is no longer paused or is cancelled.
+#[cfg(any(feature = "async_std", feature = "tokio"))]
+pub(crate) struct Resumed<'a> {
+    control: &'a Control,
+}
+
+#[cfg(any(feature = "async_std", feature = "tokio"))]
+impl Future for Resumed<'_> {
+    type Output = bool;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        let mut state: MutexGuard<'_, State> = self.control.state();
+
+        if !state.paused || state.cancelled {
+            return Poll::Ready(!state.cancelled);
+        }
+
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Guard cancelling a [`Control`] on `SIGINT` and `SIGTERM` until dropped.
+///
+/// The handlers found before the first guard are restored when the last
+/// guard is dropped.
+#[derive(Debug)]
+pub struct SignalGuard {
+    control: Control,
+}
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        signal::unregister(&self.control);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod signal {
+    use std::{
+        io, mem, ptr,
+        sync::{
+            Arc, Mutex, MutexGuard,
+            atomic::{AtomicI32, Ordering},
+        },
+        thread,
+    };
+
+    use super::Control;
+
+    const SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+    /// The write end of the pipe waking the dispatching thread, `-1` when
+    /// the handlers are not installed.
+    static WAKE_FD: AtomicI32 = AtomicI32::new(-1);
+
+    static REGISTRY: Mutex<Registry> =
+        Mutex::new(Registry { controls: Vec::new(), installed: None });
+
+    struct Registry {
+        controls: Vec<Control>,
+        installed: Option<Installed>,
+    }
+
+    struct Installed {
+        /// The handlers replaced, restored by the last guard.
+        old: Vec<(libc::c_int, libc::sigaction)>,
+        wake_fd: libc::c_int,
+    }
+
+    extern "C" fn handle(_signum: libc::c_int) {
+        // only async-signal-safe calls are allowed here, so the controls
+        // are cancelled by the dispatching thread
+        unsafe {
+            let errno: libc::c_int = *libc::__errno_location();
+            let fd: libc::c_int = WAKE_FD.load(Ordering::SeqCst);
+
+            if fd >= 0 {
+                let byte: u8 = 1;
+
+                libc::write(fd, (&raw const byte).cast(), 1);
+            }
+
+            *libc::__errno_location() = errno;
+        }
+    }
+
+    /// Cancel the registered controls on each wake up, until the write end
+    /// of the pipe is closed.
+    fn dispatch(read_fd: libc::c_int) {
+        let mut byte: u8 = 0;
+
+        loop {
+            let n: isize =
+                unsafe { libc::read(read_fd, (&raw mut byte).cast(), 1) };
+
+            if n < 0
+                && io::Error::last_os_error().kind()
+                    == io::ErrorKind::Interrupted
+            {
+                continue;
+            }
+
+            if n != 1 {
+                break;
+            }
+
+            lock().controls.iter().for_each(Control::cancel);
+        }
+
+        unsafe { libc::close(read_fd) };
+    }
+
+    fn restore(old: &[(libc::c_int, libc::sigaction)]) {
+        for (signum, action) in old.iter() {
+            unsafe { libc::sigaction(*signum, action, ptr::null_mut()) };
+        }
+    }
+
+    fn install() -> io::Result<Installed> {
+        let mut fds: [libc::c_int; 2] = [-1; 2];
+
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let [read_fd, wake_fd] = fds;
+
+        WAKE_FD.store(wake_fd, Ordering::SeqCst);
+
+        let mut old: Vec<(libc::c_int, libc::sigaction)> = Vec::new();
+
+        for signum in SIGNALS {
+            let mut previous: libc::sigaction = unsafe { mem::zeroed() };
+
+            let res: libc::c_int = unsafe {
+                let mut action: libc::sigaction = mem::zeroed();
+
+                action.sa_sigaction =
+                    handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+
+                libc::sigaction(signum, &action, &mut previous)
+            };
+
+            if res != 0 {
+                let e: io::Error = io::Error::last_os_error();
+
+                restore(&old);
+                WAKE_FD.store(-1, Ordering::SeqCst);
+                unsafe {
+                    libc::close(read_fd);
+                    libc::close(wake_fd);
+                }
+
+                return Err(e);
+            }
+
+            old.push((signum, previous));
+        }
+
+        thread::spawn(move || dispatch(read_fd));
+
+        Ok(Installed { old, wake_fd })
+    }
+
+    fn lock() -> MutexGuard<'static, Registry> {
+        REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(super) fn register(control: &Control) -> io::Result<()> {
+        let mut registry: MutexGuard<'_, Registry> = lock();
+
+        if registry.installed.is_none() {
+            registry.installed = Some(install()?);
+        }
+
+        registry.controls.push(control.clone());
+
+        Ok(())
+    }
+
+    pub(super) fn unregister(control: &Control) {
+        let mut registry: MutexGuard<'_, Registry> = lock();
+
+        if let Some(i) = registry
+            .controls
+            .iter()
+            .position(|c| Arc::ptr_eq(&c.inner, &control.inner))
+        {
+            registry.controls.swap_remove(i);
+        }
+
+        if !registry.controls.is_empty() {
+            return;
+        }
+
+        if let Some(installed) = registry.installed.take() {
+            restore(&installed.old);
+            WAKE_FD.store(-1, Ordering::SeqCst);
+
+            // the dispatching thread stops once the pipe is closed
+            unsafe { libc::close(installed.wake_fd) };
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod signal {
+    use std::io;
+
+    use super::Control;
+
+    pub(super) fn register(_control: &Control) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "signals are only handled on Linux",
+        ))
+    }
+
+    pub(super) fn unregister(_control: &Control) {}
+}
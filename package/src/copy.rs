@@ -0,0 +1,89 @@
+use std::{
+    fs::File,
+    io::{self, ErrorKind},
+    os::fd::AsRawFd as _,
+};
+
+/// Copy a range of the input file into the empty output file.
+///
+/// The range is cloned when the file system shares extents between files,
+/// e.g. XFS or Btrfs, and copied within the kernel otherwise, so the content
+/// never passes through a buffer.
+pub(crate) fn copy_range(
+    input: &File,
+    output: &File,
+    offset: u64,
+    len: u64,
+) -> io::Result<()> {
+    if clone_range(input, output, offset, len).is_ok() {
+        return Ok(());
+    }
+
+    let mut off_in: libc::loff_t = offset as libc::loff_t;
+    let mut remaining: u64 = len;
+
+    while remaining > 0 {
+        // the output offset is advanced by the kernel
+        let res: libc::c_long = unsafe {
+            libc::syscall(
+                libc::SYS_copy_file_range,
+                input.as_raw_fd(),
+                &mut off_in as *mut libc::loff_t,
+                output.as_raw_fd(),
+                std::ptr::null_mut::<libc::loff_t>(),
+                remaining as libc::size_t,
+                0 as libc::c_uint,
+            )
+        };
+
+        if res < 0 {
+            let e: io::Error = io::Error::last_os_error();
+
+            if e.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+
+            return Err(e);
+        }
+
+        if res == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        remaining -= res as u64;
+    }
+
+    Ok(())
+}
+
+/// Clone a range of the input file into the empty output file.
+///
+/// The range must be aligned to the block size of the file system, unless
+/// it ends at the end of the input file.
+fn clone_range(
+    input: &File,
+    output: &File,
+    offset: u64,
+    len: u64,
+) -> io::Result<()> {
+    let range: libc::file_clone_range = libc::file_clone_range {
+        src_fd: input.as_raw_fd() as i64,
+        src_offset: offset,
+        src_length: len,
+        dest_offset: 0,
+    };
+
+    let res: libc::c_int = unsafe {
+        libc::ioctl(
+            output.as_raw_fd(),
+            libc::FICLONERANGE as _,
+            &range as *const libc::file_clone_range,
+        )
+    };
+
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
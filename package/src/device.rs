@@ -0,0 +1,121 @@
+use std::{io, path::Path};
+
+/// Check whether two paths are on the same device.
+///
+/// Paths that do not exist yet are resolved through their closest existing
+/// ancestor. On platforms without device ids, it always returns `true` and
+/// the caller has to handle a failed rename or link.
+#[cfg(unix)]
+pub(crate) fn same_device<A: AsRef<Path>, B: AsRef<Path>>(
+    a: A,
+    b: B,
+) -> bool {
+    use std::os::unix::fs::MetadataExt as _;
+
+    fn device(path: &Path) -> Option<u64> {
+        path.ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .chain([Path::new(".")])
+            .find_map(|p| p.metadata().ok())
+            .map(|m| m.dev())
+    }
+
+    match (device(a.as_ref()), device(b.as_ref())) {
+        | (Some(a), Some(b)) => a == b,
+        | _ => false,
+    }
+}
+
+/// Check whether two paths are on the same device.
+///
+/// Paths that do not exist yet are resolved through their closest existing
+/// ancestor. On platforms without device ids, it always returns `true` and
+/// the caller has to handle a failed rename or link.
+#[cfg(not(unix))]
+pub(crate) fn same_device<A: AsRef<Path>, B: AsRef<Path>>(
+    _a: A,
+    _b: B,
+) -> bool {
+    true
+}
+
+/// Get the space available to the user on the file system of the path.
+///
+/// Paths that do not exist yet are resolved through their closest existing
+/// ancestor. Returns `None` if it cannot be known.
+#[cfg(target_os = "linux")]
+pub(crate) fn free_space<P: AsRef<Path>>(path: P) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt as _};
+
+    let path: &Path = path
+        .as_ref()
+        .ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .chain([Path::new(".")])
+        .find(|p| p.exists())?;
+
+    let c_path: CString = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } < 0 {
+        return None;
+    }
+
+    Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+/// Get the space available to the user on the file system of the path.
+///
+/// Only supported on Linux, it returns `None` on other platforms.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn free_space<P: AsRef<Path>>(_path: P) -> Option<u64> {
+    None
+}
+
+/// Reserve the space of `len` bytes for the file without changing its size.
+///
+/// The blocks are allocated upfront, so the file is less fragmented and a
+/// full disk is reported before anything is written. File systems without
+/// support for it are ignored.
+#[cfg(target_os = "linux")]
+pub(crate) fn preallocate<F: std::os::fd::AsRawFd>(
+    file: &F,
+    len: u64,
+) -> io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+
+    let res: libc::c_int = unsafe {
+        libc::fallocate(
+            file.as_raw_fd(),
+            libc::FALLOC_FL_KEEP_SIZE,
+            0,
+            len as libc::off_t,
+        )
+    };
+
+    if res < 0 {
+        let e: io::Error = io::Error::last_os_error();
+
+        return match e.kind() {
+            | io::ErrorKind::Unsupported | io::ErrorKind::InvalidInput => {
+                Ok(())
+            },
+            | _ => Err(e),
+        };
+    }
+
+    Ok(())
+}
+
+/// Reserve the space of `len` bytes for the file without changing its size.
+///
+/// Only supported on Linux, it does nothing on other platforms.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn preallocate<F>(
+    _file: &F,
+    _len: u64,
+) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind, Read as _, Write as _},
+    os::{fd::AsRawFd as _, unix::fs::OpenOptionsExt as _},
+    path::Path,
+};
+
+/// Alignment of the buffers, offsets and lengths of unbuffered IO.
+///
+/// It is the page size, a multiple of the logical block size of common
+/// devices.
+pub(crate) const ALIGNMENT: usize = 4096;
+
+/// Buffer aligned for unbuffered IO.
+#[derive(Debug)]
+pub(crate) struct AlignedBuffer {
+    data: Vec<u8>,
+    offset: usize,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    /// Create a new buffer of at least `len` bytes, rounded up to the
+    /// alignment.
+    pub(crate) fn new(len: usize) -> Self {
+        let len: usize = len.max(1).div_ceil(ALIGNMENT) * ALIGNMENT;
+        let data: Vec<u8> = vec![0; len + ALIGNMENT];
+        let offset: usize = data.as_ptr().align_offset(ALIGNMENT);
+
+        Self { data, offset, len }
+    }
+
+    /// Get the aligned bytes.
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        &self.data[self.offset..self.offset + self.len]
+    }
+
+    /// Get the aligned bytes to write them.
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.data[self.offset..self.offset + self.len]
+    }
+}
+
+/// Open the file to read it without the page cache.
+pub(crate) fn open<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    OpenOptions::new().read(true).custom_flags(libc::O_DIRECT).open(path)
+}
+
+/// Create the file to write it without the page cache.
+pub(crate) fn create<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+}
+
+/// Read until the buffer is full or the end of the file.
+pub(crate) fn read_full(
+    file: &mut File,
+    buffer: &mut [u8],
+) -> io::Result<usize> {
+    let mut read: usize = 0;
+
+    while read < buffer.len() {
+        match file.read(&mut buffer[read..]) {
+            | Ok(0) => break,
+            | Ok(n) => read += n,
+            | Err(e) if e.kind() == ErrorKind::Interrupted => {},
+            | Err(e) => return Err(e),
+        }
+    }
+
+    Ok(read)
+}
+
+/// Write the content from an aligned buffer.
+///
+/// The aligned blocks are written without the page cache, and the last
+/// partial block through it, as it cannot be written unbuffered.
+pub(crate) fn write(
+    file: &mut File,
+    content: &[u8],
+) -> io::Result<()> {
+    let aligned: usize = content.len() / ALIGNMENT * ALIGNMENT;
+
+    file.write_all(&content[..aligned])?;
+
+    if aligned < content.len() {
+        buffered(file)?;
+        file.write_all(&content[aligned..])?;
+    }
+
+    Ok(())
+}
+
+/// Turn the unbuffered IO of the file off.
+fn buffered(file: &File) -> io::Result<()> {
+    let fd: libc::c_int = file.as_raw_fd();
+
+    let flags: libc::c_int = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+
+    if flags < 0
+        || unsafe { libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_DIRECT) }
+            < 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
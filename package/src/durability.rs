@@ -0,0 +1,110 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How far the written files are persisted before a process returns.
+///
+/// Each level includes the previous ones.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     durability::Durability,
+///     merge::{Merge, MergeResult},
+/// };
+///
+/// let result: MergeResult = Merge::new()
+///     .in_dir(PathBuf::from("path").join("to").join("dir"))
+///     .out_file(PathBuf::from("path").join("to").join("file"))
+///     .durability(Durability::FsyncDir)
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Durability {
+    /// The files are flushed to the operating system, which writes them to
+    /// the disk later, so they are lost on a power failure.
+    #[default]
+    Flush,
+    /// The content of the files is written to the disk, but a new file may
+    /// still be missing from its directory after a crash.
+    Fsync,
+    /// The directory containing the files is also written to the disk, so
+    /// new files are found after a crash. The directory is only synced on
+    /// Unix, as it cannot be opened as a file on other platforms.
+    FsyncDir,
+}
+
+impl Durability {
+    /// Persist the files and the directory containing them.
+    ///
+    /// Returns the path that could not be synced with its error.
+    pub(crate) fn sync(
+        &self,
+        files: &[PathBuf],
+        dir: &Path,
+    ) -> Result<(), (PathBuf, io::Error)> {
+        if *self == Self::Flush {
+            return Ok(());
+        }
+
+        for file in files.iter() {
+            if let Err(e) = fsync(file) {
+                return Err((file.clone(), e));
+            }
+        }
+
+        if *self == Self::FsyncDir {
+            if let Err(e) = fsync_dir(dir) {
+                return Err((dir.to_path_buf(), e));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Persist the file and the directory containing it.
+    pub(crate) fn sync_file(
+        &self,
+        file: &Path,
+    ) -> Result<(), (PathBuf, io::Error)> {
+        self.sync(&[file.to_path_buf()], file.parent().unwrap_or(Path::new("")))
+    }
+}
+
+/// Write the content of the file to the disk.
+fn fsync(path: &Path) -> io::Result<()> {
+    // the file must be writable to be flushed on Windows
+    fs::OpenOptions::new()
+        .read(true)
+        .write(cfg!(windows))
+        .open(path)?
+        .sync_all()
+}
+
+/// Write the entries of the directory to the disk.
+#[cfg(unix)]
+fn fsync_dir(path: &Path) -> io::Result<()> {
+    // an empty parent is the current directory
+    let path: &Path =
+        if path.as_os_str().is_empty() { Path::new(".") } else { path };
+
+    fs::File::open(path)?.sync_all()
+}
+
+/// Write the entries of the directory to the disk.
+///
+/// Only supported on Unix, it does nothing on other platforms.
+#[cfg(not(unix))]
+fn fsync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
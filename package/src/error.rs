@@ -0,0 +1,247 @@
+use std::{error::Error as StdError, fmt, io, sync::Arc};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{check::CheckError, merge::MergeError, split::SplitError};
+
+/// Error of any of the processes.
+///
+/// It is displayed as the error of the process it wraps. It lets
+/// applications running several processes use one error type, e.g. with the
+/// `?` operator:
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{check::Check, merge::Merge, split::Split};
+///
+/// fn transfer(dir: PathBuf) -> Result<(), filego::Error> {
+///     let result = Split::new()
+///         .in_file(PathBuf::from("path").join("to").join("file"))
+///         .out_dir(&dir)
+///         .run()?;
+///
+///     Check::new()
+///         .in_dir(&dir)
+///         .file_size(result.file_size)
+///         .total_chunks(result.total_chunks)
+///         .run()?;
+///
+///     Merge::new()
+///         .in_dir(&dir)
+///         .out_file(PathBuf::from("path").join("to").join("merged"))
+///         .run()?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Error {
+    /// The error of a split process.
+    Split(SplitError),
+    /// The error of a merge process.
+    Merge(MergeError),
+    /// The error of a check process.
+    Check(CheckError),
+}
+
+impl Error {
+    /// Get the code of the error as `&str`.
+    pub fn as_code(&self) -> &str {
+        match self {
+            | Self::Split(e) => e.as_code(),
+            | Self::Merge(e) => e.as_code(),
+            | Self::Check(e) => e.as_code(),
+        }
+    }
+
+    /// Get the code of the error as `String`.
+    pub fn to_code(&self) -> String {
+        self.as_code().to_string()
+    }
+
+    /// Get the message of the error as `&str`.
+    pub fn as_message(&self) -> &str {
+        match self {
+            | Self::Split(e) => e.as_message(),
+            | Self::Merge(e) => e.as_message(),
+            | Self::Check(e) => e.as_message(),
+        }
+    }
+
+    /// Get the message of the error as `String`.
+    pub fn to_message(&self) -> String {
+        self.as_message().to_string()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            | Self::Split(e) => e.fmt(f),
+            | Self::Merge(e) => e.fmt(f),
+            | Self::Check(e) => e.fmt(f),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            | Self::Split(e) => e.source(),
+            | Self::Merge(e) => e.source(),
+            | Self::Check(e) => e.source(),
+        }
+    }
+}
+
+impl From<SplitError> for Error {
+    fn from(error: SplitError) -> Self {
+        Self::Split(error)
+    }
+}
+
+impl From<MergeError> for Error {
+    fn from(error: MergeError) -> Self {
+        Self::Merge(error)
+    }
+}
+
+impl From<CheckError> for Error {
+    fn from(error: CheckError) -> Self {
+        Self::Check(error)
+    }
+}
+
+/// An I/O error kept by the errors of the processes.
+///
+/// It wraps [`io::Error`] so the errors holding it stay cloneable and
+/// comparable. Two errors are equal when they have the same kind.
+///
+/// With the `serde` feature, it is serialized with its kind and message,
+/// e.g. `{"kind":"NotFound","message":"No such file or directory"}`.
+#[derive(Debug, Clone)]
+pub struct IoError(Arc<io::Error>);
+
+impl IoError {
+    /// Get the kind of the error.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.0.kind()
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(error: io::Error) -> Self {
+        Self(Arc::new(error))
+    }
+}
+
+impl AsRef<io::Error> for IoError {
+    fn as_ref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.kind() == other.kind()
+    }
+}
+
+impl Eq for IoError {}
+
+impl fmt::Display for IoError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The kinds kept when an error is deserialized, others become `Other`.
+#[cfg(feature = "serde")]
+const IO_ERROR_KINDS: &[io::ErrorKind] = &[
+    io::ErrorKind::NotFound,
+    io::ErrorKind::PermissionDenied,
+    io::ErrorKind::ConnectionRefused,
+    io::ErrorKind::ConnectionReset,
+    io::ErrorKind::HostUnreachable,
+    io::ErrorKind::NetworkUnreachable,
+    io::ErrorKind::ConnectionAborted,
+    io::ErrorKind::NotConnected,
+    io::ErrorKind::AddrInUse,
+    io::ErrorKind::AddrNotAvailable,
+    io::ErrorKind::NetworkDown,
+    io::ErrorKind::BrokenPipe,
+    io::ErrorKind::AlreadyExists,
+    io::ErrorKind::WouldBlock,
+    io::ErrorKind::NotADirectory,
+    io::ErrorKind::IsADirectory,
+    io::ErrorKind::DirectoryNotEmpty,
+    io::ErrorKind::ReadOnlyFilesystem,
+    io::ErrorKind::StaleNetworkFileHandle,
+    io::ErrorKind::InvalidInput,
+    io::ErrorKind::InvalidData,
+    io::ErrorKind::TimedOut,
+    io::ErrorKind::WriteZero,
+    io::ErrorKind::StorageFull,
+    io::ErrorKind::NotSeekable,
+    io::ErrorKind::FileTooLarge,
+    io::ErrorKind::ResourceBusy,
+    io::ErrorKind::ExecutableFileBusy,
+    io::ErrorKind::Deadlock,
+    io::ErrorKind::TooManyLinks,
+    io::ErrorKind::ArgumentListTooLong,
+    io::ErrorKind::Interrupted,
+    io::ErrorKind::Unsupported,
+    io::ErrorKind::UnexpectedEof,
+    io::ErrorKind::OutOfMemory,
+];
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct IoErrorRepr {
+    kind: String,
+    message: String,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for IoError {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        IoErrorRepr {
+            kind: format!("{:?}", self.kind()),
+            message: self.0.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for IoError {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D
+    ) -> Result<Self, D::Error> {
+        let repr: IoErrorRepr = IoErrorRepr::deserialize(deserializer)?;
+
+        let kind: io::ErrorKind = IO_ERROR_KINDS
+            .iter()
+            .find(|k| format!("{:?}", k) == repr.kind)
+            .copied()
+            .unwrap_or(io::ErrorKind::Other);
+
+        Ok(io::Error::new(kind, repr.message).into())
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// The default interval between two polls of a followed input.
+pub const FOLLOW_INTERVAL_DEFAULT: Duration = Duration::from_millis(100);
+
+/// Handle to follow an input which is still being written.
+///
+/// A process following an input keeps polling it for new data instead of
+/// stopping at its current end, until [`end`](Self::end) is called. A
+/// handle can be cloned and shared between threads, all clones signal the
+/// same input.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{follow::Follow, split::Split};
+///
+/// let follow: Follow = Follow::new();
+///
+/// let split: Split = Split::new()
+///     .in_file(PathBuf::from("path").join("to").join("file"))
+///     .out_dir(PathBuf::from("path").join("to").join("dir"))
+///     .follow(&follow);
+///
+/// // from another thread, once the file is complete...
+/// follow.end();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Follow {
+    ended: Arc<AtomicBool>,
+    pub(crate) interval: Duration,
+}
+
+impl Follow {
+    /// Create a new follow handle.
+    pub fn new() -> Self {
+        Self {
+            ended: Arc::new(AtomicBool::new(false)),
+            interval: FOLLOW_INTERVAL_DEFAULT,
+        }
+    }
+
+    /// Set the interval between two polls of the input.
+    ///
+    /// By default, the interval follows the [`FOLLOW_INTERVAL_DEFAULT`].
+    pub fn interval(
+        mut self,
+        interval: Duration,
+    ) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Signal that the input is complete.
+    ///
+    /// The process reads the remaining data and then finishes.
+    pub fn end(&self) {
+        self.ended.store(true, Ordering::Release);
+    }
+
+    /// Check whether the input is complete.
+    pub fn is_ended(&self) -> bool {
+        self.ended.load(Ordering::Acquire)
+    }
+}
+
+impl Default for Follow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,470 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, RecvError, Sender, SyncSender},
+    },
+    thread::{self, JoinHandle},
+};
+
+#[cfg(feature = "blake3")]
+use crate::blake3::{self, Blake3};
+
+/// Algorithm to hash the chunks with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// SHA-256.
+    #[default]
+    Sha256,
+    /// BLAKE3, available with the `blake3` feature.
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Get the algorithm from code.
+    pub fn from_code<C: AsRef<str>>(code: C) -> Option<Self> {
+        match code.as_ref() {
+            | "sha256" => Some(Self::Sha256),
+            #[cfg(feature = "blake3")]
+            | "blake3" => Some(Self::Blake3),
+            | _ => None,
+        }
+    }
+
+    /// Get the code of the algorithm as `&str`.
+    pub fn as_code(&self) -> &str {
+        match self {
+            | Self::Sha256 => "sha256",
+            #[cfg(feature = "blake3")]
+            | Self::Blake3 => "blake3",
+        }
+    }
+
+    /// Get the code of the algorithm as `String`.
+    pub fn to_code(&self) -> String {
+        self.as_code().to_string()
+    }
+
+    /// Create an incremental hasher of the algorithm.
+    pub(crate) fn hasher(&self) -> Hasher {
+        match self {
+            | Self::Sha256 => Hasher::Sha256(Sha256::new()),
+            #[cfg(feature = "blake3")]
+            | Self::Blake3 => Hasher::Blake3(Blake3::new()),
+        }
+    }
+
+    /// Hash data and get the digest.
+    pub(crate) fn digest(
+        &self,
+        data: &[u8],
+    ) -> [u8; 32] {
+        let mut hasher: Hasher = self.hasher();
+
+        hasher.update(data);
+
+        hasher.finalize()
+    }
+
+    /// Hash data with up to `threads` threads and get the digest.
+    ///
+    /// Only BLAKE3 hashes in parallel, SHA-256 is sequential.
+    #[cfg_attr(not(feature = "blake3"), allow(unused_variables))]
+    pub(crate) fn digest_parallel(
+        &self,
+        data: &[u8],
+        threads: usize,
+    ) -> [u8; 32] {
+        match self {
+            | Self::Sha256 => self.digest(data),
+            #[cfg(feature = "blake3")]
+            | Self::Blake3 => blake3::hash_parallel(data, threads),
+        }
+    }
+
+    /// Hash data and encode it as a lowercase hex string.
+    pub(crate) fn hex(
+        &self,
+        data: &[u8],
+    ) -> String {
+        let mut hasher: Hasher = self.hasher();
+
+        hasher.update(data);
+
+        hasher.finalize_hex()
+    }
+}
+
+/// Incremental hasher of any algorithm.
+#[derive(Debug, Clone)]
+pub(crate) enum Hasher {
+    Sha256(Sha256),
+    #[cfg(feature = "blake3")]
+    Blake3(Blake3),
+}
+
+impl Hasher {
+    /// Feed data into the hasher.
+    pub(crate) fn update(
+        &mut self,
+        data: &[u8],
+    ) {
+        match self {
+            | Self::Sha256(h) => h.update(data),
+            #[cfg(feature = "blake3")]
+            | Self::Blake3(h) => h.update(data),
+        }
+    }
+
+    /// Complete the hashing and get the digest.
+    pub(crate) fn finalize(self) -> [u8; 32] {
+        match self {
+            | Self::Sha256(h) => h.finalize(),
+            #[cfg(feature = "blake3")]
+            | Self::Blake3(h) => h.finalize(),
+        }
+    }
+
+    /// Complete the hashing and get the digest as a lowercase hex string.
+    pub(crate) fn finalize_hex(self) -> String {
+        to_hex(&self.finalize())
+    }
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1,
+    0x923f82a4, 0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147,
+    0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+    0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const H: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c,
+    0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Incremental SHA-256 hasher.
+#[derive(Debug, Clone)]
+pub(crate) struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_len: usize,
+    total_len: u64,
+}
+
+impl Sha256 {
+    /// Create a new hasher.
+    pub(crate) fn new() -> Self {
+        Self { state: H, block: [0; 64], block_len: 0, total_len: 0 }
+    }
+
+    /// Feed data into the hasher.
+    pub(crate) fn update(
+        &mut self,
+        mut data: &[u8],
+    ) {
+        self.total_len += data.len() as u64;
+
+        // fill the pending block first
+        if self.block_len > 0 {
+            let take: usize = (64 - self.block_len).min(data.len());
+
+            self.block[self.block_len..self.block_len + take]
+                .copy_from_slice(&data[..take]);
+            self.block_len += take;
+            data = &data[take..];
+
+            if self.block_len < 64 {
+                return;
+            }
+
+            let block: [u8; 64] = self.block;
+            self.compress(&block);
+            self.block_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(64);
+
+        for block in &mut blocks {
+            self.compress(block);
+        }
+
+        let rest: &[u8] = blocks.remainder();
+
+        self.block[..rest.len()].copy_from_slice(rest);
+        self.block_len = rest.len();
+    }
+
+    /// Complete the hashing and get the digest.
+    pub(crate) fn finalize(mut self) -> [u8; 32] {
+        let bit_len: u64 = self.total_len.wrapping_mul(8);
+
+        let mut padding: [u8; 72] = [0; 72];
+        padding[0] = 0x80;
+
+        let pad_len: usize = if self.block_len < 56 {
+            56 - self.block_len
+        } else {
+            120 - self.block_len
+        };
+
+        padding[pad_len..pad_len + 8].copy_from_slice(&bit_len.to_be_bytes());
+
+        // the length is not part of the message
+        let total_len: u64 = self.total_len;
+        self.update(&padding[..pad_len + 8]);
+        self.total_len = total_len;
+
+        let mut digest: [u8; 32] = [0; 32];
+
+        for (i, word) in self.state.iter().enumerate() {
+            digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+        }
+
+        digest
+    }
+
+    fn compress(
+        &mut self,
+        block: &[u8],
+    ) {
+        let mut w: [u32; 64] = [0; 64];
+
+        for (i, bytes) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        for i in 16..64 {
+            let s0: u32 = w[i - 15].rotate_right(7)
+                ^ w[i - 15].rotate_right(18)
+                ^ (w[i - 15] >> 3);
+            let s1: u32 = w[i - 2].rotate_right(17)
+                ^ w[i - 2].rotate_right(19)
+                ^ (w[i - 2] >> 10);
+
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] =
+            self.state;
+
+        for i in 0..64 {
+            let s1: u32 =
+                e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch: u32 = (e & f) ^ (!e & g);
+            let t1: u32 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0: u32 =
+                a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj: u32 = (a & b) ^ (a & c) ^ (b & c);
+            let t2: u32 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+}
+
+/// Encode bytes as a lowercase hex string.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    const CHARS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut hex: String = String::with_capacity(bytes.len() * 2);
+
+    for b in bytes {
+        hex.push(CHARS[(b >> 4) as usize] as char);
+        hex.push(CHARS[(b & 0xf) as usize] as char);
+    }
+
+    hex
+}
+
+/// Decode a 32-byte digest from a hex string.
+pub(crate) fn from_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut bytes: [u8; 32] = [0; 32];
+
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    Some(bytes)
+}
+
+/// Hash data with SHA-256 and encode it as a lowercase hex string.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher: Sha256 = Sha256::new();
+
+    hasher.update(data);
+
+    to_hex(&hasher.finalize())
+}
+
+/// Get the default number of workers hashing chunks in parallel.
+pub(crate) fn default_workers() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// A chunk to hash and its index.
+type Job = (usize, Vec<u8>);
+
+/// Chunks hashed by a fixed pool of worker threads while the next chunks
+/// are processed.
+///
+/// At most `workers` chunks wait for a worker, so the memory held by
+/// pending chunks stays bounded. BLAKE3 chunks are hashed one at a time by
+/// a single worker using `workers` threads, as a chunk is split into
+/// subtrees hashed in parallel.
+#[derive(Debug)]
+pub(crate) struct HashPipeline {
+    algorithm: HashAlgorithm,
+    /// The number of worker threads of the pool.
+    pool: usize,
+    /// The number of threads hashing each chunk.
+    threads: usize,
+    jobs: Option<SyncSender<Job>>,
+    queue: Arc<Mutex<Receiver<Job>>>,
+    results_tx: Sender<(usize, String)>,
+    results: Receiver<(usize, String)>,
+    handles: Vec<JoinHandle<()>>,
+    pushed: usize,
+}
+
+impl HashPipeline {
+    pub(crate) fn new(
+        algorithm: HashAlgorithm,
+        workers: usize,
+    ) -> Self {
+        let workers: usize = workers.max(1);
+
+        let (pool, threads): (usize, usize) = match algorithm {
+            | HashAlgorithm::Sha256 => (workers, 1),
+            #[cfg(feature = "blake3")]
+            | HashAlgorithm::Blake3 => (1, workers),
+        };
+
+        let (jobs, queue) = mpsc::sync_channel::<Job>(workers);
+        let (results_tx, results) = mpsc::channel::<(usize, String)>();
+
+        Self {
+            algorithm,
+            pool,
+            threads,
+            jobs: Some(jobs),
+            queue: Arc::new(Mutex::new(queue)),
+            results_tx,
+            results,
+            handles: Vec::with_capacity(pool),
+            pushed: 0,
+        }
+    }
+
+    /// Hash a chunk, waiting for a worker if all of them are busy.
+    pub(crate) fn push(
+        &mut self,
+        chunk: Vec<u8>,
+    ) -> thread::Result<()> {
+        // the workers are started as the chunks arrive
+        if self.handles.len() < self.pool.min(self.pushed + 1) {
+            let queue: Arc<Mutex<Receiver<Job>>> = self.queue.clone();
+            let results: Sender<(usize, String)> = self.results_tx.clone();
+            let algorithm: HashAlgorithm = self.algorithm;
+            let threads: usize = self.threads;
+
+            self.handles.push(thread::spawn(move || {
+                work(&queue, &results, algorithm, threads)
+            }));
+        }
+
+        let sent: bool = self
+            .jobs
+            .as_ref()
+            .is_some_and(|tx| tx.send((self.pushed, chunk)).is_ok());
+
+        // the workers only stop early on a panic
+        if !sent {
+            self.join()?;
+
+            return Err(Box::new("the hash workers stopped"));
+        }
+
+        self.pushed += 1;
+
+        Ok(())
+    }
+
+    /// Wait for all the chunks and get their hashes in order.
+    pub(crate) fn finish(mut self) -> thread::Result<Vec<String>> {
+        // the workers stop once the queue is closed and empty
+        self.jobs = None;
+        self.join()?;
+
+        let mut hashes: Vec<String> = vec![String::new(); self.pushed];
+
+        for (index, hash) in self.results.try_iter() {
+            hashes[index] = hash;
+        }
+
+        Ok(hashes)
+    }
+
+    fn join(&mut self) -> thread::Result<()> {
+        for handle in self.handles.drain(..) {
+            handle.join()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash the chunks of the queue until it is closed.
+fn work(
+    queue: &Mutex<Receiver<Job>>,
+    results: &Sender<(usize, String)>,
+    algorithm: HashAlgorithm,
+    threads: usize,
+) {
+    loop {
+        // the lock is released before hashing, so the workers run together
+        let job: Result<Job, RecvError> =
+            queue.lock().unwrap_or_else(|e| e.into_inner()).recv();
+
+        let (index, chunk): Job = match job {
+            | Ok(job) => job,
+            | Err(_) => break,
+        };
+
+        let hash: String = to_hex(&algorithm.digest_parallel(&chunk, threads));
+
+        if results.send((index, hash)).is_err() {
+            break;
+        }
+    }
+}
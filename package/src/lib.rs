@@ -71,6 +71,125 @@ pub mod check;
 /// Merge module.
 pub mod merge;
 
+/// Lock module.
+pub mod lock;
+
+/// Chunk module.
+pub mod chunk;
+
+/// Cleanup module.
+pub mod cleanup;
+
+/// Clock module.
+pub mod clock;
+
+/// Conformance module.
+pub mod conformance;
+
+/// Control module.
+pub mod control;
+
+/// Durability module.
+pub mod durability;
+
+/// Error module.
+pub mod error;
+
+/// Follow module.
+pub mod follow;
+
+/// Hash module.
+pub mod hash;
+
+/// Manifest module.
+///
+/// Reading and writing the manifests as JSON, and the manifest options of
+/// the processes, require the `manifest` feature:
+///
+/// ```toml
+/// [dependencies]
+/// filego = { version = "*", features = ["manifest"] }
+/// ```
+pub mod manifest;
+
+/// Memory module.
+pub mod memory;
+
+/// Migrate module.
+pub mod migrate;
+
+/// Naming module.
+pub mod naming;
+
+/// Pipeline module.
+pub mod pipeline;
+
+/// Plan module.
+///
+/// Pure functions planning the processes without touching the file system,
+/// suitable for property-based testing and fuzzing.
+pub mod plan;
+
+/// Priority module.
+pub mod priority;
+
+/// Progress module.
+pub mod progress;
+
+/// Rechunk module.
+pub mod rechunk;
+
+/// Reader module.
+pub mod reader;
+
+/// Run ID module.
+pub mod run_id;
+
+/// Scope module.
+pub mod scope;
+
+/// Sniff module.
+pub mod sniff;
+
+/// Utilities for testing with generated files.
+///
+/// To use it, add the following code to the `Cargo.toml` file:
+///
+/// ```toml
+/// [dev-dependencies]
+/// filego = { version = "*", features = ["test_util"] }
+/// ```
+#[cfg(feature = "test_util")]
+pub mod test_util;
+
+/// Advice functions.
+pub(crate) mod advise;
+
+/// BLAKE3 functions.
+#[cfg(feature = "blake3")]
+pub(crate) mod blake3;
+
+/// Copy functions.
+#[cfg(target_os = "linux")]
+pub(crate) mod copy;
+
+/// Device functions.
+pub(crate) mod device;
+
+/// Direct IO functions.
+#[cfg(target_os = "linux")]
+pub(crate) mod direct;
+
+/// Memory mapping functions.
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+pub(crate) mod mmap;
+
+/// Throttle functions.
+pub(crate) mod throttle;
+
+/// Vectored write functions.
+pub(crate) mod vectored;
+
 /// Functions implemented with `async_std`.
 #[cfg(feature = "async_std")]
 pub(crate) mod async_std;
@@ -79,8 +198,19 @@ pub(crate) mod async_std;
 #[cfg(feature = "tokio")]
 pub(crate) mod tokio;
 
+pub use error::Error;
+pub use scope::scope;
+
 /// The default chunk size in bytes.
 pub const CHUNK_SIZE_DEFAULT: usize = 2 * 1024 * 1024;
 
 /// The default maximum size of the buffer capacity in bytes.
 pub const BUFFER_CAPACITY_MAX_DEFAULT: usize = 10 * 1024 * 1024;
+
+/// The default maximum number of chunks of a merge process running with
+/// untrusted inputs.
+pub const MAX_CHUNKS_UNTRUSTED_DEFAULT: usize = 10_000;
+
+/// The default maximum size in bytes of the file merged by a merge process
+/// running with untrusted inputs.
+pub const MAX_FILE_SIZE_UNTRUSTED_DEFAULT: u64 = 64 * 1024 * 1024 * 1024;
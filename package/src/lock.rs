@@ -0,0 +1,62 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The name of the lock file created in a locked directory.
+pub const LOCK_FILE_NAME: &str = ".filego.lock";
+
+/// Lock of a chunk directory, released on drop.
+///
+/// The lock is a file created exclusively in the directory, so it is shared
+/// between processes. The check and merge processes take it when locking is
+/// enabled, and an uploader writing chunks can take it as well to prevent
+/// them from running too early.
+///
+/// A lock file left behind by a crashed process must be removed manually,
+/// or with [`cleanup_orphans`](crate::cleanup::cleanup_orphans).
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::lock::DirLock;
+///
+/// let lock: DirLock =
+///     DirLock::acquire(PathBuf::from("path").join("to").join("dir"))
+///         .unwrap();
+///
+/// // write chunks...
+///
+/// drop(lock);
+/// ```
+#[derive(Debug)]
+pub struct DirLock {
+    path: PathBuf,
+}
+
+impl DirLock {
+    /// Acquire the lock of a directory.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::AlreadyExists`] when the
+    /// directory is already locked.
+    pub fn acquire<D: AsRef<Path>>(dir: D) -> io::Result<Self> {
+        let path: PathBuf = dir.as_ref().join(LOCK_FILE_NAME);
+
+        fs::OpenOptions::new().write(true).create_new(true).open(&path)?;
+
+        Ok(Self { path })
+    }
+
+    /// Get the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "manifest")]
+use std::{
+    fs,
+    io::{self, Read as _},
+    path::Path,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{chunk::ChunkInfo, hash::HashAlgorithm, naming, run_id::RunId};
+
+/// The name of the manifest file written in the output directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// The version of the manifest format written by this crate.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// The default maximum size in bytes of a manifest file to read, see
+/// [`Manifest::read_max`].
+pub const MANIFEST_SIZE_MAX_DEFAULT: u64 = 16 * 1024 * 1024;
+
+/// The metadata key of the ID of the split process, see [`RunId`].
+pub const RUN_ID_KEY: &str = "run_id";
+
+/// A chunk recorded in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ManifestChunk {
+    /// Index of the chunk.
+    pub index: usize,
+    /// File name of the chunk in the chunk directory.
+    pub name: String,
+    /// Size of the chunk in bytes.
+    pub size: usize,
+    /// SHA-256 hash of the chunk in hex, if it was hashed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub sha256: Option<String>,
+    /// BLAKE3 hash of the chunk in hex, if it was hashed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub blake3: Option<String>,
+}
+
+impl ManifestChunk {
+    /// Get the hash of the chunk to verify it with, and its algorithm.
+    ///
+    /// BLAKE3 is preferred when the `blake3` feature is enabled, otherwise
+    /// BLAKE3 hashes are ignored.
+    pub fn hash(&self) -> Option<(HashAlgorithm, &str)> {
+        #[cfg(feature = "blake3")]
+        if let Some(ref h) = self.blake3 {
+            return Some((HashAlgorithm::Blake3, h));
+        }
+
+        self.sha256.as_deref().map(|h| (HashAlgorithm::Sha256, h))
+    }
+
+    /// Check whether two chunks have the same size and a common hash.
+    fn same_content(
+        &self,
+        other: &ManifestChunk,
+    ) -> bool {
+        let same = |a: &Option<String>, b: &Option<String>| match (a, b) {
+            | (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            | _ => false,
+        };
+
+        self.size == other.size
+            && (same(&self.sha256, &other.sha256)
+                || same(&self.blake3, &other.blake3))
+    }
+}
+
+/// Description of a chunk directory, written by the split process.
+///
+/// It makes the chunk directory self-describing, so the receiver of the
+/// chunks does not need the [`SplitResult`](crate::split::SplitResult) to
+/// check and merge them.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::manifest::{MANIFEST_FILE_NAME, Manifest};
+///
+/// let manifest: Manifest = Manifest::read(
+///     PathBuf::from("path").join("to").join("dir").join(MANIFEST_FILE_NAME),
+/// )
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Manifest {
+    /// Version of the manifest format.
+    pub version: u32,
+    /// Size of the original file in bytes.
+    pub file_size: usize,
+    /// Maximum size of each chunk in bytes.
+    pub chunk_size: usize,
+    /// The total number of chunks.
+    pub total_chunks: usize,
+    /// The chunks in order.
+    pub chunks: Vec<ManifestChunk>,
+    /// Metadata of the chunk set, e.g. the uploader or the content type.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Parse a manifest from JSON.
+    #[cfg(feature = "manifest")]
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json).map_err(io::Error::from)
+    }
+
+    /// Serialize the manifest to JSON.
+    #[cfg(feature = "manifest")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Read a manifest from a file.
+    ///
+    /// A file larger than [`MANIFEST_SIZE_MAX_DEFAULT`] is not read, see
+    /// [`read_max`](Self::read_max).
+    #[cfg(feature = "manifest")]
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_max(path, MANIFEST_SIZE_MAX_DEFAULT)
+    }
+
+    /// Read a manifest from a file of at most `max_size` bytes.
+    ///
+    /// A larger file returns an error of kind
+    /// [`FileTooLarge`](io::ErrorKind::FileTooLarge) before its content is
+    /// read, so a crafted manifest cannot exhaust the memory.
+    #[cfg(feature = "manifest")]
+    pub fn read_max<P: AsRef<Path>>(
+        path: P,
+        max_size: u64,
+    ) -> io::Result<Self> {
+        let too_large = || {
+            io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                "the manifest exceeds the size limit",
+            )
+        };
+
+        let file: fs::File = fs::File::open(path)?;
+
+        if file.metadata()?.len() > max_size {
+            return Err(too_large());
+        }
+
+        // the file may grow while it is read
+        let mut json: String = String::new();
+
+        file.take(max_size.saturating_add(1)).read_to_string(&mut json)?;
+
+        if json.len() as u64 > max_size {
+            return Err(too_large());
+        }
+
+        Self::from_json(&json)
+    }
+
+    /// Write the manifest to a file.
+    #[cfg(feature = "manifest")]
+    pub fn write<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    /// Get the ID of the split process which wrote the chunks, if recorded.
+    pub fn run_id(&self) -> Option<RunId> {
+        self.metadata.get(RUN_ID_KEY).and_then(|id| id.parse().ok())
+    }
+
+    /// Find the first chunk name equal to a preceding one when the case is
+    /// ignored, see [`naming::find_collision`].
+    pub fn find_collision(&self) -> Option<&str> {
+        naming::find_collision(self.chunks.iter().map(|c| c.name.as_str()))
+    }
+
+    /// Get the chunks with their offset in the original file.
+    ///
+    /// The offsets are derived from the size of the preceding chunks, so
+    /// the chunks may vary in size.
+    pub fn chunk_infos(&self) -> Vec<ChunkInfo> {
+        let mut offset: usize = 0;
+
+        self.chunks
+            .iter()
+            .map(|chunk| {
+                let info: ChunkInfo = ChunkInfo {
+                    index: chunk.index,
+                    offset,
+                    size: chunk.size,
+                    name: chunk.name.clone(),
+                    hash: chunk.hash().map(|(_, h)| h.to_string()),
+                };
+
+                offset += chunk.size;
+
+                info
+            })
+            .collect()
+    }
+
+    /// Get the delta from an older manifest of the same file to this one.
+    ///
+    /// A chunk is unchanged when it has the same size and the same SHA-256
+    /// or BLAKE3 hash in both manifests. Chunks without a common hash are
+    /// always reported as changed, as their content cannot be compared.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::path::PathBuf;
+    ///
+    /// use filego::manifest::{Manifest, ManifestDelta};
+    ///
+    /// let old: Manifest = Manifest::read(PathBuf::from("old.json")).unwrap();
+    /// let new: Manifest = Manifest::read(PathBuf::from("new.json")).unwrap();
+    ///
+    /// let delta: ManifestDelta = new.diff(&old);
+    /// ```
+    pub fn diff(
+        &self,
+        old: &Manifest,
+    ) -> ManifestDelta {
+        let len: usize = self.chunks.len().max(old.chunks.len());
+
+        let changes: Vec<ManifestChange> = (0..len)
+            .filter_map(|index| {
+                let old: Option<&ManifestChunk> = old.chunks.get(index);
+                let new: Option<&ManifestChunk> = self.chunks.get(index);
+
+                if let (Some(o), Some(n)) = (old, new) {
+                    if o.same_content(n) {
+                        return None;
+                    }
+                }
+
+                Some(ManifestChange {
+                    index,
+                    old: old.cloned(),
+                    new: new.cloned(),
+                })
+            })
+            .collect();
+
+        ManifestDelta {
+            version: MANIFEST_VERSION,
+            file_size: self.file_size,
+            chunk_size: self.chunk_size,
+            total_chunks: self.total_chunks,
+            changes,
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+/// A chunk changed between two manifests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ManifestChange {
+    /// Index of the chunk.
+    pub index: usize,
+    /// The chunk in the old manifest, `None` if it is added.
+    pub old: Option<ManifestChunk>,
+    /// The chunk in the new manifest, `None` if it is removed.
+    pub new: Option<ManifestChunk>,
+}
+
+/// Delta between two manifests of the same file.
+///
+/// It records the chunks changed since an older manifest, so a sync
+/// protocol can transmit it instead of the full list of chunks. The new
+/// manifest is rebuilt from the old one with [`apply`](Self::apply).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ManifestDelta {
+    /// Version of the manifest format.
+    pub version: u32,
+    /// Size of the new file in bytes.
+    pub file_size: usize,
+    /// Maximum size of each chunk in bytes in the new manifest.
+    pub chunk_size: usize,
+    /// The total number of chunks in the new manifest.
+    pub total_chunks: usize,
+    /// The changed chunks in order.
+    pub changes: Vec<ManifestChange>,
+    /// Metadata of the chunk set in the new manifest.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ManifestDelta {
+    /// Parse a delta from JSON.
+    #[cfg(feature = "manifest")]
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json).map_err(io::Error::from)
+    }
+
+    /// Serialize the delta to JSON.
+    #[cfg(feature = "manifest")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Get the indices of the changed chunks which exist in the new manifest.
+    pub fn changed(&self) -> Vec<usize> {
+        self.changes
+            .iter()
+            .filter(|c| c.new.is_some())
+            .map(|c| c.index)
+            .collect()
+    }
+
+    /// Apply the delta to the old manifest to get the new one.
+    pub fn apply(
+        &self,
+        old: &Manifest,
+    ) -> Manifest {
+        let mut chunks: Vec<ManifestChunk> = old.chunks.clone();
+
+        for change in self.changes.iter() {
+            match change.new {
+                | Some(ref new) if change.index < chunks.len() => {
+                    chunks[change.index] = new.clone();
+                },
+                | Some(ref new) => chunks.push(new.clone()),
+                | None => {},
+            }
+        }
+
+        chunks.truncate(self.total_chunks);
+
+        Manifest {
+            version: self.version,
+            file_size: self.file_size,
+            chunk_size: self.chunk_size,
+            total_chunks: self.total_chunks,
+            chunks,
+            metadata: self.metadata.clone(),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(target_os = "linux")]
+use std::fs;
+
+use crate::BUFFER_CAPACITY_MAX_DEFAULT;
+
+/// The smallest buffer capacity in bytes derived from the memory.
+pub const BUFFER_CAPACITY_MIN: usize = 64 * 1024;
+
+/// Get the memory available to the current process in bytes.
+///
+/// On Linux, it is the available memory of the system, limited by the
+/// memory limit of the cgroup when the process runs in a container.
+/// Returns `None` when it cannot be determined, e.g. on other platforms.
+pub fn available() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        let system: Option<usize> = meminfo_available();
+        let cgroup: Option<usize> = cgroup_available();
+
+        match (system, cgroup) {
+            | (Some(a), Some(b)) => Some(a.min(b)),
+            | (a, b) => a.or(b),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Derive a buffer capacity from the available memory.
+///
+/// A quarter of the [`available`] memory is shared by the buffers of
+/// `concurrency` processes running at the same time, each holding a
+/// reader and a writer buffer. The capacity is clamped between
+/// [`BUFFER_CAPACITY_MIN`] and [`BUFFER_CAPACITY_MAX_DEFAULT`], which is
+/// also used when the available memory cannot be determined.
+///
+/// ## Example
+///
+/// ```
+/// use filego::{BUFFER_CAPACITY_MAX_DEFAULT, memory};
+///
+/// let capacity: usize = memory::buffer_capacity(8);
+///
+/// assert!(capacity <= BUFFER_CAPACITY_MAX_DEFAULT);
+/// ```
+pub fn buffer_capacity(concurrency: usize) -> usize {
+    match available() {
+        | Some(bytes) => capacity_from(bytes, concurrency),
+        | None => BUFFER_CAPACITY_MAX_DEFAULT,
+    }
+}
+
+fn capacity_from(
+    available: usize,
+    concurrency: usize,
+) -> usize {
+    let buffers: usize = concurrency.max(1).saturating_mul(2);
+
+    (available / 4 / buffers)
+        .clamp(BUFFER_CAPACITY_MIN, BUFFER_CAPACITY_MAX_DEFAULT)
+}
+
+#[cfg(target_os = "linux")]
+fn meminfo_available() -> Option<usize> {
+    let meminfo: String = fs::read_to_string("/proc/meminfo").ok()?;
+
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|v| v.trim().strip_suffix("kB"))
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .map(|kb| kb.saturating_mul(1024))
+}
+
+#[cfg(target_os = "linux")]
+fn cgroup_available() -> Option<usize> {
+    fn read(path: &str) -> Option<usize> {
+        fs::read_to_string(path).ok()?.trim().parse::<usize>().ok()
+    }
+
+    // cgroup v2, then v1; an unlimited v2 cgroup reads "max"
+    let (limit, usage): (usize, usize) = match (
+        read("/sys/fs/cgroup/memory.max"),
+        read("/sys/fs/cgroup/memory.current"),
+    ) {
+        | (Some(limit), Some(usage)) => (limit, usage),
+        | _ => (
+            read("/sys/fs/cgroup/memory/memory.limit_in_bytes")?,
+            read("/sys/fs/cgroup/memory/memory.usage_in_bytes")?,
+        ),
+    };
+
+    Some(limit.saturating_sub(usage))
+}
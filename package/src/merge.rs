@@ -1,10 +1,53 @@
 use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
     fs::{self, ReadDir},
-    io::{self, Read as _, Write as _},
-    path::{Path, PathBuf},
+    hash::{BuildHasher as _, RandomState},
+    io::{self, ErrorKind, Read as _, Seek as _, SeekFrom, Write as _},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
 };
 
-use crate::BUFFER_CAPACITY_MAX_DEFAULT;
+#[cfg(all(feature = "rayon", any(unix, windows)))]
+use crate::chunk::ChunkInfo;
+
+#[cfg(any(
+    all(feature = "rayon", any(unix, windows)),
+    all(feature = "mmap", target_os = "linux")
+))]
+use crate::cleanup;
+#[cfg(target_os = "linux")]
+use crate::direct::{self, AlignedBuffer};
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+use crate::mmap::Mmap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BUFFER_CAPACITY_MAX_DEFAULT, MAX_CHUNKS_UNTRUSTED_DEFAULT,
+    MAX_FILE_SIZE_UNTRUSTED_DEFAULT, advise,
+    clock::{Clock, SystemClock},
+    control::{Control, ControlGuard},
+    device,
+    durability::Durability,
+    error::IoError,
+    follow::Follow,
+    hash::{self, HashAlgorithm, Hasher, Sha256},
+    lock::{DirLock, LOCK_FILE_NAME},
+    manifest::Manifest,
+    memory,
+    naming::{self, ChunkNamer, Plain},
+    priority::{Priority, PriorityGuard},
+    progress::{Progress, ProgressCallback, Tracker},
+    reader::ChunkedReader,
+    run_id::RunId,
+    sniff,
+    throttle::Throttle,
+    vectored::VectoredWriter,
+};
 
 /// Run asynchronously with `async_std` feature.
 ///
@@ -32,20 +75,72 @@ pub mod tokio {
     pub use crate::tokio::merge::MergeAsyncExt;
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What the merge process does when the output file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum IfExists {
+    /// The output file is replaced.
+    #[default]
+    Overwrite,
+    /// The merge fails with [`MergeError::OutFileExists`].
+    Error,
+    /// The output file is kept and the merge is skipped.
+    Skip,
+}
+
+/// Result of the merge process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MergeResult {
+    /// Size of the merged file in bytes.
+    pub file_size: usize,
+    /// The total number of chunks merged into the file.
+    pub total_chunks: usize,
+    /// SHA-256 hash of the merged file in hex, if hashing is enabled.
+    pub sha256: Option<String>,
+    /// Path of the merged file.
+    pub out_file: PathBuf,
+    /// Whether the merge was skipped, as the output file was already equal
+    /// the chunks.
+    pub skipped: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum MergeError {
     InDirNotFound,
     InDirNotDir,
     InDirNotSet,
-    InDirNotRead,
+    InDirNotRead { path: PathBuf, source: IoError },
     InDirNoFile,
-    InFileNotOpened,
-    InFileNotRead,
-    OutDirNotCreated,
+    InDirNotContiguous,
+    InDirDuplicateIndex { index: usize },
+    InFileNotOpened { path: PathBuf, source: IoError },
+    InFileNotRead { path: PathBuf, source: IoError },
+    InFileNotSafe,
+    InFileNameInvalid,
+    OutDirNotCreated { path: PathBuf, source: IoError },
     OutFileNotSet,
-    OutFileNotRemoved,
-    OutFileNotOpened,
-    OutFileNotWritten,
+    OutFileNotSafe,
+    OutFileNameInvalid,
+    OutFileExists,
+    OutFileNotRemoved { path: PathBuf, source: IoError },
+    OutFileNotOpened { path: PathBuf, source: IoError },
+    OutFileNotWritten { path: PathBuf, source: IoError },
+    OutFileNotSynced { path: PathBuf, source: IoError },
+    PriorityNotSet { source: IoError },
+    TooManyChunks,
+    Locked,
+    LockNotCreated { path: PathBuf, source: IoError },
+    Cancelled,
+    ContentRejected,
+    FileTooLarge,
+    ChunkCorrupted { index: usize },
+    VerifyNotSet,
+    InsufficientSpace,
+    SinkNotWritten { index: usize, source: IoError },
 }
 
 impl MergeError {
@@ -55,15 +150,34 @@ impl MergeError {
             | Self::InDirNotFound => "in_dir_not_found",
             | Self::InDirNotDir => "in_dir_not_dir",
             | Self::InDirNotSet => "in_dir_not_set",
-            | Self::InDirNotRead => "in_dir_not_read",
+            | Self::InDirNotRead { .. } => "in_dir_not_read",
             | Self::InDirNoFile => "in_dir_no_file",
-            | Self::InFileNotOpened => "in_file_not_opened",
-            | Self::InFileNotRead => "in_file_not_read",
-            | Self::OutDirNotCreated => "out_dir_not_created",
+            | Self::InDirNotContiguous => "in_dir_not_contiguous",
+            | Self::InDirDuplicateIndex { .. } => "in_dir_duplicate_index",
+            | Self::InFileNotOpened { .. } => "in_file_not_opened",
+            | Self::InFileNotRead { .. } => "in_file_not_read",
+            | Self::InFileNotSafe => "in_file_not_safe",
+            | Self::InFileNameInvalid => "in_file_name_invalid",
+            | Self::OutDirNotCreated { .. } => "out_dir_not_created",
             | Self::OutFileNotSet => "out_file_not_set",
-            | Self::OutFileNotRemoved => "out_file_not_removed",
-            | Self::OutFileNotOpened => "out_file_not_opened",
-            | Self::OutFileNotWritten => "out_file_not_written",
+            | Self::OutFileNotSafe => "out_file_not_safe",
+            | Self::OutFileNameInvalid => "out_file_name_invalid",
+            | Self::OutFileExists => "out_file_exists",
+            | Self::OutFileNotRemoved { .. } => "out_file_not_removed",
+            | Self::OutFileNotOpened { .. } => "out_file_not_opened",
+            | Self::OutFileNotWritten { .. } => "out_file_not_written",
+            | Self::OutFileNotSynced { .. } => "out_file_not_synced",
+            | Self::PriorityNotSet { .. } => "priority_not_set",
+            | Self::TooManyChunks => "too_many_chunks",
+            | Self::Locked => "locked",
+            | Self::LockNotCreated { .. } => "lock_not_created",
+            | Self::Cancelled => "cancelled",
+            | Self::ContentRejected => "content_rejected",
+            | Self::FileTooLarge => "file_too_large",
+            | Self::ChunkCorrupted { .. } => "chunk_corrupted",
+            | Self::VerifyNotSet => "verify_not_set",
+            | Self::InsufficientSpace => "insufficient_space",
+            | Self::SinkNotWritten { .. } => "sink_not_written",
         }
     }
 
@@ -78,21 +192,68 @@ impl MergeError {
             | Self::InDirNotFound => "The input directory not found.",
             | Self::InDirNotDir => "The input directory is not a directory.",
             | Self::InDirNotSet => "The input directory is not set.",
-            | Self::InDirNotRead => "The input directory could not be read.",
+            | Self::InDirNotRead { .. } => {
+                "The input directory could not be read."
+            },
             | Self::InDirNoFile => "The input directory has no file.",
-            | Self::InFileNotOpened => "The input file could not be opened.",
-            | Self::InFileNotRead => "The input file could not be read.",
-            | Self::OutDirNotCreated => {
+            | Self::InDirNotContiguous => {
+                "The input directory has missing chunks."
+            },
+            | Self::InDirDuplicateIndex { .. } => {
+                "The input directory has several chunks with the same index."
+            },
+            | Self::InFileNotOpened { .. } => {
+                "The input file could not be opened."
+            },
+            | Self::InFileNotRead { .. } => "The input file could not be read.",
+            | Self::InFileNotSafe => "The input file is a symbolic link.",
+            | Self::InFileNameInvalid => {
+                "The input file name is not valid UTF-8."
+            },
+            | Self::OutDirNotCreated { .. } => {
                 "The output directory could not be created."
             },
             | Self::OutFileNotSet => "The output file is not set.",
-            | Self::OutFileNotRemoved => {
+            | Self::OutFileNotSafe => {
+                "The output file path contains a parent directory."
+            },
+            | Self::OutFileNameInvalid => {
+                "The output file name is not a valid file name on Windows."
+            },
+            | Self::OutFileExists => "The output file already exists.",
+            | Self::OutFileNotRemoved { .. } => {
                 "The output file could not be removed."
             },
-            | Self::OutFileNotOpened => "The output file could not be opened.",
-            | Self::OutFileNotWritten => {
+            | Self::OutFileNotOpened { .. } => {
+                "The output file could not be opened."
+            },
+            | Self::OutFileNotWritten { .. } => {
                 "The output file could not be written."
             },
+            | Self::OutFileNotSynced { .. } => {
+                "The output file could not be synced to the disk."
+            },
+            | Self::PriorityNotSet { .. } => "The priority could not be set.",
+            | Self::TooManyChunks => "The number of chunks exceeds the limit.",
+            | Self::Locked => {
+                "The input directory is locked by another process."
+            },
+            | Self::LockNotCreated { .. } => {
+                "The lock file could not be created."
+            },
+            | Self::Cancelled => "The process was cancelled.",
+            | Self::ContentRejected => "The content was rejected.",
+            | Self::FileTooLarge => "The merged file exceeds the size limit.",
+            | Self::ChunkCorrupted { .. } => {
+                "The hash of a chunk does not match the expected hash."
+            },
+            | Self::VerifyNotSet => {
+                "The expected hashes of the chunks are not set."
+            },
+            | Self::InsufficientSpace => {
+                "The output directory has not enough free space."
+            },
+            | Self::SinkNotWritten { .. } => "A sink could not be written.",
         }
     }
 
@@ -100,18 +261,140 @@ impl MergeError {
     pub fn to_message(&self) -> String {
         self.as_message().to_string()
     }
+
+    /// Get the path involved in the error, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.io().map(|(path, _)| path)
+    }
+
+    /// Get the path and the I/O error of the error, if any.
+    fn io(&self) -> Option<(&Path, &IoError)> {
+        match self {
+            | Self::InDirNotRead { path, source }
+            | Self::InFileNotOpened { path, source }
+            | Self::InFileNotRead { path, source }
+            | Self::OutDirNotCreated { path, source }
+            | Self::OutFileNotRemoved { path, source }
+            | Self::OutFileNotOpened { path, source }
+            | Self::OutFileNotWritten { path, source }
+            | Self::OutFileNotSynced { path, source }
+            | Self::LockNotCreated { path, source } => Some((path, source)),
+            | _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self.io() {
+            | Some((path, source)) => write!(
+                f,
+                "{} ({}: {})",
+                self.as_message(),
+                path.display(),
+                source
+            ),
+            | None => f.write_str(self.as_message()),
+        }
+    }
+}
+
+impl Error for MergeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            | Self::PriorityNotSet { source }
+            | Self::SinkNotWritten { source, .. } => {
+                Some(source.as_ref() as &(dyn Error + 'static))
+            },
+            | _ => self
+                .io()
+                .map(|(_, source)| source.as_ref() as &(dyn Error + 'static)),
+        }
+    }
+}
+
+/// Hook deciding whether merged content is written, see
+/// [`Merge::pre_finalize`].
+#[derive(Clone)]
+pub struct PreFinalize(Arc<dyn Fn(&mut ChunkedReader) -> bool + Send + Sync>);
+
+impl PreFinalize {
+    /// Create a new hook from a function.
+    pub fn new<F: Fn(&mut ChunkedReader) -> bool + Send + Sync + 'static>(
+        f: F
+    ) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub(crate) fn call(
+        &self,
+        reader: &mut ChunkedReader,
+    ) -> bool {
+        (self.0)(reader)
+    }
+}
+
+impl fmt::Debug for PreFinalize {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str("PreFinalize")
+    }
+}
+
+/// Writer receiving a copy of the merged content, see [`Merge::tee`].
+#[derive(Clone)]
+pub struct Sink(Arc<Mutex<dyn io::Write + Send>>);
+
+impl Sink {
+    /// Create a new sink from a writer.
+    pub fn new<W: io::Write + Send + 'static>(writer: W) -> Self {
+        Self(Arc::new(Mutex::new(writer)))
+    }
+
+    pub(crate) fn write_all(
+        &self,
+        content: &[u8],
+    ) -> io::Result<()> {
+        self.lock().write_all(content)
+    }
+
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        self.lock().flush()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, dyn io::Write + Send + 'static> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for Sink {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str("Sink")
+    }
 }
 
 /// Process to merge chunks from a directory to a path.
 ///
+/// The input directory must contain at least one chunk, otherwise
+/// [`MergeError::InDirNoFile`] is returned. To merge an empty file, split it
+/// with [`Split::empty_chunk`](crate::split::Split::empty_chunk) enabled.
+///
 /// ## Example
 ///
 /// ```no_run
 /// use std::path::PathBuf;
 ///
-/// use filego::merge::Merge;
+/// use filego::merge::{Merge, MergeResult};
 ///
-/// let result: bool = Merge::new()
+/// let result: MergeResult = Merge::new()
 ///     .in_dir(PathBuf::from("path").join("to").join("dir"))
 ///     .out_file(PathBuf::from("path").join("to").join("file"))
 ///     .run()
@@ -122,6 +405,44 @@ pub struct Merge {
     pub in_dir: Option<PathBuf>,
     pub out_file: Option<PathBuf>,
     pub cap_max: usize,
+    pub namer: Arc<dyn ChunkNamer>,
+    pub passthrough: bool,
+    pub mmap: bool,
+    pub max_chunks: Option<usize>,
+    pub contiguous: bool,
+    pub reject_unsafe_paths: bool,
+    pub reject_invalid_names: bool,
+    pub portable_names: bool,
+    pub lock: bool,
+    pub priority: Option<Priority>,
+    pub throttle: Option<u64>,
+    pub fill_buffer: bool,
+    pub vectored_buffers: usize,
+    pub kernel_copy: bool,
+    pub preallocate: bool,
+    pub cache_hints: bool,
+    pub direct_io: bool,
+    pub durability: Durability,
+    pub skip_merged: bool,
+    pub if_exists: IfExists,
+    pub resume: bool,
+    pub run_id: RunId,
+    pub check_space: bool,
+    pub concurrency: usize,
+    pub clock: Arc<dyn Clock>,
+    pub control: Option<Control>,
+    pub on_progress: Option<ProgressCallback>,
+    pub follow: Option<Follow>,
+    pub pre_finalize: Option<PreFinalize>,
+    pub sinks: Vec<Sink>,
+    pub allowed_types: Option<Vec<String>>,
+    pub max_file_size: Option<u64>,
+    pub hash: bool,
+    pub verify: Option<HashMap<usize, [u8; 32]>>,
+    pub verify_algorithm: HashAlgorithm,
+    pub verify_algorithms: HashMap<usize, HashAlgorithm>,
+    pub verify_sample: Option<usize>,
+    pub require_verify: bool,
 }
 
 impl Merge {
@@ -131,6 +452,44 @@ impl Merge {
             in_dir: None,
             out_file: None,
             cap_max: BUFFER_CAPACITY_MAX_DEFAULT,
+            namer: Arc::new(Plain),
+            passthrough: false,
+            mmap: false,
+            max_chunks: None,
+            contiguous: false,
+            reject_unsafe_paths: false,
+            reject_invalid_names: false,
+            portable_names: cfg!(windows),
+            lock: false,
+            priority: None,
+            throttle: None,
+            fill_buffer: false,
+            vectored_buffers: 1,
+            kernel_copy: true,
+            preallocate: true,
+            cache_hints: false,
+            direct_io: false,
+            durability: Durability::Flush,
+            skip_merged: false,
+            if_exists: IfExists::Overwrite,
+            resume: false,
+            run_id: RunId::generate(),
+            check_space: false,
+            concurrency: 1,
+            clock: Arc::new(SystemClock),
+            control: None,
+            on_progress: None,
+            follow: None,
+            pre_finalize: None,
+            sinks: Vec::new(),
+            allowed_types: None,
+            max_file_size: None,
+            hash: false,
+            verify: None,
+            verify_algorithm: HashAlgorithm::Sha256,
+            verify_algorithms: HashMap::new(),
+            verify_sample: None,
+            require_verify: false,
         }
     }
 
@@ -171,148 +530,2437 @@ impl Merge {
         self
     }
 
-    /// Run the merge process.
-    pub fn run(&self) -> Result<bool, MergeError> {
-        let in_dir: &Path = match self.in_dir {
-            | Some(ref p) => {
-                let p: &Path = p.as_ref();
+    /// Set the maximum size of the buffer capacity from the available memory.
+    ///
+    /// It is meant for memory-constrained environments, e.g. containers,
+    /// where `concurrency` processes run at the same time. See
+    /// [`memory::buffer_capacity`] for how it is derived.
+    pub fn max_buffer_capacity_auto(
+        mut self,
+        concurrency: usize,
+    ) -> Self {
+        self.cap_max = memory::buffer_capacity(concurrency);
+        self
+    }
 
-                // if in_dir not exists
-                if !p.exists() {
-                    return Err(MergeError::InDirNotFound);
-                }
+    /// Set whether the buffer is filled before it is written.
+    ///
+    /// By default, whatever a read returns is written right away. When
+    /// enabled, the chunks are read until the buffer is full or the chunk
+    /// ends, so the output is written in fewer and larger writes, which
+    /// helps on compressed or network file systems returning short reads.
+    pub fn fill_buffer(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.fill_buffer = enabled;
+        self
+    }
 
-                // if in_dir not a directory
-                if !p.is_dir() {
-                    return Err(MergeError::InDirNotDir);
-                }
+    /// Set the number of buffers gathered into a single write.
+    ///
+    /// By default, each buffer is written once it is read. With more
+    /// buffers, `count` buffers of up to
+    /// [`max_buffer_capacity`](Self::max_buffer_capacity) bytes are read,
+    /// e.g. across many small chunks, and written with a single vectored
+    /// write. It only applies to [`run`](Self::run) when the chunks are not
+    /// copied by the kernel, see [`kernel_copy`](Self::kernel_copy).
+    pub fn vectored_buffers(
+        mut self,
+        count: usize,
+    ) -> Self {
+        self.vectored_buffers = count;
+        self
+    }
 
-                p
-            },
-            | None => return Err(MergeError::InDirNotSet),
-        };
+    /// Set whether the chunks may be copied by the kernel.
+    ///
+    /// By default, when nothing inspects, paces, limits or cancels the
+    /// content, e.g. no hashing, each chunk is appended with [`io::copy`],
+    /// which uses `copy_file_range` or `sendfile` on Linux and falls back to
+    /// a buffered copy where they are not supported. When disabled, the
+    /// chunks are always copied through the buffer of
+    /// [`max_buffer_capacity`](Self::max_buffer_capacity), e.g. on a file
+    /// system known to handle these system calls poorly.
+    pub fn kernel_copy(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.kernel_copy = enabled;
+        self
+    }
 
-        let out_file: &Path = match self.out_file {
-            | Some(ref p) => p.as_ref(),
-            | None => return Err(MergeError::OutFileNotSet),
-        };
+    /// Set whether the space of the output file is reserved upfront.
+    ///
+    /// By default on Linux, the blocks of the whole output are allocated
+    /// before the chunks are written one at a time, so the output is less
+    /// fragmented and a full disk returns [`MergeError::OutFileNotWritten`]
+    /// before anything is written. Disable it on file systems where the
+    /// allocation misbehaves. It does nothing on other platforms.
+    pub fn preallocate(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.preallocate = enabled;
+        self
+    }
 
-        // check file size for buffer capacity
-        let input_size: usize = {
-            let read_dir: ReadDir = match fs::read_dir(in_dir) {
-                | Ok(read_dir) => read_dir,
-                | Err(_) => return Err(MergeError::InDirNotFound),
-            };
+    /// Set whether the page cache is hinted about how the files are used.
+    ///
+    /// By default, the chunks and the output file are cached like any other
+    /// file. When enabled, each chunk is hinted to be read sequentially and
+    /// its cached pages are dropped once it is merged, as are the pages of
+    /// the output already written back, so a large merge does not evict the
+    /// cache of the rest of the system. It is supported on Linux, and with
+    /// the synchronous `run` on Windows. It only applies when the chunks are
+    /// merged one at a time.
+    pub fn cache_hints(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.cache_hints = enabled;
+        self
+    }
 
-            let file: PathBuf = match read_dir
-                .filter_map(Result::ok)
-                .filter(|entry| entry.path().is_file())
-                .map(|entry| entry.path())
-                .next()
-            {
-                | Some(path) => path,
-                | None => return Err(MergeError::InDirNoFile),
-            };
+    /// Set whether the files are read and written without the page cache.
+    ///
+    /// By default, the files go through the page cache. When enabled on
+    /// Linux, the chunks are read and the output is written with
+    /// `O_DIRECT` through aligned buffers, so a merge of terabytes does not
+    /// touch the cache at all, only the last partial block of the output is
+    /// written through it. It falls back to the page cache when the file
+    /// system does not support it, e.g. `tmpfs`, and has no effect when the
+    /// output is hashed, verified, throttled or limited in size.
+    pub fn direct_io(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.direct_io = enabled;
+        self
+    }
 
-            match fs::metadata(&file) {
-                | Ok(metadata) => metadata.len() as usize,
-                | Err(_) => return Err(MergeError::InFileNotRead),
-            }
-        };
+    /// Set how far the output file is persisted before the merge returns.
+    ///
+    /// By default, the output is only flushed to the operating system, so it
+    /// may be lost on a power failure. With [`Durability::Fsync`], it is
+    /// written to the disk, and with [`Durability::FsyncDir`], so is the
+    /// directory containing it, so a crash after the merge returns cannot
+    /// lose it. A failure returns [`MergeError::OutFileNotSynced`].
+    pub fn durability(
+        mut self,
+        durability: Durability,
+    ) -> Self {
+        self.durability = durability;
+        self
+    }
 
-        let buffer_capacity: usize = input_size.min(self.cap_max);
+    /// Set the number of chunks merged at the same time.
+    ///
+    /// By default, the chunks are merged one at a time. With the `rayon`
+    /// feature, [`run`](Self::run) preallocates the output and writes each
+    /// chunk at its offset from a thread pool of `concurrency` threads,
+    /// unless the output is hashed, throttled or the chunks are followed.
+    /// Other implementations merge the chunks one at a time regardless.
+    pub fn concurrency(
+        mut self,
+        concurrency: usize,
+    ) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
 
-        // delete outpath target if exists
-        if out_file.exists() {
-            if out_file.is_dir() {
-                if fs::remove_dir_all(out_file).is_err() {
-                    return Err(MergeError::OutFileNotRemoved);
-                }
-            } else if fs::remove_file(out_file).is_err() {
-                return Err(MergeError::OutFileNotRemoved);
-            }
-        }
+    /// Set whether the merge is skipped when the output file is already
+    /// merged.
+    ///
+    /// By default, the output file is always written again. When enabled
+    /// and the output file has the size of the chunks, it is compared with
+    /// them before anything is removed, and kept if equal, with
+    /// [`MergeResult::skipped`] set, so a pipeline can be run again safely.
+    /// A chunk with an expected hash, see [`verify`](Self::verify), is
+    /// compared by hashing its section of the output file, so the chunk is
+    /// not read. Others are compared byte by byte.
+    pub fn skip_merged(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.skip_merged = enabled;
+        self
+    }
 
-        // create outpath
-        if let Some(parent) = out_file.parent() {
-            if fs::create_dir_all(parent).is_err() {
-                return Err(MergeError::OutDirNotCreated);
-            }
+    /// Set what to do when the output file already exists.
+    ///
+    /// By default, an existing output file is replaced. With
+    /// [`IfExists::Error`], the merge fails with
+    /// [`MergeError::OutFileExists`], and with [`IfExists::Skip`], the file
+    /// is kept and returned with [`MergeResult::skipped`] set, its
+    /// `total_chunks` being `0` when the chunks are followed. A directory at
+    /// the path of the output file is never removed, whatever the policy.
+    pub fn if_exists(
+        mut self,
+        policy: IfExists,
+    ) -> Self {
+        self.if_exists = policy;
+        self
+    }
+
+    /// Set whether the merge continues a partially written output file.
+    ///
+    /// By default, an existing output file is replaced. When enabled, the
+    /// chunks completely written to the output file, by its size, are kept
+    /// and the output file is truncated after them, then the next chunks
+    /// are appended, e.g. after a merge was cancelled or interrupted. The
+    /// kept content is not read again unless hashing is enabled, and is not
+    /// verified. The output file must have been written in order, like the
+    /// sequential merge does, and one longer than the chunks is replaced.
+    /// The parallel and memory-mapped merges write to a temporary file
+    /// renamed once complete, so they never leave a partial output. It
+    /// applies to the synchronous merge when the chunks are not followed.
+    pub fn resume(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.resume = enabled;
+        self
+    }
+
+    /// Get the number of chunks completely written to the output file by a
+    /// previous merge, `0` if it is not resumed.
+    pub(crate) fn resumed_chunks(
+        &self,
+        out_file: &Path,
+        sizes: &[u64],
+    ) -> usize {
+        if !self.resume {
+            return 0;
         }
 
-        let output: fs::File = match fs::OpenOptions::new()
-            .create(true)
-            .truncate(false)
-            .write(true)
-            .open(out_file)
-        {
-            | Ok(file) => file,
-            | Err(_) => return Err(MergeError::OutFileNotOpened),
+        let len: u64 = match fs::symlink_metadata(out_file) {
+            | Ok(m) if m.is_file() => m.len(),
+            | _ => return 0,
         };
 
-        // writer
-        let mut writer: io::BufWriter<fs::File> =
-            io::BufWriter::with_capacity(buffer_capacity, output);
+        // an output longer than the chunks is not made of them
+        if len > sizes.iter().sum() {
+            return 0;
+        }
 
-        // get inputs
-        let mut entries: Vec<PathBuf> = {
-            let read_dir: ReadDir = match fs::read_dir(in_dir) {
-                | Ok(read_dir) => read_dir,
-                | Err(_) => return Err(MergeError::InDirNotRead),
-            };
+        let mut offset: u64 = 0;
 
-            read_dir
-                .filter_map(Result::ok)
-                .filter(|entry| entry.path().is_file())
-                .map(|entry| entry.path())
-                .collect()
-        };
+        sizes
+            .iter()
+            .take_while(|size| {
+                offset += **size;
+                offset <= len
+            })
+            .count()
+    }
 
-        entries.sort_by_key(|entry| {
-            entry
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .parse::<usize>()
-                .unwrap()
-        });
+    /// Set the ID of the merge process.
+    ///
+    /// By default, a new process is assigned a random ID. It is passed in
+    /// every [`Progress`] report, see [`RunId`].
+    pub fn run_id(
+        mut self,
+        id: RunId,
+    ) -> Self {
+        self.run_id = id;
+        self
+    }
 
-        // merge
-        for entry in entries {
-            let input: fs::File =
-                match fs::OpenOptions::new().read(true).open(&entry) {
-                    | Ok(file) => file,
-                    | Err(_) => return Err(MergeError::InFileNotOpened),
-                };
+    /// Set whether the free space for the output file is checked before
+    /// anything is removed or written.
+    ///
+    /// By default, a full disk is only noticed when the output cannot be
+    /// written. When enabled, the merge fails early with
+    /// [`MergeError::InsufficientSpace`] if the file system has less free
+    /// space than the size of the chunks, counting the space of an existing
+    /// output file that is replaced. It is supported on Linux, and has no
+    /// effect when the chunks are followed.
+    pub fn check_space(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.check_space = enabled;
+        self
+    }
 
-            let mut reader: io::BufReader<fs::File> =
-                io::BufReader::with_capacity(buffer_capacity, input);
+    /// Set the naming convention of the chunks.
+    ///
+    /// By default, the chunks are named by their index with [`Plain`].
+    pub fn namer<N: ChunkNamer + 'static>(
+        mut self,
+        namer: N,
+    ) -> Self {
+        self.namer = Arc::new(namer);
+        self
+    }
 
-            let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+    /// Set whether a single chunk is moved instead of copied.
+    ///
+    /// When the input directory contains only one chunk and it is on the
+    /// same device as the output file, it is renamed to the output file. It
+    /// falls back to copying when the devices differ or renaming fails. Note
+    /// that the chunk is then no longer in the input directory.
+    pub fn passthrough(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.passthrough = enabled;
+        self
+    }
 
-            loop {
-                let read: usize = match reader.read(&mut buffer) {
-                    | Ok(read) => read,
-                    | Err(_) => return Err(MergeError::InFileNotRead),
-                };
+    /// Set whether the output file is mapped into memory.
+    ///
+    /// By default, the chunks are written through a buffer. When enabled
+    /// with the `mmap` feature on Linux, the output file is preallocated and
+    /// the chunks are read straight into the mapped output file, which is
+    /// faster on local SSDs. A cancellation then takes effect between the
+    /// chunks. It has no effect when the chunks are followed.
+    pub fn mmap(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.mmap = enabled;
+        self
+    }
 
-                if read == 0 {
-                    break;
-                }
+    /// Set the maximum number of chunks to merge.
+    ///
+    /// By default, the number of chunks is unlimited. When the input
+    /// directory contains more chunks, [`MergeError::TooManyChunks`] is
+    /// returned before the output file is touched.
+    pub fn max_chunks(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.max_chunks = Some(chunks);
+        self
+    }
+
+    /// Set the maximum size of the merged file in bytes.
+    ///
+    /// By default, the size is unlimited. The limit is enforced while the
+    /// chunks are merged, so the merge process stops as soon as it would be
+    /// exceeded, removes the partial output file and returns
+    /// [`MergeError::FileTooLarge`].
+    pub fn max_file_size(
+        mut self,
+        size: u64,
+    ) -> Self {
+        self.max_file_size = Some(size);
+        self
+    }
+
+    /// Set whether the merged file is hashed with SHA-256.
+    ///
+    /// The hash is computed while the chunks are written and returned in
+    /// [`MergeResult::sha256`], so the merged file is not read again. The
+    /// passthrough is not used when hashing.
+    pub fn hash(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.hash = enabled;
+        self
+    }
+
+    /// Set the expected hashes of the chunks by index.
+    ///
+    /// Each chunk with an expected hash is hashed while it is merged, and
+    /// [`MergeError::ChunkCorrupted`] is returned on a mismatch after the
+    /// partial output file is removed, so the chunks are not read twice.
+    /// The hashes are SHA-256 digests unless another algorithm is set with
+    /// [`verify_algorithm`](Self::verify_algorithm). The passthrough is not
+    /// used when verifying.
+    pub fn verify(
+        mut self,
+        hashes: HashMap<usize, [u8; 32]>,
+    ) -> Self {
+        self.verify = Some(hashes);
+        self.verify_algorithms.clear();
+        self
+    }
+
+    /// Set the algorithm of the expected hashes of the chunks.
+    ///
+    /// By default, it is [`HashAlgorithm::Sha256`]. It applies to the chunks
+    /// without an algorithm of their own, see
+    /// [`verify_manifest`](Self::verify_manifest).
+    pub fn verify_algorithm(
+        mut self,
+        algorithm: HashAlgorithm,
+    ) -> Self {
+        self.verify_algorithm = algorithm;
+        self
+    }
+
+    /// Set the expected hashes of the chunks from a manifest.
+    ///
+    /// The hashes are taken as in [`verify`](Self::verify), and each chunk
+    /// is verified with the algorithm of its own hash, see
+    /// [`ManifestChunk::hash`](crate::manifest::ManifestChunk::hash), so a
+    /// manifest mixing algorithms is verified correctly. Chunks without a
+    /// hash are not verified.
+    pub fn verify_manifest(
+        mut self,
+        manifest: &Manifest,
+    ) -> Self {
+        let mut hashes: HashMap<usize, [u8; 32]> = HashMap::new();
+        let mut algorithms: HashMap<usize, HashAlgorithm> = HashMap::new();
 
-                if writer.write(&buffer[..read]).is_err() {
-                    return Err(MergeError::OutFileNotWritten);
+        for chunk in manifest.chunks.iter() {
+            if let Some((algorithm, hex)) = chunk.hash() {
+                if let Some(digest) = hash::from_hex(hex) {
+                    hashes.insert(chunk.index, digest);
+                    algorithms.insert(chunk.index, algorithm);
                 }
             }
         }
 
-        if writer.flush().is_err() {
-            return Err(MergeError::OutFileNotWritten);
+        self.verify = Some(hashes);
+        self.verify_algorithms = algorithms;
+        self
+    }
+
+    /// Set the number of chunks verified at random instead of all of them.
+    ///
+    /// The first and the last chunks with an expected hash, and `count`
+    /// others picked at random, are verified before the output file is
+    /// touched, so gross corruption of a huge set of chunks is caught
+    /// quickly without hashing every chunk. The other chunks are not
+    /// verified. It has no effect without expected hashes, see
+    /// [`verify`](Self::verify), nor when following the input directory,
+    /// where every chunk is verified.
+    pub fn verify_sample(
+        mut self,
+        count: usize,
+    ) -> Self {
+        self.verify_sample = Some(count);
+        self
+    }
+
+    /// Apply the policy to an existing output file before it is replaced.
+    ///
+    /// Returns the result of the skipped merge if the output file is kept.
+    pub(crate) fn existing_output<P: AsRef<Path>>(
+        &self,
+        out_file: P,
+        total_chunks: usize,
+    ) -> Result<Option<MergeResult>, MergeError> {
+        let out_file: &Path = out_file.as_ref();
+
+        let metadata: fs::Metadata = match fs::symlink_metadata(out_file) {
+            | Ok(m) => m,
+            | Err(_) => return Ok(None),
+        };
+
+        if metadata.is_dir() {
+            return Err(MergeError::OutFileExists);
         }
 
-        Ok(true)
+        match self.if_exists {
+            | IfExists::Overwrite => Ok(None),
+            | IfExists::Error => Err(MergeError::OutFileExists),
+            | IfExists::Skip => Ok(Some(MergeResult {
+                file_size: metadata.len() as usize,
+                total_chunks,
+                sha256: None,
+                out_file: out_file.to_path_buf(),
+                skipped: true,
+            })),
+        }
     }
+
+    /// Check that the output file fits in the free space of its directory.
+    pub(crate) fn ensure_space<P: AsRef<Path>>(
+        &self,
+        out_file: P,
+        file_size: u64,
+    ) -> Result<(), MergeError> {
+        if !self.check_space {
+            return Ok(());
+        }
+
+        let out_file: &Path = out_file.as_ref();
+
+        // the existing output is removed before writing
+        let replaced: u64 = fs::metadata(out_file)
+            .ok()
+            .filter(|m| m.is_file())
+            .map_or(0, |m| m.len());
+
+        match device::free_space(out_file) {
+            | Some(free) if free.saturating_add(replaced) < file_size => {
+                Err(MergeError::InsufficientSpace)
+            },
+            | _ => Ok(()),
+        }
+    }
+
+    /// Get the expected hash of a chunk.
+    pub(crate) fn expected_hash(
+        &self,
+        index: usize,
+    ) -> Option<&[u8; 32]> {
+        self.verify.as_ref().and_then(|v| v.get(&index))
+    }
+
+    /// Get the algorithm of the expected hash of a chunk.
+    pub(crate) fn verify_algorithm_of(
+        &self,
+        index: usize,
+    ) -> HashAlgorithm {
+        self.verify_algorithms
+            .get(&index)
+            .copied()
+            .unwrap_or(self.verify_algorithm)
+    }
+
+    /// Get the chunks verified in the sampling mode.
+    ///
+    /// The first and the last chunks with an expected hash are always
+    /// picked, and `count` others at random.
+    pub(crate) fn sample(
+        &self,
+        indices: &[usize],
+        count: usize,
+    ) -> Vec<usize> {
+        let mut candidates: Vec<usize> = indices
+            .iter()
+            .copied()
+            .filter(|i| self.expected_hash(*i).is_some())
+            .collect();
+
+        if candidates.len() <= count.saturating_add(2) {
+            return candidates;
+        }
+
+        let last: Option<usize> = candidates.pop();
+        let first: usize = candidates.remove(0);
+
+        // a partial Fisher-Yates shuffle with a xorshift seeded per run
+        let mut seed: u64 = RandomState::new().hash_one(indices.len()) | 1;
+
+        for i in 0..count {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+
+            let j: usize = i + (seed % (candidates.len() - i) as u64) as usize;
+
+            candidates.swap(i, j);
+        }
+
+        candidates.truncate(count);
+
+        [first].into_iter().chain(candidates).chain(last).collect()
+    }
+
+    /// Whether the chunks can be copied as a whole, as nothing inspects,
+    /// paces, limits or cancels their content while it is written.
+    pub(crate) fn copies(&self) -> bool {
+        self.kernel_copy
+            && !self.hash
+            && self.throttle.is_none()
+            && self.control.is_none()
+            && self.max_file_size.is_none()
+            && self.sinks.is_empty()
+            && (self.verify.is_none() || self.verify_sample.is_some())
+    }
+
+    /// Set whether the chunks must be contiguous.
+    ///
+    /// By default, the chunks are merged in order even if some indices are
+    /// missing. When enabled, [`MergeError::InDirNotContiguous`] is returned
+    /// unless the chunks are exactly `0` to `n - 1`.
+    pub fn contiguous(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.contiguous = enabled;
+        self
+    }
+
+    /// Set whether paths escaping their directory are rejected.
+    ///
+    /// When enabled, a symbolic link in the input directory returns
+    /// [`MergeError::InFileNotSafe`] and an output file path containing `..`
+    /// returns [`MergeError::OutFileNotSafe`].
+    pub fn reject_unsafe_paths(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.reject_unsafe_paths = enabled;
+        self
+    }
+
+    /// Set whether file names which are not valid UTF-8 are rejected.
+    ///
+    /// By default, such files are ignored like any other file which is not
+    /// a chunk. When enabled, [`MergeError::InFileNameInvalid`] is returned.
+    pub fn reject_invalid_names(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.reject_invalid_names = enabled;
+        self
+    }
+
+    /// Set whether the output file name must be a valid file name on
+    /// Windows.
+    ///
+    /// Enabled by default on Windows. When enabled, a name refused by
+    /// [`naming::is_portable`], e.g. `con.txt` or `file?`, returns
+    /// [`MergeError::OutFileNameInvalid`] before any chunk is read.
+    pub fn portable_names(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.portable_names = enabled;
+        self
+    }
+
+    /// Enable all the protections for chunks received from untrusted
+    /// sources.
+    ///
+    /// It enables [`contiguous`](Self::contiguous),
+    /// [`reject_unsafe_paths`](Self::reject_unsafe_paths),
+    /// [`reject_invalid_names`](Self::reject_invalid_names) and
+    /// [`require_verify`](Self::require_verify), and limits
+    /// [`max_chunks`](Self::max_chunks) to [`MAX_CHUNKS_UNTRUSTED_DEFAULT`]
+    /// and [`max_file_size`](Self::max_file_size) to
+    /// [`MAX_FILE_SIZE_UNTRUSTED_DEFAULT`] unless a limit is already set. The
+    /// expected hashes must then be set with [`verify`](Self::verify) or
+    /// [`verify_manifest`](Self::verify_manifest).
+    pub fn strict_untrusted(mut self) -> Self {
+        self.contiguous = true;
+        self.reject_unsafe_paths = true;
+        self.reject_invalid_names = true;
+        self.require_verify = true;
+        self.max_chunks.get_or_insert(MAX_CHUNKS_UNTRUSTED_DEFAULT);
+        self.max_file_size.get_or_insert(MAX_FILE_SIZE_UNTRUSTED_DEFAULT);
+        self
+    }
+
+    /// Set whether the expected hashes of the chunks must be set.
+    ///
+    /// When enabled, [`MergeError::VerifyNotSet`] is returned before any
+    /// chunk is read if no expected hash is set, see
+    /// [`verify`](Self::verify).
+    pub fn require_verify(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.require_verify = enabled;
+        self
+    }
+
+    /// Set whether the input directory is locked during the merge process.
+    ///
+    /// When enabled, a [`DirLock`] is held while running, and
+    /// [`MergeError::Locked`] is returned if another process holds it, e.g. an
+    /// uploader still writing chunks.
+    pub fn lock(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.lock = enabled;
+        self
+    }
+
+    /// Set the maximum rate of IO in bytes per second.
+    ///
+    /// By default, the rate is unlimited. The merge process sleeps whenever it
+    /// gets ahead of the rate, so background jobs do not saturate the disk.
+    /// Short bursts of up to one second of IO are allowed.
+    pub fn throttle_bytes_per_sec(
+        mut self,
+        rate: u64,
+    ) -> Self {
+        self.throttle = Some(rate);
+        self
+    }
+
+    /// Set the clock measuring the time of the merge process.
+    ///
+    /// By default, it is the [`SystemClock`]. It is used by the throttle and
+    /// the progress of the synchronous process, e.g. to run tests with a mock
+    /// clock without waiting. The asynchronous processes wait with the timers
+    /// of their runtime.
+    pub fn clock<C: Clock + 'static>(
+        mut self,
+        clock: C,
+    ) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Set the priority of the thread running the merge process.
+    ///
+    /// It is only applied by the synchronous `run`, as an async task may be
+    /// moved between threads. See [`Priority`] for more details.
+    pub fn priority(
+        mut self,
+        priority: Priority,
+    ) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Attach a control handle to pause, resume and cancel the merge process.
+    pub fn control(
+        mut self,
+        control: &Control,
+    ) -> Self {
+        self.control = Some(control.clone());
+        self
+    }
+
+    /// Set a callback receiving the progress after each merged chunk.
+    ///
+    /// The total size is taken from the chunks in the input directory, and
+    /// the throughput is smoothed over the chunks, so the
+    /// [`eta`](Progress::eta) does not jump with every chunk.
+    pub fn on_progress<F: Fn(&Progress) + Send + Sync + 'static>(
+        mut self,
+        callback: F,
+    ) -> Self {
+        self.on_progress = Some(ProgressCallback::new(callback));
+        self
+    }
+
+    /// Merge the chunks as they arrive in the input directory.
+    ///
+    /// Instead of listing the input directory once, the merge process waits
+    /// for each chunk in order and appends it to the output file, so the
+    /// output file can be read while the chunks are still being written. A
+    /// chunk is merged once the next one exists, or once [`Follow::end`] is
+    /// called for the last one. The chunks must be written in order, and
+    /// `passthrough`, `contiguous`, `on_progress`, `pre_finalize` and
+    /// `allowed_types` have no effect.
+    pub fn follow(
+        mut self,
+        follow: &Follow,
+    ) -> Self {
+        self.follow = Some(follow.clone());
+        self
+    }
+
+    /// Set a hook inspecting the content before the output file is written.
+    ///
+    /// The hook receives a [`ChunkedReader`] over the chunks in order, e.g.
+    /// to scan them, and returns whether the merge continues. When it
+    /// returns `false`, [`MergeError::ContentRejected`] is returned and the
+    /// output file is not touched. The async runs call it on a blocking
+    /// thread.
+    pub fn pre_finalize<
+        F: Fn(&mut ChunkedReader) -> bool + Send + Sync + 'static,
+    >(
+        mut self,
+        hook: F,
+    ) -> Self {
+        self.pre_finalize = Some(PreFinalize::new(hook));
+        self
+    }
+
+    /// Add a writer receiving a copy of the merged content.
+    ///
+    /// The content is written to the output file and to every sink in the
+    /// same pass over the chunks, e.g. to archive the file while uploading
+    /// it, and the sinks are flushed when the merge succeeds. A failed
+    /// write returns [`MergeError::SinkNotWritten`] with the position of the
+    /// sink, which may then hold partial content. The merge is then
+    /// sequential, as the content must reach the sinks in order, and a
+    /// skipped merge writes nothing to them. The async runs write to the
+    /// sinks on the current task.
+    pub fn tee<W: io::Write + Send + 'static>(
+        mut self,
+        sink: W,
+    ) -> Self {
+        self.sinks.push(Sink::new(sink));
+        self
+    }
+
+    /// Write the content to every sink.
+    pub(crate) fn write_sinks(
+        &self,
+        content: &[u8],
+    ) -> Result<(), MergeError> {
+        for (index, sink) in self.sinks.iter().enumerate() {
+            if let Err(e) = sink.write_all(content) {
+                return Err(MergeError::SinkNotWritten {
+                    index,
+                    source: e.into(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flush every sink.
+    pub(crate) fn flush_sinks(&self) -> Result<(), MergeError> {
+        for (index, sink) in self.sinks.iter().enumerate() {
+            if let Err(e) = sink.flush() {
+                return Err(MergeError::SinkNotWritten {
+                    index,
+                    source: e.into(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the content types allowed to be merged.
+    ///
+    /// The content type is sniffed from the first chunks, see
+    /// [`sniff`](crate::sniff::sniff). When it does not match any of the
+    /// allowed types, e.g. `image/png` or `image/*`,
+    /// [`MergeError::ContentRejected`] is returned before the output file is
+    /// touched.
+    pub fn allowed_types<T: Into<String>, I: IntoIterator<Item = T>>(
+        mut self,
+        types: I,
+    ) -> Self {
+        self.allowed_types = Some(types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Run the merge process.
+    pub fn run(&self) -> Result<MergeResult, MergeError> {
+        let merge_result: MergeResult = self.merge()?;
+
+        self.flush_sinks()?;
+
+        if let Err((path, e)) =
+            self.durability.sync_file(&merge_result.out_file)
+        {
+            return Err(MergeError::OutFileNotSynced {
+                path,
+                source: e.into(),
+            });
+        }
+
+        Ok(merge_result)
+    }
+
+    /// Merge the chunks into the output file.
+    fn merge(&self) -> Result<MergeResult, MergeError> {
+        let in_dir: &Path = match self.in_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_ref();
+
+                // if in_dir not exists
+                if !p.exists() {
+                    return Err(MergeError::InDirNotFound);
+                }
+
+                // if in_dir not a directory
+                if !p.is_dir() {
+                    return Err(MergeError::InDirNotDir);
+                }
+
+                p
+            },
+            | None => return Err(MergeError::InDirNotSet),
+        };
+
+        let out_file: &Path = match self.out_file {
+            | Some(ref p) => p.as_ref(),
+            | None => return Err(MergeError::OutFileNotSet),
+        };
+
+        // if the chunks cannot be verified
+        if self.require_verify
+            && self.verify.as_ref().is_none_or(|v| v.is_empty())
+        {
+            return Err(MergeError::VerifyNotSet);
+        }
+
+        // if out_file may escape its directory
+        if self.reject_unsafe_paths
+            && out_file.components().any(|c| c == Component::ParentDir)
+        {
+            return Err(MergeError::OutFileNotSafe);
+        }
+
+        // if out_file cannot be created on Windows
+        if self.portable_names
+            && !out_file
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(naming::is_portable)
+        {
+            return Err(MergeError::OutFileNameInvalid);
+        }
+
+        let _priority: Option<PriorityGuard> = match self.priority {
+            | Some(ref p) => match PriorityGuard::apply(p) {
+                | Ok(g) => Some(g),
+                | Err(e) => {
+                    return Err(MergeError::PriorityNotSet {
+                        source: e.into(),
+                    });
+                },
+            },
+            | None => None,
+        };
+
+        let _control: Option<ControlGuard> =
+            self.control.as_ref().map(Control::run);
+
+        let _lock: Option<DirLock> = if self.lock {
+            match DirLock::acquire(in_dir) {
+                | Ok(l) => Some(l),
+                | Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    return Err(MergeError::Locked);
+                },
+                | Err(e) => {
+                    return Err(MergeError::LockNotCreated {
+                        path: in_dir.join(LOCK_FILE_NAME),
+                        source: e.into(),
+                    });
+                },
+            }
+        } else {
+            None
+        };
+
+        // merge the chunks as they arrive
+        if let Some(ref f) = self.follow {
+            return merge_following(self, in_dir, out_file, f);
+        }
+
+        // get inputs
+        // only the indices are kept and the paths are derived from the
+        // namer, so millions of chunks do not hold as many paths in memory
+        let mut indices: Vec<usize> = Vec::new();
+
+        let read_dir: ReadDir = match fs::read_dir(in_dir) {
+            | Ok(read_dir) => read_dir,
+            | Err(e) => {
+                return Err(MergeError::InDirNotRead {
+                    path: in_dir.to_path_buf(),
+                    source: e.into(),
+                });
+            },
+        };
+
+        for entry in read_dir.filter_map(Result::ok) {
+            // if entry is a symlink that may point outside in_dir
+            if self.reject_unsafe_paths
+                && entry.file_type().is_ok_and(|t| t.is_symlink())
+            {
+                return Err(MergeError::InFileNotSafe);
+            }
+
+            let path: PathBuf = entry.path();
+
+            if !path.is_file() {
+                continue;
+            }
+
+            let name: &str = match path.file_name().and_then(|n| n.to_str()) {
+                | Some(name) => name,
+                | None if self.reject_invalid_names => {
+                    return Err(MergeError::InFileNameInvalid);
+                },
+                | None => continue,
+            };
+
+            if let Some(index) = self.namer.parse(name) {
+                indices.push(index);
+            }
+        }
+
+        indices.sort_unstable();
+
+        if let Some(w) = indices.windows(2).find(|w| w[0] == w[1]) {
+            return Err(MergeError::InDirDuplicateIndex { index: w[0] });
+        }
+
+        if self.max_chunks.is_some_and(|max| indices.len() > max) {
+            return Err(MergeError::TooManyChunks);
+        }
+
+        if self.contiguous
+            && indices.iter().enumerate().any(|(i, index)| i != *index)
+        {
+            return Err(MergeError::InDirNotContiguous);
+        }
+
+        // the names of the chunks may depend on their content
+        self.namer.scan(in_dir);
+
+        // the chunks may vary in size, so the largest one sets the capacity
+        let mut sizes: Vec<u64> = Vec::with_capacity(indices.len());
+
+        for index in indices.iter() {
+            let file: PathBuf = in_dir.join(self.namer.name(*index));
+
+            match fs::metadata(&file) {
+                | Ok(metadata) => sizes.push(metadata.len()),
+                | Err(e) => {
+                    return Err(MergeError::InFileNotRead {
+                        path: file,
+                        source: e.into(),
+                    });
+                },
+            }
+        }
+
+        let input_size: usize = match sizes.iter().max() {
+            | Some(size) => *size as usize,
+            | None => return Err(MergeError::InDirNoFile),
+        };
+
+        let buffer_capacity: usize = input_size.min(self.cap_max);
+
+        // total size for progress
+        let mut tracker: Option<Tracker> =
+            self.on_progress.is_some().then(|| {
+                Tracker::new(
+                    indices.len(),
+                    sizes.iter().sum(),
+                    self.run_id,
+                    self.clock.clone(),
+                )
+            });
+
+        // sniff the content type from the first chunks
+        if let Some(ref allowed) = self.allowed_types {
+            let mut head: Vec<u8> = Vec::with_capacity(sniff::SNIFF_LEN);
+
+            for index in indices.iter() {
+                if head.len() >= sniff::SNIFF_LEN {
+                    break;
+                }
+
+                let entry: PathBuf = in_dir.join(self.namer.name(*index));
+
+                let input: fs::File = match fs::File::open(&entry) {
+                    | Ok(file) => file,
+                    | Err(e) => {
+                        return Err(MergeError::InFileNotOpened {
+                            path: entry.clone(),
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                if let Err(e) = input
+                    .take((sniff::SNIFF_LEN - head.len()) as u64)
+                    .read_to_end(&mut head)
+                {
+                    return Err(MergeError::InFileNotRead {
+                        path: entry.clone(),
+                        source: e.into(),
+                    });
+                }
+            }
+
+            let content_type: &str = sniff::sniff(&head);
+
+            if !allowed.iter().any(|a| sniff::matches(content_type, a)) {
+                return Err(MergeError::ContentRejected);
+            }
+        }
+
+        // let the hook inspect the content before it is written
+        if let Some(ref hook) = self.pre_finalize {
+            let mut reader: ChunkedReader = match ChunkedReader::open(
+                indices.iter().map(|i| in_dir.join(self.namer.name(*i))),
+            ) {
+                | Ok(reader) => reader,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotRead {
+                        path: in_dir.to_path_buf(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if !hook.call(&mut reader) {
+                return Err(MergeError::ContentRejected);
+            }
+        }
+
+        // catch gross corruption before the output is touched
+        if let Some(count) = self.verify_sample {
+            let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+
+            for index in self.sample(&indices, count) {
+                let expected: &[u8; 32] = match self.expected_hash(index) {
+                    | Some(e) => e,
+                    | None => continue,
+                };
+
+                let entry: PathBuf = in_dir.join(self.namer.name(index));
+
+                let mut input: fs::File = match fs::File::open(&entry) {
+                    | Ok(f) => f,
+                    | Err(e) => {
+                        return Err(MergeError::InFileNotOpened {
+                            path: entry,
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                // streamed, so a huge chunk is not held in memory
+                let mut chunk_hasher: Hasher =
+                    self.verify_algorithm_of(index).hasher();
+
+                loop {
+                    let read: usize = match input.read(&mut buffer) {
+                        | Ok(0) => break,
+                        | Ok(n) => n,
+                        | Err(e) if e.kind() == ErrorKind::Interrupted => {
+                            continue;
+                        },
+                        | Err(e) => {
+                            return Err(MergeError::InFileNotRead {
+                                path: entry,
+                                source: e.into(),
+                            });
+                        },
+                    };
+
+                    chunk_hasher.update(&buffer[..read]);
+                }
+
+                if chunk_hasher.finalize() != *expected {
+                    return Err(MergeError::ChunkCorrupted { index });
+                }
+            }
+        }
+
+        // keep the output if it is already merged
+        if self.skip_merged {
+            if let Some(r) = self.merged_result(
+                in_dir,
+                out_file,
+                &indices,
+                &sizes,
+                buffer_capacity,
+            )? {
+                return Ok(r);
+            }
+        }
+
+        // continue after the chunks written by a previous merge
+        let resumed: usize = self.resumed_chunks(out_file, &sizes);
+        let offset: u64 = sizes[..resumed].iter().sum();
+
+        // keep or refuse an existing output
+        if resumed == 0 {
+            if let Some(r) = self.existing_output(out_file, indices.len())? {
+                return Ok(r);
+            }
+        }
+
+        self.ensure_space(out_file, sizes.iter().sum())?;
+
+        // replace an existing output file
+        if resumed == 0 && out_file.exists() {
+            if let Err(e) = fs::remove_file(out_file) {
+                return Err(MergeError::OutFileNotRemoved {
+                    path: out_file.to_path_buf(),
+                    source: e.into(),
+                });
+            }
+        }
+
+        // create outpath
+        if let Some(parent) = out_file.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                return Err(MergeError::OutDirNotCreated {
+                    path: parent.to_path_buf(),
+                    source: e.into(),
+                });
+            }
+        }
+
+        // move the only chunk into place instead of copying it
+        let first: PathBuf = in_dir.join(self.namer.name(indices[0]));
+
+        if self.passthrough
+            && !self.hash
+            && self.verify.is_none()
+            && self.sinks.is_empty()
+            && resumed == 0
+            && indices.len() == 1
+            && self.max_file_size.is_none_or(|max| input_size as u64 <= max)
+            && device::same_device(&first, out_file)
+            && fs::rename(&first, out_file).is_ok()
+        {
+            if let Some(ref c) = self.control {
+                c.record(indices[0], input_size as u64);
+            }
+
+            if let (Some(t), Some(cb)) = (tracker.as_mut(), &self.on_progress) {
+                cb.call(t.advance(input_size as u64));
+            }
+
+            return Ok(MergeResult {
+                file_size: input_size,
+                total_chunks: 1,
+                sha256: None,
+                out_file: out_file.to_path_buf(),
+                skipped: false,
+            });
+        }
+
+        // read the chunks straight into the mapped output
+        #[cfg(all(feature = "mmap", target_os = "linux"))]
+        if self.mmap
+            && self.sinks.is_empty()
+            && resumed == 0
+            && sizes.iter().any(|size| *size > 0)
+        {
+            return self
+                .merge_mapped(in_dir, out_file, &indices, &sizes, tracker);
+        }
+
+        // write the chunks from a thread pool at their offsets
+        #[cfg(all(feature = "rayon", any(unix, windows)))]
+        if self.concurrency > 1
+            && !self.hash
+            && self.throttle.is_none()
+            && self.sinks.is_empty()
+            && resumed == 0
+        {
+            if let Ok(pool) = rayon::ThreadPoolBuilder::new()
+                .num_threads(self.concurrency)
+                .build()
+            {
+                return pool.install(|| {
+                    self.merge_parallel(
+                        in_dir, out_file, &indices, &sizes, tracker,
+                    )
+                });
+            }
+        }
+
+        // read and write the chunks without the page cache
+        #[cfg(target_os = "linux")]
+        if self.direct_io
+            && !self.hash
+            && self.verify.is_none()
+            && self.throttle.is_none()
+            && self.max_file_size.is_none()
+            && self.sinks.is_empty()
+            && resumed == 0
+        {
+            if let Some(r) =
+                self.merge_direct(in_dir, out_file, &indices, &mut tracker)?
+            {
+                return Ok(r);
+            }
+        }
+
+        let output: fs::File = match fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(resumed > 0)
+            .write(true)
+            .open(out_file)
+        {
+            | Ok(file) => file,
+            | Err(e) => {
+                return Err(MergeError::OutFileNotOpened {
+                    path: out_file.to_path_buf(),
+                    source: e.into(),
+                });
+            },
+        };
+
+        // reserve the whole output before writing it
+        if self.preallocate {
+            if let Err(e) = device::preallocate(&output, sizes.iter().sum()) {
+                drop(output);
+
+                if resumed == 0 {
+                    let _ = fs::remove_file(out_file);
+                }
+
+                return Err(MergeError::OutFileNotWritten {
+                    path: out_file.to_path_buf(),
+                    source: e.into(),
+                });
+            }
+        }
+
+        let mut hasher: Option<Sha256> = self.hash.then(Sha256::new);
+
+        // append after the chunks kept from a previous merge
+        if resumed > 0 {
+            let kept: io::Result<u64> = output.set_len(offset).and_then(|_| {
+                if let Some(ref mut h) = hasher {
+                    hash_prefix(&output, offset, h, buffer_capacity)?;
+                }
+
+                (&output).seek(SeekFrom::Start(offset))
+            });
+
+            if let Err(e) = kept {
+                return Err(MergeError::OutFileNotWritten {
+                    path: out_file.to_path_buf(),
+                    source: e.into(),
+                });
+            }
+
+            for (index, size) in indices.iter().zip(sizes.iter()).take(resumed)
+            {
+                if let Some(ref c) = self.control {
+                    c.record(*index, *size);
+                }
+
+                if let (Some(t), Some(cb)) =
+                    (tracker.as_mut(), &self.on_progress)
+                {
+                    cb.call(t.advance(*size));
+                }
+            }
+        }
+
+        let copy: bool = self.copies();
+
+        // writer, its buffers are reused by every chunk
+        let mut writer: VectoredWriter<io::BufWriter<fs::File>> =
+            VectoredWriter::new(
+                io::BufWriter::with_capacity(buffer_capacity, output),
+                self.vectored_buffers,
+                if copy { 0 } else { buffer_capacity },
+            );
+
+        let total_chunks: usize = indices.len();
+        let mut merged: u64 = offset;
+        let mut throttle: Option<Throttle> =
+            self.throttle.map(|r| Throttle::new(r, self.clock.clone()));
+
+        // merge
+        for index in indices.into_iter().skip(resumed) {
+            let entry: PathBuf = in_dir.join(self.namer.name(index));
+
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(MergeError::Cancelled);
+                }
+            }
+
+            let mut options: fs::OpenOptions = fs::OpenOptions::new();
+            options.read(true);
+
+            if self.cache_hints {
+                advise::sequential_scan(&mut options);
+            }
+
+            let mut input: fs::File = match options.open(&entry) {
+                | Ok(file) => file,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if self.cache_hints {
+                advise::sequential(&input);
+            }
+
+            // let the standard library copy the chunk, e.g. within the kernel
+            if copy {
+                // the copy does not tell a failed read from a failed write
+                let chunk_bytes: u64 =
+                    match io::copy(&mut input, writer.get_mut()) {
+                        | Ok(n) => n,
+                        | Err(e) => {
+                            return Err(MergeError::OutFileNotWritten {
+                                path: out_file.to_path_buf(),
+                                source: e.into(),
+                            });
+                        },
+                    };
+
+                merged += chunk_bytes;
+
+                if self.cache_hints {
+                    advise::dont_need(&input);
+                }
+
+                if let (Some(t), Some(cb)) =
+                    (tracker.as_mut(), &self.on_progress)
+                {
+                    cb.call(t.advance(chunk_bytes));
+                }
+
+                continue;
+            }
+
+            let mut chunk_bytes: u64 = 0;
+            let mut chunk_hasher: Option<Hasher> = self
+                .expected_hash(index)
+                .filter(|_| self.verify_sample.is_none())
+                .map(|_| self.verify_algorithm_of(index).hasher());
+
+            loop {
+                // a large chunk is slow to merge, so checked for each buffer
+                if self.control.as_ref().is_some_and(Control::is_cancelled) {
+                    return Err(MergeError::Cancelled);
+                }
+
+                let buffer: &mut [u8] = writer.buffer();
+                let mut read: usize = 0;
+
+                // a short read ends the fill unless it is the end of the chunk
+                while read < buffer.len() {
+                    let n: usize = match input.read(&mut buffer[read..]) {
+                        | Ok(n) => n,
+                        | Err(e) => {
+                            return Err(MergeError::InFileNotRead {
+                                path: entry,
+                                source: e.into(),
+                            });
+                        },
+                    };
+
+                    read += n;
+
+                    if n == 0 || !self.fill_buffer {
+                        break;
+                    }
+                }
+
+                if read == 0 {
+                    break;
+                }
+
+                merged += read as u64;
+
+                // stop before the output exceeds the limit
+                if self.max_file_size.is_some_and(|max| merged > max) {
+                    drop(writer);
+                    let _ = fs::remove_file(out_file);
+
+                    return Err(MergeError::FileTooLarge);
+                }
+
+                if let Some(ref mut h) = hasher {
+                    h.update(&buffer[..read]);
+                }
+
+                if let Some(ref mut h) = chunk_hasher {
+                    h.update(&buffer[..read]);
+                }
+
+                self.write_sinks(&buffer[..read])?;
+
+                if let Err(e) = writer.commit(read) {
+                    return Err(MergeError::OutFileNotWritten {
+                        path: out_file.to_path_buf(),
+                        source: e.into(),
+                    });
+                }
+
+                chunk_bytes += read as u64;
+
+                if let Some(ref mut t) = throttle {
+                    t.wait(read as u64);
+                }
+            }
+
+            if self.cache_hints {
+                advise::dont_need(&input);
+            }
+
+            // stop before the corrupted chunk is used
+            if let (Some(h), Some(expected)) =
+                (chunk_hasher, self.expected_hash(index))
+            {
+                if h.finalize() != *expected {
+                    drop(writer);
+                    let _ = fs::remove_file(out_file);
+
+                    return Err(MergeError::ChunkCorrupted { index });
+                }
+            }
+
+            if let Some(ref c) = self.control {
+                c.record(index, chunk_bytes);
+            }
+
+            if let (Some(t), Some(cb)) = (tracker.as_mut(), &self.on_progress) {
+                cb.call(t.advance(chunk_bytes));
+            }
+        }
+
+        if let Err(e) = writer.flush() {
+            return Err(MergeError::OutFileNotWritten {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        }
+
+        if self.cache_hints {
+            advise::dont_need(writer.get_mut().get_ref());
+        }
+
+        Ok(MergeResult {
+            file_size: merged as usize,
+            total_chunks,
+            sha256: hasher.map(|h| hash::to_hex(&h.finalize())),
+            out_file: out_file.to_path_buf(),
+            skipped: false,
+        })
+    }
+}
+
+#[cfg(all(feature = "rayon", any(unix, windows)))]
+impl Merge {
+    /// Merge the chunks from the current thread pool.
+    ///
+    /// The output is preallocated, as each chunk is written at the offset
+    /// following the chunks before it, in any order.
+    fn merge_parallel(
+        &self,
+        in_dir: &Path,
+        out_file: &Path,
+        indices: &[usize],
+        sizes: &[u64],
+        tracker: Option<Tracker>,
+    ) -> Result<MergeResult, MergeError> {
+        use rayon::prelude::*;
+
+        let file_size: u64 = sizes.iter().sum();
+
+        // stop before the output exceeds the limit
+        if self.max_file_size.is_some_and(|max| file_size > max) {
+            return Err(MergeError::FileTooLarge);
+        }
+
+        // written to a temporary file until complete, so an interrupted
+        // merge never leaves an output of full length to resume from
+        let temp: PathBuf = cleanup::temp_path(out_file);
+
+        let output: fs::File = match fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(&temp)
+        {
+            | Ok(file) => file,
+            | Err(e) => {
+                return Err(MergeError::OutFileNotOpened {
+                    path: temp,
+                    source: e.into(),
+                });
+            },
+        };
+
+        if let Err(e) = output.set_len(file_size) {
+            drop(output);
+            let _ = fs::remove_file(&temp);
+
+            return Err(MergeError::OutFileNotWritten {
+                path: temp,
+                source: e.into(),
+            });
+        }
+
+        let mut offset: usize = 0;
+
+        // each chunk follows the chunks before it in the output
+        let chunks: Vec<ChunkInfo> = indices
+            .iter()
+            .zip(sizes)
+            .map(|(index, size)| {
+                let chunk: ChunkInfo = ChunkInfo::new(
+                    *index,
+                    offset,
+                    *size as usize,
+                    self.namer.as_ref(),
+                );
+                offset += chunk.size;
+                chunk
+            })
+            .collect();
+
+        let tracker: Mutex<Option<Tracker>> = Mutex::new(tracker);
+
+        let buffer_capacity: usize = chunks
+            .iter()
+            .map(|chunk| chunk.size)
+            .max()
+            .unwrap_or(0)
+            .min(self.cap_max);
+
+        // a buffer is reused by the chunks merged on the same worker
+        let merged: Result<(), MergeError> =
+            chunks.par_iter().try_for_each_init(
+                || vec![0; buffer_capacity],
+                |buffer, chunk| {
+                    self.merge_section(
+                        in_dir, &output, &temp, chunk, &tracker, buffer,
+                    )
+                },
+            );
+
+        drop(output);
+
+        if let Err(e) = merged.and_then(|_| persist_temp(&temp, out_file)) {
+            let _ = fs::remove_file(&temp);
+
+            return Err(e);
+        }
+
+        Ok(MergeResult {
+            file_size: file_size as usize,
+            total_chunks: indices.len(),
+            sha256: None,
+            out_file: out_file.to_path_buf(),
+            skipped: false,
+        })
+    }
+
+    /// Write the chunk to its section of the preallocated output.
+    fn merge_section(
+        &self,
+        in_dir: &Path,
+        output: &fs::File,
+        out_file: &Path,
+        chunk: &ChunkInfo,
+        tracker: &Mutex<Option<Tracker>>,
+        buffer: &mut [u8],
+    ) -> Result<(), MergeError> {
+        let index: usize = chunk.index;
+        let entry: PathBuf = in_dir.join(&chunk.name);
+
+        if let Some(ref c) = self.control {
+            if !c.wait() {
+                return Err(MergeError::Cancelled);
+            }
+        }
+
+        let input: fs::File = match fs::File::open(&entry) {
+            | Ok(file) => file,
+            | Err(e) => {
+                return Err(MergeError::InFileNotOpened {
+                    path: entry,
+                    source: e.into(),
+                });
+            },
+        };
+
+        // the chunk must not spill into the section of the next one
+        let mut reader: io::Take<fs::File> = input.take(chunk.size as u64);
+
+        let mut chunk_bytes: u64 = 0;
+        let mut chunk_hasher: Option<Hasher> = self
+            .expected_hash(index)
+            .filter(|_| self.verify_sample.is_none())
+            .map(|_| self.verify_algorithm_of(index).hasher());
+
+        loop {
+            if self.control.as_ref().is_some_and(Control::is_cancelled) {
+                return Err(MergeError::Cancelled);
+            }
+
+            let read: usize = match reader.read(buffer) {
+                | Ok(n) => n,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotRead {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if read == 0 {
+                break;
+            }
+
+            if let Err(e) = write_all_at(
+                output,
+                &buffer[..read],
+                chunk.offset as u64 + chunk_bytes,
+            ) {
+                return Err(MergeError::OutFileNotWritten {
+                    path: out_file.to_path_buf(),
+                    source: e.into(),
+                });
+            }
+
+            if let Some(ref mut h) = chunk_hasher {
+                h.update(&buffer[..read]);
+            }
+
+            chunk_bytes += read as u64;
+        }
+
+        // the chunk shrank since its size was read
+        if chunk_bytes != chunk.size as u64 {
+            return Err(MergeError::InFileNotRead {
+                path: entry,
+                source: io::Error::from(ErrorKind::UnexpectedEof).into(),
+            });
+        }
+
+        // stop before the corrupted chunk is used
+        if let (Some(h), Some(expected)) =
+            (chunk_hasher, self.expected_hash(index))
+        {
+            if h.finalize() != *expected {
+                return Err(MergeError::ChunkCorrupted { index });
+            }
+        }
+
+        if let Some(ref c) = self.control {
+            c.record(index, chunk_bytes);
+        }
+
+        if let Some(ref cb) = self.on_progress {
+            let mut tracker: MutexGuard<Option<Tracker>> =
+                tracker.lock().unwrap_or_else(|e| e.into_inner());
+
+            if let Some(ref mut t) = *tracker {
+                cb.call(t.advance(chunk_bytes));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+impl Merge {
+    /// Merge the chunks into the output file mapped into memory.
+    ///
+    /// The output is preallocated, and each chunk is read into the pages of
+    /// its section.
+    fn merge_mapped(
+        &self,
+        in_dir: &Path,
+        out_file: &Path,
+        indices: &[usize],
+        sizes: &[u64],
+        tracker: Option<Tracker>,
+    ) -> Result<MergeResult, MergeError> {
+        let file_size: u64 = sizes.iter().sum();
+
+        // stop before the output exceeds the limit
+        if self.max_file_size.is_some_and(|max| file_size > max) {
+            return Err(MergeError::FileTooLarge);
+        }
+
+        // written to a temporary file until complete, so an interrupted
+        // merge never leaves an output of full length to resume from
+        let temp: PathBuf = cleanup::temp_path(out_file);
+
+        // the mapping must be readable as well
+        let output: fs::File = match fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(&temp)
+        {
+            | Ok(file) => file,
+            | Err(e) => {
+                return Err(MergeError::OutFileNotOpened {
+                    path: temp,
+                    source: e.into(),
+                });
+            },
+        };
+
+        let merged: Result<Option<String>, MergeError> = self
+            .merge_mapped_into(in_dir, &output, &temp, indices, sizes, tracker);
+
+        drop(output);
+
+        let sha256: Option<String> = match merged
+            .and_then(|sha256| persist_temp(&temp, out_file).map(|_| sha256))
+        {
+            | Ok(sha256) => sha256,
+            | Err(e) => {
+                let _ = fs::remove_file(&temp);
+
+                return Err(e);
+            },
+        };
+
+        Ok(MergeResult {
+            file_size: file_size as usize,
+            total_chunks: indices.len(),
+            sha256,
+            out_file: out_file.to_path_buf(),
+            skipped: false,
+        })
+    }
+
+    /// Map the output into memory and read the chunks into it.
+    ///
+    /// Returns the hash of the content if hashing is enabled.
+    fn merge_mapped_into(
+        &self,
+        in_dir: &Path,
+        output: &fs::File,
+        path: &Path,
+        indices: &[usize],
+        sizes: &[u64],
+        tracker: Option<Tracker>,
+    ) -> Result<Option<String>, MergeError> {
+        let file_size: u64 = sizes.iter().sum();
+
+        if let Err(e) = output.set_len(file_size) {
+            return Err(MergeError::OutFileNotWritten {
+                path: path.to_path_buf(),
+                source: e.into(),
+            });
+        }
+
+        let mut map: Mmap = match Mmap::map_mut(output, file_size as usize) {
+            | Ok(map) => map,
+            | Err(e) => {
+                return Err(MergeError::OutFileNotOpened {
+                    path: path.to_path_buf(),
+                    source: e.into(),
+                });
+            },
+        };
+
+        let sha256: Option<String> = self.merge_into(
+            in_dir,
+            map.as_mut_slice(),
+            indices,
+            sizes,
+            tracker,
+        )?;
+
+        match map.flush() {
+            | Ok(()) => Ok(sha256),
+            | Err(e) => Err(MergeError::OutFileNotWritten {
+                path: path.to_path_buf(),
+                source: e.into(),
+            }),
+        }
+    }
+
+    /// Read the chunks into their sections of the content.
+    ///
+    /// Returns the hash of the content if hashing is enabled.
+    fn merge_into(
+        &self,
+        in_dir: &Path,
+        content: &mut [u8],
+        indices: &[usize],
+        sizes: &[u64],
+        mut tracker: Option<Tracker>,
+    ) -> Result<Option<String>, MergeError> {
+        let mut hasher: Option<Sha256> = self.hash.then(Sha256::new);
+        let mut throttle: Option<Throttle> =
+            self.throttle.map(|r| Throttle::new(r, self.clock.clone()));
+
+        let mut offset: usize = 0;
+
+        for (index, size) in indices.iter().zip(sizes) {
+            let index: usize = *index;
+            let size: usize = *size as usize;
+            let entry: PathBuf = in_dir.join(self.namer.name(index));
+
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(MergeError::Cancelled);
+                }
+            }
+
+            let mut input: fs::File = match fs::File::open(&entry) {
+                | Ok(file) => file,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
+            };
+
+            let section: &mut [u8] = &mut content[offset..offset + size];
+
+            if let Err(e) = input.read_exact(section) {
+                return Err(MergeError::InFileNotRead {
+                    path: entry,
+                    source: e.into(),
+                });
+            }
+
+            // stop before the corrupted chunk is used
+            if let Some(expected) = self
+                .expected_hash(index)
+                .filter(|_| self.verify_sample.is_none())
+            {
+                if self.verify_algorithm_of(index).digest(section) != *expected
+                {
+                    return Err(MergeError::ChunkCorrupted { index });
+                }
+            }
+
+            if let Some(ref mut h) = hasher {
+                h.update(section);
+            }
+
+            if let Some(ref mut t) = throttle {
+                t.wait(size as u64);
+            }
+
+            if let Some(ref c) = self.control {
+                c.record(index, size as u64);
+            }
+
+            if let (Some(t), Some(cb)) = (tracker.as_mut(), &self.on_progress) {
+                cb.call(t.advance(size as u64));
+            }
+
+            offset += size;
+        }
+
+        Ok(hasher.map(|h| hash::to_hex(&h.finalize())))
+    }
+}
+
+/// Write the whole buffer to the file at the offset.
+#[cfg(all(feature = "rayon", any(unix, windows)))]
+fn write_all_at(
+    file: &fs::File,
+    buffer: &[u8],
+    offset: u64,
+) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileExt as _;
+
+        file.write_all_at(buffer, offset)
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileExt as _;
+
+        let mut written: usize = 0;
+
+        while written < buffer.len() {
+            match file.seek_write(&buffer[written..], offset + written as u64) {
+                | Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                | Ok(n) => written += n,
+                | Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                | Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Merge {
+    /// Merge the chunks without the page cache.
+    ///
+    /// The chunks are gathered into an aligned buffer, so the output is
+    /// written in aligned blocks. Returns `None` to fall back to the page
+    /// cache when the file system does not support it.
+    fn merge_direct(
+        &self,
+        in_dir: &Path,
+        out_file: &Path,
+        indices: &[usize],
+        tracker: &mut Option<Tracker>,
+    ) -> Result<Option<MergeResult>, MergeError> {
+        let mut output: fs::File = match direct::create(out_file) {
+            | Ok(file) => file,
+            | Err(_) => return Ok(None),
+        };
+
+        let merged: u64 = match self.write_direct(
+            in_dir,
+            out_file,
+            &mut output,
+            indices,
+            tracker,
+        ) {
+            | Ok(merged) => merged,
+            | Err(e) => {
+                drop(output);
+                let _ = fs::remove_file(out_file);
+
+                return Err(e);
+            },
+        };
+
+        Ok(Some(MergeResult {
+            file_size: merged as usize,
+            total_chunks: indices.len(),
+            sha256: None,
+            out_file: out_file.to_path_buf(),
+            skipped: false,
+        }))
+    }
+
+    /// Write the chunks to the output opened for unbuffered IO, and get the
+    /// number of bytes merged.
+    fn write_direct(
+        &self,
+        in_dir: &Path,
+        out_file: &Path,
+        output: &mut fs::File,
+        indices: &[usize],
+        tracker: &mut Option<Tracker>,
+    ) -> Result<u64, MergeError> {
+        let mut buffer: AlignedBuffer = AlignedBuffer::new(self.cap_max);
+        let mut pending: AlignedBuffer = AlignedBuffer::new(self.cap_max);
+        let mut pending_len: usize = 0;
+        let mut merged: u64 = 0;
+
+        for index in indices.iter().copied() {
+            let entry: PathBuf = in_dir.join(self.namer.name(index));
+
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(MergeError::Cancelled);
+                }
+            }
+
+            // the chunks may be on a file system without unbuffered IO
+            let mut input: fs::File = match direct::open(&entry)
+                .or_else(|_| fs::File::open(&entry))
+            {
+                | Ok(file) => file,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
+            };
+
+            let mut chunk_bytes: u64 = 0;
+
+            loop {
+                let read: usize = match direct::read_full(
+                    &mut input,
+                    buffer.as_mut_slice(),
+                ) {
+                    | Ok(n) => n,
+                    | Err(e) => {
+                        return Err(MergeError::InFileNotRead {
+                            path: entry,
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                let mut content: &[u8] = &buffer.as_slice()[..read];
+
+                while !content.is_empty() {
+                    let space: &mut [u8] =
+                        &mut pending.as_mut_slice()[pending_len..];
+                    let n: usize = content.len().min(space.len());
+
+                    space[..n].copy_from_slice(&content[..n]);
+                    pending_len += n;
+                    content = &content[n..];
+
+                    // only whole blocks are written until the end
+                    if pending_len == pending.as_slice().len() {
+                        if let Err(e) =
+                            direct::write(output, pending.as_slice())
+                        {
+                            return Err(MergeError::OutFileNotWritten {
+                                path: out_file.to_path_buf(),
+                                source: e.into(),
+                            });
+                        }
+
+                        pending_len = 0;
+                    }
+                }
+
+                chunk_bytes += read as u64;
+
+                if read < buffer.as_slice().len() {
+                    break;
+                }
+            }
+
+            merged += chunk_bytes;
+
+            if let Some(ref c) = self.control {
+                c.record(index, chunk_bytes);
+            }
+
+            if let (Some(t), Some(cb)) = (tracker.as_mut(), &self.on_progress) {
+                cb.call(t.advance(chunk_bytes));
+            }
+        }
+
+        if let Err(e) =
+            direct::write(output, &pending.as_slice()[..pending_len])
+        {
+            return Err(MergeError::OutFileNotWritten {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        }
+
+        Ok(merged)
+    }
+}
+
+impl Merge {
+    /// Get the result of the merge if the output file is already equal the
+    /// chunks.
+    fn merged_result(
+        &self,
+        in_dir: &Path,
+        out_file: &Path,
+        indices: &[usize],
+        sizes: &[u64],
+        buffer_capacity: usize,
+    ) -> Result<Option<MergeResult>, MergeError> {
+        let file_size: u64 = sizes.iter().sum();
+
+        if !fs::metadata(out_file)
+            .is_ok_and(|m| m.is_file() && m.len() == file_size)
+        {
+            return Ok(None);
+        }
+
+        // merged again if the output cannot be read
+        let mut output: fs::File = match fs::File::open(out_file) {
+            | Ok(file) => file,
+            | Err(_) => return Ok(None),
+        };
+
+        let mut output_buffer: Vec<u8> = vec![0; buffer_capacity];
+        let mut chunk_buffer: Vec<u8> = vec![0; buffer_capacity];
+        let mut hasher: Option<Sha256> = self.hash.then(Sha256::new);
+
+        for (index, size) in indices.iter().zip(sizes) {
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(MergeError::Cancelled);
+                }
+            }
+
+            let entry: PathBuf = in_dir.join(self.namer.name(*index));
+
+            let expected: Option<&[u8; 32]> = self.expected_hash(*index);
+            let mut chunk_hasher: Option<Hasher> =
+                expected.map(|_| self.verify_algorithm_of(*index).hasher());
+
+            let mut input: Option<fs::File> = match chunk_hasher {
+                | Some(_) => None,
+                | None => match fs::File::open(&entry) {
+                    | Ok(file) => Some(file),
+                    | Err(e) => {
+                        return Err(MergeError::InFileNotOpened {
+                            path: entry,
+                            source: e.into(),
+                        });
+                    },
+                },
+            };
+
+            let mut remaining: usize = *size as usize;
+
+            while remaining > 0 {
+                let n: usize = remaining.min(buffer_capacity);
+
+                if output.read_exact(&mut output_buffer[..n]).is_err() {
+                    return Ok(None);
+                }
+
+                if let Some(ref mut h) = hasher {
+                    h.update(&output_buffer[..n]);
+                }
+
+                if let Some(ref mut h) = chunk_hasher {
+                    h.update(&output_buffer[..n]);
+                }
+
+                if let Some(ref mut f) = input {
+                    if let Err(e) = f.read_exact(&mut chunk_buffer[..n]) {
+                        return Err(MergeError::InFileNotRead {
+                            path: entry,
+                            source: e.into(),
+                        });
+                    }
+
+                    if output_buffer[..n] != chunk_buffer[..n] {
+                        return Ok(None);
+                    }
+                }
+
+                remaining -= n;
+            }
+
+            if let (Some(h), Some(e)) = (chunk_hasher, expected) {
+                if h.finalize() != *e {
+                    return Ok(None);
+                }
+            }
+        }
+
+        Ok(Some(MergeResult {
+            file_size: file_size as usize,
+            total_chunks: indices.len(),
+            sha256: hasher.map(|h| hash::to_hex(&h.finalize())),
+            out_file: out_file.to_path_buf(),
+            skipped: true,
+        }))
+    }
+}
+
+/// Move the complete temporary file to the output file.
+#[cfg(any(
+    all(feature = "rayon", any(unix, windows)),
+    all(feature = "mmap", target_os = "linux")
+))]
+fn persist_temp(
+    temp: &Path,
+    out_file: &Path,
+) -> Result<(), MergeError> {
+    match fs::rename(temp, out_file) {
+        | Ok(()) => Ok(()),
+        | Err(e) => Err(MergeError::OutFileNotWritten {
+            path: out_file.to_path_buf(),
+            source: e.into(),
+        }),
+    }
+}
+
+/// Hash the first `len` bytes of the file.
+fn hash_prefix(
+    file: &fs::File,
+    len: u64,
+    hasher: &mut Sha256,
+    buffer_capacity: usize,
+) -> io::Result<()> {
+    let mut reader: io::Take<&fs::File> = file.take(len);
+    let mut buffer: Vec<u8> = vec![0; buffer_capacity.max(1)];
+
+    loop {
+        let n: usize = reader.read(&mut buffer)?;
+
+        if n == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(())
+}
+
+/// Merge the chunks in order as they arrive, until the input is ended.
+///
+/// A chunk is merged once the next one exists or the input is ended, as it
+/// may still be written before.
+fn merge_following(
+    process: &Merge,
+    in_dir: &Path,
+    out_file: &Path,
+    follow: &Follow,
+) -> Result<MergeResult, MergeError> {
+    // keep or refuse an existing output
+    if let Some(r) = process.existing_output(out_file, 0)? {
+        return Ok(r);
+    }
+
+    // replace an existing output file
+    if out_file.exists() {
+        if let Err(e) = fs::remove_file(out_file) {
+            return Err(MergeError::OutFileNotRemoved {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        }
+    }
+
+    // create outpath
+    if let Some(parent) = out_file.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            return Err(MergeError::OutDirNotCreated {
+                path: parent.to_path_buf(),
+                source: e.into(),
+            });
+        }
+    }
+
+    let mut output: fs::File = match fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(out_file)
+    {
+        | Ok(file) => file,
+        | Err(e) => {
+            return Err(MergeError::OutFileNotOpened {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        },
+    };
+
+    let mut index: usize = 0;
+    let mut merged: u64 = 0;
+    let mut hasher: Option<Sha256> = process.hash.then(Sha256::new);
+    let mut throttle: Option<Throttle> =
+        process.throttle.map(|r| Throttle::new(r, process.clock.clone()));
+
+    loop {
+        if let Some(ref c) = process.control {
+            if !c.wait() {
+                return Err(MergeError::Cancelled);
+            }
+        }
+
+        // checked before the chunks, so no chunk written before the end is
+        // missed
+        let ended: bool = follow.is_ended();
+
+        // the names of the chunks may depend on their content
+        process.namer.scan(in_dir);
+
+        let entry: PathBuf = in_dir.join(process.namer.name(index));
+
+        let complete: bool = entry.is_file()
+            && (ended || in_dir.join(process.namer.name(index + 1)).exists());
+
+        if !complete {
+            if ended {
+                break;
+            }
+
+            thread::sleep(follow.interval);
+
+            continue;
+        }
+
+        if process.max_chunks.is_some_and(|max| index >= max) {
+            return Err(MergeError::TooManyChunks);
+        }
+
+        // if entry is a symlink that may point outside in_dir
+        if process.reject_unsafe_paths
+            && entry.symlink_metadata().is_ok_and(|m| m.is_symlink())
+        {
+            return Err(MergeError::InFileNotSafe);
+        }
+
+        let mut input: fs::File =
+            match fs::OpenOptions::new().read(true).open(&entry) {
+                | Ok(file) => file,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
+            };
+
+        merged += match input.metadata() {
+            | Ok(metadata) => metadata.len(),
+            | Err(e) => {
+                return Err(MergeError::InFileNotRead {
+                    path: entry,
+                    source: e.into(),
+                });
+            },
+        };
+
+        // stop before the output exceeds the limit
+        if process.max_file_size.is_some_and(|max| merged > max) {
+            drop(output);
+            let _ = fs::remove_file(out_file);
+
+            return Err(MergeError::FileTooLarge);
+        }
+
+        let expected: Option<&[u8; 32]> = process.expected_hash(index);
+
+        let written: u64 = if hasher.is_some()
+            || expected.is_some()
+            || !process.sinks.is_empty()
+        {
+            let mut content: Vec<u8> = Vec::new();
+
+            if let Err(e) = input.read_to_end(&mut content) {
+                return Err(MergeError::InFileNotRead {
+                    path: entry,
+                    source: e.into(),
+                });
+            }
+
+            // stop before the corrupted chunk is used
+            if expected.is_some_and(|e| {
+                process.verify_algorithm_of(index).digest(&content) != *e
+            }) {
+                drop(output);
+                let _ = fs::remove_file(out_file);
+
+                return Err(MergeError::ChunkCorrupted { index });
+            }
+
+            if let Some(ref mut h) = hasher {
+                h.update(&content);
+            }
+
+            if let Err(e) = output.write_all(&content) {
+                return Err(MergeError::OutFileNotWritten {
+                    path: out_file.to_path_buf(),
+                    source: e.into(),
+                });
+            }
+
+            process.write_sinks(&content)?;
+
+            content.len() as u64
+        } else {
+            match io::copy(&mut input, &mut output) {
+                | Ok(written) => written,
+                | Err(e) => {
+                    return Err(MergeError::OutFileNotWritten {
+                        path: out_file.to_path_buf(),
+                        source: e.into(),
+                    });
+                },
+            }
+        };
+
+        if let Some(ref c) = process.control {
+            c.record(index, written);
+        }
+
+        if let Some(ref mut t) = throttle {
+            t.wait(written);
+        }
+
+        index += 1;
+    }
+
+    if index == 0 {
+        return Err(MergeError::InDirNoFile);
+    }
+
+    Ok(MergeResult {
+        file_size: merged as usize,
+        total_chunks: index,
+        sha256: hasher.map(|h| hash::to_hex(&h.finalize())),
+        out_file: out_file.to_path_buf(),
+        skipped: false,
+    })
 }
 
 impl Default for Merge {
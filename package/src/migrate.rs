@@ -0,0 +1,436 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "manifest")]
+use crate::manifest::{MANIFEST_FILE_NAME, Manifest};
+use crate::{
+    error::IoError,
+    naming::{ChunkNamer, Plain},
+};
+
+/// Result of the migrate process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MigrateResult {
+    /// The total number of chunks in the new layout.
+    pub total_chunks: usize,
+    /// The number of chunks moved by this run, the others were already
+    /// migrated by an interrupted run.
+    pub migrated: usize,
+    /// Paths of the chunks, in order.
+    pub chunk_paths: Vec<PathBuf>,
+}
+
+/// Error of the migrate process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MigrateError {
+    InDirNotFound,
+    InDirNotDir,
+    InDirNotSet,
+    InDirNotRead { path: PathBuf, source: IoError },
+    InDirNoFile,
+    OutDirNotCreated { path: PathBuf, source: IoError },
+    NameCollision,
+    ChunkNotMoved { path: PathBuf, source: IoError },
+    ManifestNotRead { path: PathBuf, source: IoError },
+    ManifestNotWritten { path: PathBuf, source: IoError },
+}
+
+impl MigrateError {
+    /// Get the code of the error as `&str`.
+    pub fn as_code(&self) -> &str {
+        match self {
+            | Self::InDirNotFound => "in_dir_not_found",
+            | Self::InDirNotDir => "in_dir_not_dir",
+            | Self::InDirNotSet => "in_dir_not_set",
+            | Self::InDirNotRead { .. } => "in_dir_not_read",
+            | Self::InDirNoFile => "in_dir_no_file",
+            | Self::OutDirNotCreated { .. } => "out_dir_not_created",
+            | Self::NameCollision => "name_collision",
+            | Self::ChunkNotMoved { .. } => "chunk_not_moved",
+            | Self::ManifestNotRead { .. } => "manifest_not_read",
+            | Self::ManifestNotWritten { .. } => "manifest_not_written",
+        }
+    }
+
+    /// Get the code of the error as `String`.
+    pub fn to_code(&self) -> String {
+        self.as_code().to_string()
+    }
+
+    /// Get the message of the error as `&str`.
+    pub fn as_message(&self) -> &str {
+        match self {
+            | Self::InDirNotFound => "The input directory not found.",
+            | Self::InDirNotDir => "The input directory is not a directory.",
+            | Self::InDirNotSet => "The input directory is not set.",
+            | Self::InDirNotRead { .. } => {
+                "The input directory could not be read."
+            },
+            | Self::InDirNoFile => "The input directory has no file.",
+            | Self::OutDirNotCreated { .. } => {
+                "The output directory could not be created."
+            },
+            | Self::NameCollision => {
+                "A chunk name is used by another chunk in the other layout."
+            },
+            | Self::ChunkNotMoved { .. } => "The chunk could not be moved.",
+            | Self::ManifestNotRead { .. } => "The manifest could not be read.",
+            | Self::ManifestNotWritten { .. } => {
+                "The manifest could not be written."
+            },
+        }
+    }
+
+    /// Get the message of the error as `String`.
+    pub fn to_message(&self) -> String {
+        self.as_message().to_string()
+    }
+
+    /// Get the path involved in the error, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.io().map(|(path, _)| path)
+    }
+
+    /// Get the path and the I/O error of the error, if any.
+    fn io(&self) -> Option<(&Path, &IoError)> {
+        match self {
+            | Self::InDirNotRead { path, source }
+            | Self::OutDirNotCreated { path, source }
+            | Self::ChunkNotMoved { path, source }
+            | Self::ManifestNotRead { path, source }
+            | Self::ManifestNotWritten { path, source } => Some((path, source)),
+            | _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self.io() {
+            | Some((path, source)) => write!(
+                f,
+                "{} ({}: {})",
+                self.as_message(),
+                path.display(),
+                source
+            ),
+            | None => f.write_str(self.as_message()),
+        }
+    }
+}
+
+impl Error for MigrateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.io().map(|(_, source)| source.as_ref() as &(dyn Error + 'static))
+    }
+}
+
+/// Process to convert chunks to another layout.
+///
+/// The chunks are renamed from the naming convention set by
+/// [`from`](Self::from) to the one set by [`to`](Self::to), one at a time,
+/// and moved to the output directory if it is set, so the content is never
+/// merged or copied within a file system. A manifest next to the chunks is
+/// rewritten with the new names once all of them are moved.
+///
+/// An interrupted migration is resumed by running it again, as chunks
+/// already in the new layout are kept. Only the naming of the chunks can be
+/// migrated, as the chunks are always raw slices of the original file.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     migrate::{Migrate, MigrateResult},
+///     naming::{Gnu, Plain},
+/// };
+///
+/// let result: MigrateResult = Migrate::new()
+///     .in_dir(PathBuf::from("path").join("to").join("dir"))
+///     .from(Plain)
+///     .to(Gnu::new())
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Migrate {
+    pub in_dir: Option<PathBuf>,
+    pub out_dir: Option<PathBuf>,
+    pub from: Arc<dyn ChunkNamer>,
+    pub to: Arc<dyn ChunkNamer>,
+}
+
+impl Migrate {
+    /// Create a new migrate process.
+    pub fn new() -> Self {
+        Self {
+            in_dir: None,
+            out_dir: None,
+            from: Arc::new(Plain),
+            to: Arc::new(Plain),
+        }
+    }
+
+    /// Set the input directory.
+    pub fn in_dir<InDir: AsRef<Path>>(
+        mut self,
+        path: InDir,
+    ) -> Self {
+        self.in_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the output directory.
+    ///
+    /// By default, the chunks are renamed within the input directory.
+    pub fn out_dir<OutDir: AsRef<Path>>(
+        mut self,
+        path: OutDir,
+    ) -> Self {
+        self.out_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the naming convention of the existing chunks.
+    ///
+    /// By default, the chunks are named by their index with [`Plain`].
+    pub fn from<N: ChunkNamer + 'static>(
+        mut self,
+        namer: N,
+    ) -> Self {
+        self.from = Arc::new(namer);
+        self
+    }
+
+    /// Set the naming convention of the migrated chunks.
+    ///
+    /// By default, the chunks are named by their index with [`Plain`].
+    pub fn to<N: ChunkNamer + 'static>(
+        mut self,
+        namer: N,
+    ) -> Self {
+        self.to = Arc::new(namer);
+        self
+    }
+
+    /// Run the migrate process.
+    pub fn run(&self) -> Result<MigrateResult, MigrateError> {
+        let in_dir: &Path = match self.in_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_ref();
+
+                // if in_dir not exists
+                if !p.exists() {
+                    return Err(MigrateError::InDirNotFound);
+                }
+
+                // if in_dir not a directory
+                if !p.is_dir() {
+                    return Err(MigrateError::InDirNotDir);
+                }
+
+                p
+            },
+            | None => return Err(MigrateError::InDirNotSet),
+        };
+
+        let out_dir: &Path = self.out_dir.as_deref().unwrap_or(in_dir);
+
+        if let Err(e) = fs::create_dir_all(out_dir) {
+            return Err(MigrateError::OutDirNotCreated {
+                path: out_dir.to_path_buf(),
+                source: e.into(),
+            });
+        }
+
+        // the chunks are renamed within the input directory
+        let in_place: bool =
+            match (in_dir.canonicalize(), out_dir.canonicalize()) {
+                | (Ok(a), Ok(b)) => a == b,
+                | _ => in_dir == out_dir,
+            };
+
+        let out_dir: &Path = if in_place { in_dir } else { out_dir };
+
+        // the chunks still to move, and the chunks already moved by an
+        // interrupted run
+        let mut pending: BTreeMap<usize, PathBuf> = BTreeMap::new();
+        let mut done: BTreeMap<usize, PathBuf> = BTreeMap::new();
+
+        let dirs: &[&Path] =
+            if in_place { &[in_dir] } else { &[in_dir, out_dir] };
+
+        for dir in dirs.iter().copied() {
+            let read_dir: fs::ReadDir = match fs::read_dir(dir) {
+                | Ok(read_dir) => read_dir,
+                | Err(e) => {
+                    return Err(MigrateError::InDirNotRead {
+                        path: dir.to_path_buf(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            for entry in read_dir.filter_map(Result::ok) {
+                let path: PathBuf = entry.path();
+
+                if !path.is_file() {
+                    continue;
+                }
+
+                let name: &str = match path.file_name().and_then(|n| n.to_str())
+                {
+                    | Some(name) => name,
+                    | None => continue,
+                };
+
+                let old: Option<usize> =
+                    (dir == in_dir).then(|| self.from.parse(name)).flatten();
+                let new: Option<usize> =
+                    (dir == out_dir).then(|| self.to.parse(name)).flatten();
+
+                match (old, new) {
+                    // the name is the same in both layouts
+                    | (Some(a), Some(b)) if a == b => {
+                        done.insert(b, path);
+                    },
+                    // moving it would overwrite another chunk
+                    | (Some(_), Some(_)) => {
+                        return Err(MigrateError::NameCollision);
+                    },
+                    | (Some(index), None) => {
+                        pending.insert(index, path);
+                    },
+                    | (None, Some(index)) => {
+                        done.insert(index, path);
+                    },
+                    | (None, None) => {},
+                }
+            }
+        }
+
+        if pending.is_empty() && done.is_empty() {
+            return Err(MigrateError::InDirNoFile);
+        }
+
+        // refuse to overwrite a chunk of another index before moving any
+        for (index, path) in pending.iter() {
+            let target: PathBuf = out_dir.join(self.target_name(*index, path));
+
+            if target.exists() && done.get(index) != Some(&target) {
+                return Err(MigrateError::NameCollision);
+            }
+        }
+
+        let mut migrated: usize = 0;
+
+        for (index, path) in pending.iter() {
+            let target: PathBuf = out_dir.join(self.to.name(*index));
+
+            // moved across file systems by a copy
+            if let Err(e) = fs::rename(path, &target).or_else(|_| {
+                fs::copy(path, &target).and_then(|_| fs::remove_file(path))
+            }) {
+                return Err(MigrateError::ChunkNotMoved {
+                    path: path.clone(),
+                    source: e.into(),
+                });
+            }
+
+            done.insert(*index, target);
+            migrated += 1;
+        }
+
+        #[cfg(feature = "manifest")]
+        self.migrate_manifest(in_dir, out_dir)?;
+
+        Ok(MigrateResult {
+            total_chunks: done.len(),
+            migrated,
+            chunk_paths: done.into_values().collect(),
+        })
+    }
+
+    /// Describe the chunks with their new names in the manifest, if any.
+    #[cfg(feature = "manifest")]
+    fn migrate_manifest(
+        &self,
+        in_dir: &Path,
+        out_dir: &Path,
+    ) -> Result<(), MigrateError> {
+        self.to.scan(out_dir);
+
+        let manifest_path: PathBuf = in_dir.join(MANIFEST_FILE_NAME);
+
+        if !manifest_path.is_file() {
+            return Ok(());
+        }
+
+        let mut manifest: Manifest = match Manifest::read(&manifest_path) {
+            | Ok(m) => m,
+            | Err(e) => {
+                return Err(MigrateError::ManifestNotRead {
+                    path: manifest_path,
+                    source: e.into(),
+                });
+            },
+        };
+
+        for chunk in manifest.chunks.iter_mut() {
+            chunk.name = self.to.name(chunk.index);
+        }
+
+        let out_path: PathBuf = out_dir.join(MANIFEST_FILE_NAME);
+
+        if let Err(e) = manifest.write(&out_path) {
+            return Err(MigrateError::ManifestNotWritten {
+                path: out_path,
+                source: e.into(),
+            });
+        }
+
+        if out_dir != in_dir {
+            let _ = fs::remove_file(&manifest_path);
+        }
+
+        Ok(())
+    }
+
+    /// Get the new name of the chunk, reading it if the name depends on
+    /// its content.
+    fn target_name(
+        &self,
+        index: usize,
+        path: &Path,
+    ) -> String {
+        if self.to.uses_content() {
+            if let Ok(content) = fs::read(path) {
+                return self.to.name_content(index, &content);
+            }
+        }
+
+        self.to.name(index)
+    }
+}
+
+impl Default for Migrate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,92 @@
+use std::{fs::File, io, os::fd::AsRawFd as _, ptr, slice};
+
+/// A file mapped into memory.
+///
+/// The mapping is shared, so the changes are written back to the file.
+#[derive(Debug)]
+pub(crate) struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    /// Map the first `len` bytes of the file to read them.
+    ///
+    /// The file must not be truncated while it is mapped, as reading the
+    /// missing pages raises `SIGBUS`.
+    pub(crate) fn map(
+        file: &File,
+        len: usize,
+    ) -> io::Result<Self> {
+        Self::map_with(file, len, libc::PROT_READ)
+    }
+
+    /// Map the first `len` bytes of the file to write them.
+    ///
+    /// The file must already be at least `len` bytes long.
+    pub(crate) fn map_mut(
+        file: &File,
+        len: usize,
+    ) -> io::Result<Self> {
+        Self::map_with(file, len, libc::PROT_READ | libc::PROT_WRITE)
+    }
+
+    fn map_with(
+        file: &File,
+        len: usize,
+        prot: libc::c_int,
+    ) -> io::Result<Self> {
+        // an empty mapping is refused by the kernel
+        if len == 0 {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+
+        let ptr: *mut libc::c_void = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                prot,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { ptr, len })
+    }
+
+    /// Get the mapped bytes.
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    /// Get the mapped bytes to write them.
+    ///
+    /// Only a mapping created with [`map_mut`](Self::map_mut) may be
+    /// written.
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr as *mut u8, self.len) }
+    }
+
+    /// Write the changed pages back to the file.
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        let res: libc::c_int =
+            unsafe { libc::msync(self.ptr, self.len, libc::MS_SYNC) };
+
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
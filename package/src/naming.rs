@@ -0,0 +1,719 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Debug},
+    fs,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{hash, plan};
+
+/// Naming convention of the chunk files.
+///
+/// It maps an index to a file name and back. The split process uses
+/// [`name`](ChunkNamer::name) to create the chunks, the check process uses
+/// it to find them, and the merge process uses
+/// [`parse`](ChunkNamer::parse) to order them. Both functions must be the
+/// inverse of each other.
+pub trait ChunkNamer: Debug + Send + Sync {
+    /// Get the file name of the chunk at the index.
+    fn name(
+        &self,
+        index: usize,
+    ) -> String;
+
+    /// Parse the index from a file name, `None` if it is not a chunk.
+    fn parse(
+        &self,
+        name: &str,
+    ) -> Option<usize>;
+
+    /// Get the file name of the chunk at the index from its content.
+    ///
+    /// The split process names the chunks it writes with it. By default,
+    /// the name does not depend on the content.
+    fn name_content(
+        &self,
+        index: usize,
+        _content: &[u8],
+    ) -> String {
+        self.name(index)
+    }
+
+    /// Check whether the names depend on the content of the chunks.
+    ///
+    /// Such chunks are split sequentially, as each chunk is read before it
+    /// is named.
+    fn uses_content(&self) -> bool {
+        false
+    }
+
+    /// Learn the names of the chunks in a directory.
+    ///
+    /// The check, merge and migrate processes call it before naming the
+    /// chunks by their index, so names depending on the content are found.
+    /// By default, it does nothing.
+    fn scan(
+        &self,
+        _dir: &Path,
+    ) {
+    }
+}
+
+/// Device names reserved by Windows, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6",
+    "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6",
+    "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters refused in file names by Windows, besides control characters.
+const INVALID_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Check whether the file name is valid on Windows.
+///
+/// A valid name is not empty, `.` or `..`, does not end with a dot or a
+/// space, contains no control characters or any of `<>:"/\|?*`, and is not
+/// a reserved device name like `CON` or `nul.txt`.
+///
+/// ## Example
+///
+/// ```
+/// use filego::naming::is_portable;
+///
+/// assert!(is_portable("part0"));
+/// assert!(!is_portable("nul"));
+/// assert!(!is_portable("part:0"));
+/// ```
+pub fn is_portable(name: &str) -> bool {
+    if name.is_empty() || name.ends_with(['.', ' ']) {
+        return false;
+    }
+
+    if name.chars().any(|c| c.is_control() || INVALID_CHARS.contains(&c)) {
+        return false;
+    }
+
+    // the extension does not make a reserved name usable
+    let stem: &str = name.split('.').next().unwrap_or(name).trim_end();
+
+    !RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem))
+}
+
+/// Find the first name equal to a preceding one when the case is ignored.
+///
+/// Such names refer to the same file on case-insensitive file systems, e.g.
+/// the defaults of macOS and Windows, so the later chunk would overwrite the
+/// earlier one. The names are compared by their lowercase form, which
+/// covers the case folding of these file systems for most scripts.
+///
+/// ## Example
+///
+/// ```
+/// use filego::naming::find_collision;
+///
+/// assert_eq!(find_collision(["a", "b", "c"]), None);
+/// assert_eq!(find_collision(["a", "b", "A"]), Some("A"));
+/// ```
+pub fn find_collision<'a, I: IntoIterator<Item = &'a str>>(
+    names: I
+) -> Option<&'a str> {
+    let mut seen: HashSet<String> = HashSet::new();
+
+    names.into_iter().find(|name| !seen.insert(name.to_lowercase()))
+}
+
+/// Chunks named by their index, e.g. `0`, `1`, `2`.
+///
+/// This is the default naming convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Plain;
+
+impl ChunkNamer for Plain {
+    fn name(
+        &self,
+        index: usize,
+    ) -> String {
+        index.to_string()
+    }
+
+    fn parse(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        plan::parse_chunk_name(name)
+    }
+}
+
+/// Chunks named by their zero-padded index, e.g. `000`, `001`, `002`.
+///
+/// Indices wider than `width` are not truncated, so `1000` follows `999`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Padded {
+    pub width: usize,
+}
+
+impl Padded {
+    /// Create a new padded naming convention.
+    pub fn new(width: usize) -> Self {
+        Self { width }
+    }
+}
+
+impl ChunkNamer for Padded {
+    fn name(
+        &self,
+        index: usize,
+    ) -> String {
+        format!("{:0width$}", index, width = self.width)
+    }
+
+    fn parse(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        if name.len() < self.width || !name.bytes().all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+
+        // wider names must not be padded
+        if name.len() > self.width.max(1) && name.starts_with('0') {
+            return None;
+        }
+
+        name.parse::<usize>().ok()
+    }
+}
+
+/// Chunks named by a prefix followed by their index, e.g. `part0`, `part1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prefixed {
+    pub prefix: String,
+}
+
+impl Prefixed {
+    /// Create a new prefixed naming convention.
+    pub fn new<P: Into<String>>(prefix: P) -> Self {
+        Self { prefix: prefix.into() }
+    }
+}
+
+impl ChunkNamer for Prefixed {
+    fn name(
+        &self,
+        index: usize,
+    ) -> String {
+        format!("{}{}", self.prefix, index)
+    }
+
+    fn parse(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        plan::parse_chunk_name(name.strip_prefix(self.prefix.as_str())?)
+    }
+}
+
+/// Chunks named like the output of GNU `split`, e.g. `xaa`, `xab`, `xac`.
+///
+/// The suffixes are alphabetic by default, or numeric like `split -d`.
+/// Once the two-character suffixes are exhausted, they are extended in the
+/// same way as GNU `split` (`xyz` is followed by `xzaaa`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gnu {
+    pub prefix: String,
+    pub numeric: bool,
+}
+
+impl Gnu {
+    /// Create a new GNU naming convention with the default `x` prefix.
+    pub fn new() -> Self {
+        Self { prefix: "x".to_string(), numeric: false }
+    }
+
+    /// Set the prefix of the names.
+    pub fn prefix<P: Into<String>>(
+        mut self,
+        prefix: P,
+    ) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set whether the suffixes are numeric.
+    pub fn numeric(
+        mut self,
+        numeric: bool,
+    ) -> Self {
+        self.numeric = numeric;
+        self
+    }
+
+    fn symbols(&self) -> &'static [u8] {
+        if self.numeric { b"0123456789" } else { b"abcdefghijklmnopqrstuvwxyz" }
+    }
+
+    /// Number of names of the stage with the given suffix width, `None` if
+    /// it exceeds `usize`.
+    fn stage_len(
+        base: usize,
+        width: usize,
+    ) -> Option<usize> {
+        (base - 1).checked_mul(base.checked_pow((width - 1) as u32)?)
+    }
+}
+
+impl Default for Gnu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChunkNamer for Gnu {
+    fn name(
+        &self,
+        mut index: usize,
+    ) -> String {
+        let symbols: &[u8] = self.symbols();
+        let base: usize = symbols.len();
+        let last: char = symbols[base - 1] as char;
+
+        let mut stage: usize = 0;
+        let mut width: usize = 2;
+
+        while let Some(len) = Self::stage_len(base, width) {
+            if index < len {
+                break;
+            }
+
+            index -= len;
+            stage += 1;
+            width += 1;
+        }
+
+        let mut suffix: Vec<u8> = vec![symbols[0]; width];
+
+        for digit in suffix.iter_mut().rev() {
+            *digit = symbols[index % base];
+            index /= base;
+        }
+
+        let mut name: String = self.prefix.clone();
+        name.extend(std::iter::repeat_n(last, stage));
+        name.push_str(std::str::from_utf8(&suffix).unwrap_or_default());
+        name
+    }
+
+    fn parse(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        let symbols: &[u8] = self.symbols();
+        let base: usize = symbols.len();
+        let last: u8 = symbols[base - 1];
+
+        let name: &[u8] = name.strip_prefix(self.prefix.as_str())?.as_bytes();
+
+        let stage: usize = name.iter().take_while(|b| **b == last).count();
+        let suffix: &[u8] = &name[stage..];
+
+        if suffix.len() != stage + 2 {
+            return None;
+        }
+
+        let mut index: usize = 0;
+
+        for width in 2..suffix.len() {
+            index = index.checked_add(Self::stage_len(base, width)?)?;
+        }
+
+        let mut value: usize = 0;
+
+        for b in suffix {
+            let digit: usize = symbols.iter().position(|s| s == b)?;
+            value = value.checked_mul(base)?.checked_add(digit)?;
+        }
+
+        index.checked_add(value)
+    }
+}
+
+/// Error of a naming template, see [`Template::new`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TemplateError {
+    PlaceholderUnknown { placeholder: String },
+    PlaceholderNotClosed,
+}
+
+impl TemplateError {
+    /// Get the code of the error as `&str`.
+    pub fn as_code(&self) -> &str {
+        match self {
+            | Self::PlaceholderUnknown { .. } => "placeholder_unknown",
+            | Self::PlaceholderNotClosed => "placeholder_not_closed",
+        }
+    }
+
+    /// Get the code of the error as `String`.
+    pub fn to_code(&self) -> String {
+        self.as_code().to_string()
+    }
+
+    /// Get the message of the error as `&str`.
+    pub fn as_message(&self) -> &str {
+        match self {
+            | Self::PlaceholderUnknown { .. } => {
+                "The template has an unknown placeholder."
+            },
+            | Self::PlaceholderNotClosed => {
+                "The template has a placeholder that is not closed."
+            },
+        }
+    }
+
+    /// Get the message of the error as `String`.
+    pub fn to_message(&self) -> String {
+        self.as_message().to_string()
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            | Self::PlaceholderUnknown { placeholder } => {
+                write!(f, "{} ({{{}}})", self.as_message(), placeholder)
+            },
+            | _ => f.write_str(self.as_message()),
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// Part of a naming template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Name,
+    Stem,
+    Extension,
+    Index(usize),
+    Hash(usize),
+}
+
+/// Chunks named by a template, e.g. `{stem}.{index:06}.{hash8}.part`.
+///
+/// The placeholders are:
+///
+/// - `{name}`, `{stem}` and `{ext}`: the file name of the input file, and
+///   its name without and only its extension, see [`file`](Self::file).
+/// - `{index}` or `{index:0N}`: the index, zero-padded to `N` digits.
+/// - `{hashN}`: the first `N` hex digits of the SHA-256 hash of the chunk,
+///   up to 64.
+///
+/// Any other text is kept as is, but a `{` always opens a placeholder, so
+/// an unknown or unclosed placeholder is rejected by [`new`](Self::new)
+/// rather than repeated in every name. The template must contain the index
+/// placeholder to give each chunk its own name, otherwise the split process
+/// returns [`SplitError::ChunkNameNotUnique`].
+///
+/// The names are parsed back by matching the template, the file name
+/// placeholders with the values of the input file. A name with a hash is
+/// only known once the chunk is written or its directory is scanned, see
+/// [`ChunkNamer::scan`], and is named with zeros as its hash until then. A
+/// scanned index with several names, e.g. stale chunks of another file, is
+/// left unknown. The known hashes are shared by the clones of the template.
+///
+/// [`SplitError::ChunkNameNotUnique`]: crate::split::SplitError::ChunkNameNotUnique
+///
+/// ## Example
+///
+/// ```
+/// use filego::naming::{ChunkNamer, Template};
+///
+/// let namer: Template = Template::new("{stem}.{index:06}.{hash8}.part")
+///     .unwrap()
+///     .file("video.mp4");
+///
+/// let name: String = namer.name_content(1, b"content");
+///
+/// assert_eq!(name, "video.000001.ed7002b4.part");
+/// assert_eq!(namer.parse(&name), Some(1));
+/// assert_eq!(namer.name(1), name);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+    name: String,
+    stem: String,
+    extension: String,
+    hashes: Arc<Mutex<HashMap<usize, String>>>,
+}
+
+impl Template {
+    /// Create a new naming template.
+    ///
+    /// Returns [`TemplateError`] if a placeholder is unknown or not closed.
+    pub fn new<T: AsRef<str>>(template: T) -> Result<Self, TemplateError> {
+        let mut parts: Vec<Part> = Vec::new();
+        let mut rest: &str = template.as_ref();
+
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix('{') {
+                let (placeholder, r) = match r.split_once('}') {
+                    | Some(p) => p,
+                    | None => return Err(TemplateError::PlaceholderNotClosed),
+                };
+
+                match Self::placeholder(placeholder) {
+                    | Some(part) => parts.push(part),
+                    | None => {
+                        return Err(TemplateError::PlaceholderUnknown {
+                            placeholder: placeholder.to_string(),
+                        });
+                    },
+                }
+
+                rest = r;
+
+                continue;
+            }
+
+            // text up to the next placeholder
+            let end: usize = rest.find('{').unwrap_or(rest.len());
+
+            match parts.last_mut() {
+                | Some(Part::Literal(s)) => s.push_str(&rest[..end]),
+                | _ => parts.push(Part::Literal(rest[..end].to_string())),
+            }
+
+            rest = &rest[end..];
+        }
+
+        Ok(Self {
+            parts,
+            name: String::new(),
+            stem: String::new(),
+            extension: String::new(),
+            hashes: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Set the input file filling the file name placeholders.
+    pub fn file<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> Self {
+        let path: &Path = path.as_ref();
+        let to_string = |s: Option<&std::ffi::OsStr>| {
+            s.map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+        };
+
+        self.name = to_string(path.file_name());
+        self.stem = to_string(path.file_stem());
+        self.extension = to_string(path.extension());
+        self
+    }
+
+    fn placeholder(text: &str) -> Option<Part> {
+        match text {
+            | "name" => Some(Part::Name),
+            | "stem" => Some(Part::Stem),
+            | "ext" => Some(Part::Extension),
+            | "index" => Some(Part::Index(0)),
+            | _ => {
+                if let Some(width) = text.strip_prefix("index:0") {
+                    return width.parse().ok().map(Part::Index);
+                }
+
+                match text.strip_prefix("hash")?.parse() {
+                    | Ok(len) if (1..=64).contains(&len) => {
+                        Some(Part::Hash(len))
+                    },
+                    | _ => None,
+                }
+            },
+        }
+    }
+
+    fn hashes(&self) -> MutexGuard<'_, HashMap<usize, String>> {
+        self.hashes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn render(
+        &self,
+        index: usize,
+        hash: Option<&str>,
+    ) -> String {
+        let mut name: String = String::new();
+
+        for part in self.parts.iter() {
+            match part {
+                | Part::Literal(s) => name.push_str(s),
+                | Part::Name => name.push_str(&self.name),
+                | Part::Stem => name.push_str(&self.stem),
+                | Part::Extension => name.push_str(&self.extension),
+                | Part::Index(width) => {
+                    name.push_str(&Padded::new(*width).name(index))
+                },
+                | Part::Hash(len) => match hash.and_then(|h| h.get(..*len)) {
+                    | Some(h) => name.push_str(h),
+                    | None => name.extend(std::iter::repeat_n('0', *len)),
+                },
+            }
+        }
+
+        name
+    }
+
+    /// Match the parts to the name, returning the index and the hash.
+    fn matches<'a>(
+        &self,
+        parts: &[Part],
+        name: &'a str,
+        index: Option<usize>,
+        hash: Option<&'a str>,
+    ) -> Option<(Option<usize>, Option<&'a str>)> {
+        let (part, parts) = match parts.split_first() {
+            | Some(p) => p,
+            | None => return name.is_empty().then_some((index, hash)),
+        };
+
+        let literal: &str = match part {
+            | Part::Literal(s) => s,
+            | Part::Name => &self.name,
+            | Part::Stem => &self.stem,
+            | Part::Extension => &self.extension,
+            | Part::Index(width) => {
+                let digits: usize =
+                    name.bytes().take_while(u8::is_ascii_digit).count();
+
+                // the longest run of digits first, then shorter ones
+                return (1..=digits).rev().find_map(|end| {
+                    let parsed: usize =
+                        Padded::new(*width).parse(&name[..end])?;
+
+                    // every index placeholder must agree
+                    if index.is_some_and(|i| i != parsed) {
+                        return None;
+                    }
+
+                    self.matches(parts, &name[end..], Some(parsed), hash)
+                });
+            },
+            | Part::Hash(len) => {
+                let value: &str = name.get(..*len)?;
+
+                if !value
+                    .bytes()
+                    .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+                {
+                    return None;
+                }
+
+                // every hash placeholder must agree, the longest is kept
+                let hash: &str = match hash {
+                    | Some(h) if h.starts_with(value) => h,
+                    | Some(h) if value.starts_with(h) => value,
+                    | Some(_) => return None,
+                    | None => value,
+                };
+
+                return self.matches(parts, &name[*len..], index, Some(hash));
+            },
+        };
+
+        self.matches(parts, name.strip_prefix(literal)?, index, hash)
+    }
+}
+
+impl ChunkNamer for Template {
+    fn name(
+        &self,
+        index: usize,
+    ) -> String {
+        let hashes: MutexGuard<'_, HashMap<usize, String>> = self.hashes();
+
+        self.render(index, hashes.get(&index).map(String::as_str))
+    }
+
+    fn parse(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        self.matches(&self.parts, name, None, None)?.0
+    }
+
+    fn name_content(
+        &self,
+        index: usize,
+        content: &[u8],
+    ) -> String {
+        if !self.uses_content() {
+            return self.name(index);
+        }
+
+        let hash: String = hash::sha256_hex(content);
+        let name: String = self.render(index, Some(&hash));
+
+        self.hashes().insert(index, hash);
+
+        name
+    }
+
+    fn uses_content(&self) -> bool {
+        self.parts.iter().any(|p| matches!(p, Part::Hash(_)))
+    }
+
+    fn scan(
+        &self,
+        dir: &Path,
+    ) {
+        if !self.uses_content() {
+            return;
+        }
+
+        let entries: fs::ReadDir = match fs::read_dir(dir) {
+            | Ok(entries) => entries,
+            | Err(_) => return,
+        };
+
+        // the hash of each index, `None` if several names have the index
+        let mut found: HashMap<usize, Option<String>> = HashMap::new();
+
+        for entry in entries.flatten() {
+            let name: String = match entry.file_name().into_string() {
+                | Ok(name) => name,
+                | Err(_) => continue,
+            };
+
+            if let Some((Some(index), Some(hash))) =
+                self.matches(&self.parts, &name, None, None)
+            {
+                found
+                    .entry(index)
+                    .and_modify(|h| *h = None)
+                    .or_insert_with(|| Some(hash.to_string()));
+            }
+        }
+
+        let mut hashes: MutexGuard<'_, HashMap<usize, String>> = self.hashes();
+
+        for (index, hash) in found {
+            match hash {
+                | Some(h) => hashes.insert(index, h),
+                | None => hashes.remove(&index),
+            };
+        }
+    }
+}
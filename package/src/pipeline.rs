@@ -0,0 +1,644 @@
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, Read as _},
+    path::{Path, PathBuf},
+    sync::{
+        Condvar, Mutex, MutexGuard,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender},
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CHUNK_SIZE_DEFAULT, chunk::ChunkInfo, hash, naming::ChunkNamer,
+    split::SplitResult,
+};
+
+/// The default number of chunks uploaded at the same time.
+pub const CONCURRENCY_DEFAULT: usize = 4;
+
+/// A chunk read by the pipeline, passed to the uploader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Index of the chunk.
+    pub index: usize,
+    /// Offset of the chunk in the original file in bytes.
+    pub offset: usize,
+    /// Content of the chunk.
+    pub data: Vec<u8>,
+    /// SHA-256 hash of the chunk in hex, if hashing is enabled.
+    pub sha256: Option<String>,
+}
+
+impl Chunk {
+    /// Get the chunk without its content, named with the naming convention.
+    pub fn info(
+        &self,
+        namer: &dyn ChunkNamer,
+    ) -> ChunkInfo {
+        ChunkInfo {
+            hash: self.sha256.clone(),
+            ..ChunkInfo::new(self.index, self.offset, self.data.len(), namer)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PipelineError {
+    InFileNotFound,
+    InFileNotFile,
+    InFileNotSet,
+    InFileNotOpened,
+    InFileNotRead,
+    ChunkNotUploaded,
+    TooManyChunks,
+    DeadlineExceeded,
+}
+
+impl PipelineError {
+    /// Get the code of the error as `&str`.
+    pub fn as_code(&self) -> &str {
+        match self {
+            | Self::InFileNotFound => "in_file_not_found",
+            | Self::InFileNotFile => "in_file_not_file",
+            | Self::InFileNotSet => "in_file_not_set",
+            | Self::InFileNotOpened => "in_file_not_opened",
+            | Self::InFileNotRead => "in_file_not_read",
+            | Self::ChunkNotUploaded => "chunk_not_uploaded",
+            | Self::TooManyChunks => "too_many_chunks",
+            | Self::DeadlineExceeded => "deadline_exceeded",
+        }
+    }
+
+    /// Get the code of the error as `String`.
+    pub fn to_code(&self) -> String {
+        self.as_code().to_string()
+    }
+
+    /// Get the message of the error as `&str`.
+    pub fn as_message(&self) -> &str {
+        match self {
+            | Self::InFileNotFound => "The input file not found.",
+            | Self::InFileNotFile => "The input file is not a file.",
+            | Self::InFileNotSet => "The input file is not set.",
+            | Self::InFileNotOpened => "The input file could not be opened.",
+            | Self::InFileNotRead => "The input file could not be read.",
+            | Self::ChunkNotUploaded => "The chunk could not be uploaded.",
+            | Self::TooManyChunks => "The number of chunks exceeds the limit.",
+            | Self::DeadlineExceeded => {
+                "The upload could not complete before the deadline."
+            },
+        }
+    }
+
+    /// Get the message of the error as `String`.
+    pub fn to_message(&self) -> String {
+        self.as_message().to_string()
+    }
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(self.as_message())
+    }
+}
+
+impl Error for PipelineError {}
+
+/// Process to read, chunk, hash and upload a file with bounded memory.
+///
+/// The file is read one chunk at a time and the chunks are passed to the
+/// uploader on `concurrency` worker threads. A chunk is only read once one
+/// of the `max_buffers` buffers is free, so at most `max_buffers` chunks
+/// are held in memory however slow the uploads are. The process stops at
+/// the first failed upload.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{io, path::PathBuf};
+///
+/// use filego::{
+///     pipeline::{Chunk, Pipeline},
+///     split::SplitResult,
+/// };
+///
+/// fn upload(chunk: Chunk) -> io::Result<()> {
+///     // send `chunk.data` somewhere...
+///     Ok(())
+/// }
+///
+/// let result: SplitResult = Pipeline::new()
+///     .in_file(PathBuf::from("path").join("to").join("file"))
+///     .concurrency(8)
+///     .max_buffers(16)
+///     .hash(true)
+///     .run(upload)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub in_file: Option<PathBuf>,
+    pub chunk_size: usize,
+    pub concurrency: usize,
+    pub max_buffers: usize,
+    pub max_chunks: Option<usize>,
+    pub hash: bool,
+    pub deadline: Option<Instant>,
+}
+
+impl Pipeline {
+    /// Create a new pipeline process.
+    pub fn new() -> Self {
+        Self {
+            in_file: None,
+            chunk_size: CHUNK_SIZE_DEFAULT,
+            concurrency: CONCURRENCY_DEFAULT,
+            max_buffers: CONCURRENCY_DEFAULT * 2,
+            max_chunks: None,
+            hash: false,
+            deadline: None,
+        }
+    }
+
+    /// Set the input file.
+    pub fn in_file<InFile: AsRef<Path>>(
+        mut self,
+        path: InFile,
+    ) -> Self {
+        self.in_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the maximum size of each chunk.
+    ///
+    /// By default, the chunk size follows the [`CHUNK_SIZE_DEFAULT`].
+    pub fn chunk_size(
+        mut self,
+        size: usize,
+    ) -> Self {
+        self.chunk_size = size;
+        self
+    }
+
+    /// Set the number of chunks uploaded at the same time.
+    ///
+    /// By default, it follows the [`CONCURRENCY_DEFAULT`]. It is limited by
+    /// [`max_buffers`](Self::max_buffers).
+    pub fn concurrency(
+        mut self,
+        concurrency: usize,
+    ) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Set the maximum number of chunks held in memory.
+    ///
+    /// It includes the chunks being read, waiting and uploaded, so the
+    /// memory used by the buffers never exceeds `max_buffers * chunk_size`.
+    /// By default, it is twice the [`CONCURRENCY_DEFAULT`].
+    pub fn max_buffers(
+        mut self,
+        buffers: usize,
+    ) -> Self {
+        self.max_buffers = buffers;
+        self
+    }
+
+    /// Set the maximum number of chunks to upload.
+    ///
+    /// By default, the number of chunks is unlimited. When the input file
+    /// would produce more chunks, [`PipelineError::TooManyChunks`] is
+    /// returned before any chunk is uploaded.
+    pub fn max_chunks(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.max_chunks = Some(chunks);
+        self
+    }
+
+    /// Set whether the chunks are hashed with SHA-256 before the upload.
+    ///
+    /// The chunks are hashed on the worker threads, and the hashes are
+    /// passed to the uploader and returned in [`SplitResult::hashes`].
+    pub fn hash(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.hash = enabled;
+        self
+    }
+
+    /// Set the time by which the upload must complete.
+    ///
+    /// By default, there is no deadline. With a deadline, the throughput is
+    /// measured as the chunks are uploaded, and the concurrency is raised
+    /// one worker at a time up to [`max_buffers`](Self::max_buffers) while
+    /// the upload would finish too late. If it would still finish too late
+    /// at the highest concurrency, or the deadline passes, the upload stops
+    /// early with [`PipelineError::DeadlineExceeded`]. It only applies to
+    /// [`run`](Self::run).
+    pub fn deadline(
+        mut self,
+        deadline: Instant,
+    ) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Run the pipeline process with the given uploader.
+    pub fn run<F: Fn(Chunk) -> io::Result<()> + Sync>(
+        &self,
+        upload: F,
+    ) -> Result<SplitResult, PipelineError> {
+        let (mut input, file_size): (fs::File, usize) = self.open()?;
+
+        let chunk_size: usize = self.chunk_size.max(1);
+        let total_chunks: usize = file_size.div_ceil(chunk_size);
+
+        let buffers: Buffers = Buffers::new(self.max_buffers.max(1));
+        let max_workers: usize = buffers.free();
+        let concurrency: usize = self.concurrency.clamp(1, max_workers);
+        let failed: AtomicBool = AtomicBool::new(false);
+
+        // the idle workers wait for a permit, given as the deadline nears
+        let permits: Buffers = Buffers::new(concurrency);
+        let uploaded: AtomicUsize = AtomicUsize::new(0);
+
+        let mut schedule: Option<Schedule> =
+            self.deadline.map(|deadline| Schedule {
+                deadline,
+                since: Instant::now(),
+                since_bytes: 0,
+                workers: concurrency,
+                max_workers,
+            });
+
+        let workers: usize =
+            if schedule.is_some() { max_workers } else { concurrency };
+
+        let (sender, receiver): (Sender<Chunk>, Receiver<Chunk>) =
+            mpsc::channel();
+        let receiver: Mutex<Receiver<Chunk>> = Mutex::new(receiver);
+
+        let mut hashes: Vec<Option<String>> = vec![None; total_chunks];
+        let hashes_lock: Mutex<&mut Vec<Option<String>>> =
+            Mutex::new(&mut hashes);
+
+        let read: Result<(), PipelineError> = thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    loop {
+                        let received = lock(&receiver).recv();
+
+                        let mut chunk: Chunk = match received {
+                            | Ok(chunk) => chunk,
+                            | Err(_) => break,
+                        };
+
+                        permits.acquire();
+
+                        if !failed.load(Ordering::Acquire) {
+                            let len: usize = chunk.data.len();
+
+                            if self.hash {
+                                let sha256: String =
+                                    hash::sha256_hex(&chunk.data);
+
+                                lock(&hashes_lock)[chunk.index] =
+                                    Some(sha256.clone());
+                                chunk.sha256 = Some(sha256);
+                            }
+
+                            match upload(chunk) {
+                                | Ok(()) => {
+                                    uploaded.fetch_add(len, Ordering::AcqRel);
+                                },
+                                | Err(_) => {
+                                    failed.store(true, Ordering::Release);
+                                },
+                            }
+                        }
+
+                        permits.release();
+                        buffers.release();
+                    }
+                });
+            }
+
+            for index in 0..total_chunks {
+                buffers.acquire();
+
+                if failed.load(Ordering::Acquire) {
+                    break;
+                }
+
+                if let Some(ref mut schedule) = schedule {
+                    let uploaded: usize = uploaded.load(Ordering::Acquire);
+
+                    if let Err(e) =
+                        schedule.adjust(uploaded, file_size, &permits)
+                    {
+                        failed.store(true, Ordering::Release);
+                        return Err(e);
+                    }
+                }
+
+                let offset: usize = index * chunk_size;
+                let mut data: Vec<u8> =
+                    vec![0; chunk_size.min(file_size - offset)];
+
+                if input.read_exact(&mut data).is_err() {
+                    failed.store(true, Ordering::Release);
+                    return Err(PipelineError::InFileNotRead);
+                }
+
+                if sender
+                    .send(Chunk { index, offset, data, sha256: None })
+                    .is_err()
+                {
+                    break;
+                }
+            }
+
+            drop(sender);
+
+            Ok(())
+        });
+
+        read?;
+
+        if failed.load(Ordering::Acquire) {
+            return Err(PipelineError::ChunkNotUploaded);
+        }
+
+        Ok(self.to_result(
+            file_size,
+            self.hash.then(|| {
+                hashes.into_iter().map(Option::unwrap_or_default).collect()
+            }),
+        ))
+    }
+
+    /// Spawn the pipeline process, passing the chunks through a channel.
+    ///
+    /// The file is read on a thread, and the chunks are received in order
+    /// from the returned [`ChunkReceiver`], e.g. by an uploader or a writer
+    /// draining it at its own pace. The reading waits while `max_buffers`
+    /// chunks are waiting in the channel, so a slow consumer holds back the
+    /// reading instead of growing the memory. The `concurrency` has no
+    /// effect, as the chunks are hashed on the reading thread.
+    pub fn spawn(&self) -> Result<ChunkReceiver, PipelineError> {
+        let (mut input, file_size): (fs::File, usize) = self.open()?;
+
+        let process: Pipeline = self.clone();
+
+        let (sender, receiver): (SyncSender<Chunk>, Receiver<Chunk>) =
+            mpsc::sync_channel(self.max_buffers.max(1));
+
+        let handle: JoinHandle<Result<SplitResult, PipelineError>> =
+            thread::spawn(move || {
+                let chunk_size: usize = process.chunk_size.max(1);
+                let mut hashes: Vec<String> = Vec::new();
+
+                for index in 0..file_size.div_ceil(chunk_size) {
+                    let offset: usize = index * chunk_size;
+                    let mut data: Vec<u8> =
+                        vec![0; chunk_size.min(file_size - offset)];
+
+                    if input.read_exact(&mut data).is_err() {
+                        return Err(PipelineError::InFileNotRead);
+                    }
+
+                    let sha256: Option<String> =
+                        process.hash.then(|| hash::sha256_hex(&data));
+
+                    if let Some(ref h) = sha256 {
+                        hashes.push(h.clone());
+                    }
+
+                    // the receiver is gone, so the chunk is not consumed
+                    if sender
+                        .send(Chunk { index, offset, data, sha256 })
+                        .is_err()
+                    {
+                        return Err(PipelineError::ChunkNotUploaded);
+                    }
+                }
+
+                Ok(process.to_result(file_size, process.hash.then_some(hashes)))
+            });
+
+        Ok(ChunkReceiver { receiver, handle })
+    }
+
+    /// Open the input file and get its size.
+    fn open(&self) -> Result<(fs::File, usize), PipelineError> {
+        let in_file: &Path = match self.in_file {
+            | Some(ref p) => {
+                let p: &Path = p.as_path();
+
+                // if in_file not exists
+                if !p.exists() {
+                    return Err(PipelineError::InFileNotFound);
+                }
+
+                // if in_file not a file
+                if !p.is_file() {
+                    return Err(PipelineError::InFileNotFile);
+                }
+
+                p
+            },
+            | None => return Err(PipelineError::InFileNotSet),
+        };
+
+        let input: fs::File =
+            match fs::OpenOptions::new().read(true).open(in_file) {
+                | Ok(f) => f,
+                | Err(_) => return Err(PipelineError::InFileNotOpened),
+            };
+
+        let file_size: usize = match input.metadata() {
+            | Ok(m) => m.len() as usize,
+            | Err(_) => return Err(PipelineError::InFileNotRead),
+        };
+
+        let total_chunks: usize = file_size.div_ceil(self.chunk_size.max(1));
+
+        if self.max_chunks.is_some_and(|max| total_chunks > max) {
+            return Err(PipelineError::TooManyChunks);
+        }
+
+        Ok((input, file_size))
+    }
+
+    /// Build the result of the pipeline process.
+    fn to_result(
+        &self,
+        file_size: usize,
+        hashes: Option<Vec<String>>,
+    ) -> SplitResult {
+        let chunk_size: usize = self.chunk_size.max(1);
+        let total_chunks: usize = file_size.div_ceil(chunk_size);
+
+        SplitResult {
+            file_size,
+            total_chunks,
+            hashes,
+            file_hash: None,
+            chunk_paths: Vec::new(),
+            chunk_sizes: (0..total_chunks)
+                .map(|i| chunk_size.min(file_size - i * chunk_size))
+                .collect(),
+        }
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Receiving end of the chunks read by [`Pipeline::spawn`].
+///
+/// The chunks are received in order, also by iterating over it. Dropping it
+/// or calling [`finish`](Self::finish) stops the reading.
+#[derive(Debug)]
+pub struct ChunkReceiver {
+    receiver: Receiver<Chunk>,
+    handle: JoinHandle<Result<SplitResult, PipelineError>>,
+}
+
+impl ChunkReceiver {
+    /// Receive the next chunk, waiting until it is read.
+    ///
+    /// Returns `None` once all the chunks are received or the reading
+    /// failed, see [`finish`](Self::finish).
+    pub fn recv(&self) -> Option<Chunk> {
+        self.receiver.recv().ok()
+    }
+
+    /// Stop receiving and wait for the reading to end.
+    ///
+    /// Returns the result of the pipeline process once all the chunks were
+    /// sent, or [`PipelineError::ChunkNotUploaded`] if it was stopped before.
+    pub fn finish(self) -> Result<SplitResult, PipelineError> {
+        drop(self.receiver);
+
+        self.handle.join().unwrap_or(Err(PipelineError::InFileNotRead))
+    }
+}
+
+impl Iterator for ChunkReceiver {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+/// Concurrency of an upload with a deadline.
+struct Schedule {
+    deadline: Instant,
+    /// Start of the throughput measure, reset when the concurrency changes.
+    since: Instant,
+    since_bytes: usize,
+    workers: usize,
+    max_workers: usize,
+}
+
+impl Schedule {
+    /// Raise the concurrency if the upload would finish after the deadline
+    /// at the measured throughput.
+    fn adjust(
+        &mut self,
+        uploaded: usize,
+        file_size: usize,
+        permits: &Buffers,
+    ) -> Result<(), PipelineError> {
+        let now: Instant = Instant::now();
+
+        if now >= self.deadline {
+            return Err(PipelineError::DeadlineExceeded);
+        }
+
+        let bytes: usize = uploaded.saturating_sub(self.since_bytes);
+
+        // no upload completed since the last change to measure
+        if bytes == 0 {
+            return Ok(());
+        }
+
+        let elapsed: f64 = now.duration_since(self.since).as_secs_f64();
+        let needed: f64 =
+            file_size.saturating_sub(uploaded) as f64 * elapsed / bytes as f64;
+
+        if needed <= self.deadline.duration_since(now).as_secs_f64() {
+            return Ok(());
+        }
+
+        if self.workers >= self.max_workers {
+            return Err(PipelineError::DeadlineExceeded);
+        }
+
+        self.workers += 1;
+        self.since = now;
+        self.since_bytes = uploaded;
+        permits.release();
+
+        Ok(())
+    }
+}
+
+/// Counter of the free chunk buffers.
+struct Buffers {
+    free: Mutex<usize>,
+    condvar: Condvar,
+}
+
+impl Buffers {
+    fn new(count: usize) -> Self {
+        Self { free: Mutex::new(count), condvar: Condvar::new() }
+    }
+
+    fn free(&self) -> usize {
+        *lock(&self.free)
+    }
+
+    /// Take a buffer, waiting until one is free.
+    fn acquire(&self) {
+        let mut free: MutexGuard<'_, usize> = lock(&self.free);
+
+        while *free == 0 {
+            free = self.condvar.wait(free).unwrap_or_else(|e| e.into_inner());
+        }
+
+        *free -= 1;
+    }
+
+    /// Give a buffer back.
+    fn release(&self) {
+        *lock(&self.free) += 1;
+
+        self.condvar.notify_one();
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
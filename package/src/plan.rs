@@ -0,0 +1,186 @@
+use std::ops::Range;
+
+use crate::{chunk::ChunkInfo, naming::ChunkNamer};
+
+/// A chunk planned from a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkPlan {
+    /// Index of the chunk.
+    pub index: usize,
+    /// Offset of the chunk in the original file in bytes.
+    pub offset: usize,
+    /// Size of the chunk in bytes.
+    pub size: usize,
+}
+
+impl ChunkPlan {
+    /// Get the chunk named with the naming convention.
+    pub fn info(
+        &self,
+        namer: &dyn ChunkNamer,
+    ) -> ChunkInfo {
+        ChunkInfo::new(self.index, self.offset, self.size, namer)
+    }
+}
+
+/// Iterator over the chunks planned from a file.
+///
+/// It is created by [`plan_split`].
+#[derive(Debug, Clone)]
+pub struct SplitPlan {
+    file_size: usize,
+    chunk_size: usize,
+    next: usize,
+    total_chunks: usize,
+}
+
+impl SplitPlan {
+    /// Get the total number of chunks of the plan.
+    pub fn total_chunks(&self) -> usize {
+        self.total_chunks
+    }
+}
+
+impl Iterator for SplitPlan {
+    type Item = ChunkPlan;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.total_chunks {
+            return None;
+        }
+
+        let index: usize = self.next;
+        let offset: usize = index * self.chunk_size;
+
+        self.next += 1;
+
+        Some(ChunkPlan {
+            index,
+            offset,
+            size: self.chunk_size.min(self.file_size - offset),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining: usize = self.total_chunks - self.next;
+
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for SplitPlan {}
+
+/// Plan the chunks of a file with the given size.
+///
+/// Returns `None` when `chunk_size` is `0`.
+///
+/// ## Example
+///
+/// ```
+/// use filego::plan::{self, ChunkPlan};
+///
+/// let chunks: Vec<ChunkPlan> = plan::plan_split(10, 4).unwrap().collect();
+///
+/// assert_eq!(chunks.len(), 3);
+/// assert_eq!(chunks[2], ChunkPlan { index: 2, offset: 8, size: 2 });
+/// ```
+pub fn plan_split(
+    file_size: usize,
+    chunk_size: usize,
+) -> Option<SplitPlan> {
+    if chunk_size == 0 {
+        return None;
+    }
+
+    Some(SplitPlan {
+        file_size,
+        chunk_size,
+        next: 0,
+        total_chunks: file_size.div_ceil(chunk_size),
+    })
+}
+
+/// Chunks planned from the file names of a directory.
+///
+/// It is created by [`plan_merge`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergePlan<T> {
+    /// The chunks with their index, sorted by index.
+    pub chunks: Vec<(usize, T)>,
+    /// The ranges of indices missing before the last chunk.
+    pub missing: Vec<Range<usize>>,
+    /// The names which are not chunk names.
+    pub ignored: Vec<T>,
+}
+
+/// Plan the merge order from a list of file names.
+///
+/// The names which are not chunk names are ignored rather than rejected,
+/// so other files can live next to the chunks.
+///
+/// ## Example
+///
+/// ```
+/// use filego::plan::{self, MergePlan};
+///
+/// let plan: MergePlan<&str> = plan::plan_merge(["2", "0", "x", "3"]);
+///
+/// assert_eq!(plan.chunks, vec![(0, "0"), (2, "2"), (3, "3")]);
+/// assert_eq!(plan.missing, vec![1..2]);
+/// assert_eq!(plan.ignored, vec!["x"]);
+/// ```
+pub fn plan_merge<T: AsRef<str>, I: IntoIterator<Item = T>>(
+    names: I
+) -> MergePlan<T> {
+    let mut chunks: Vec<(usize, T)> = Vec::new();
+    let mut ignored: Vec<T> = Vec::new();
+
+    for name in names {
+        match parse_chunk_name(name.as_ref()) {
+            | Some(index) => chunks.push((index, name)),
+            | None => ignored.push(name),
+        }
+    }
+
+    chunks.sort_by_key(|(index, _)| *index);
+
+    let mut missing: Vec<Range<usize>> = Vec::new();
+    let mut expected: usize = 0;
+
+    for (index, _) in chunks.iter() {
+        if *index > expected {
+            missing.push(expected..*index);
+        }
+
+        expected = index.saturating_add(1);
+    }
+
+    MergePlan { chunks, missing, ignored }
+}
+
+/// Parse the index from the name of a chunk.
+///
+/// Only the canonical decimal form is accepted, so that every index maps
+/// to exactly one name (e.g. `01` and `+1` are rejected).
+///
+/// ## Example
+///
+/// ```
+/// use filego::plan;
+///
+/// assert_eq!(plan::parse_chunk_name("12"), Some(12));
+/// assert_eq!(plan::parse_chunk_name("012"), None);
+/// ```
+pub fn parse_chunk_name(name: &str) -> Option<usize> {
+    let bytes: &[u8] = name.as_bytes();
+
+    if bytes.is_empty() || !bytes.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    if bytes.len() > 1 && bytes[0] == b'0' {
+        return None;
+    }
+
+    name.parse::<usize>().ok()
+}
@@ -0,0 +1,223 @@
+use std::io;
+
+/// IO scheduling class of a process.
+///
+/// It maps to the classes used by `ionice` on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoClass {
+    /// Served first regardless of other workloads, requires privileges.
+    RealTime,
+    /// The default class of the system.
+    BestEffort,
+    /// Served only when no other workload needs the disk.
+    Idle,
+}
+
+impl IoClass {
+    #[cfg(target_os = "linux")]
+    fn as_raw(&self) -> i32 {
+        match self {
+            | Self::RealTime => 1,
+            | Self::BestEffort => 2,
+            | Self::Idle => 3,
+        }
+    }
+}
+
+/// Priority of the thread running a process.
+///
+/// The priority is applied to the calling thread for the duration of the
+/// process and restored afterwards on a best effort basis. Restoring a
+/// higher priority than the one applied, such as a lower niceness, needs
+/// the `CAP_SYS_NICE` capability; without it, the failure is ignored and
+/// the calling thread keeps the applied priority. It only takes effect on
+/// Linux, it is ignored on other platforms.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{
+///     priority::{IoClass, Priority},
+///     split::{Split, SplitResult},
+/// };
+///
+/// let result: SplitResult = Split::new()
+///     .in_file(PathBuf::from("path").join("to").join("file"))
+///     .out_dir(PathBuf::from("path").join("to").join("dir"))
+///     .priority(Priority::new().io(IoClass::Idle, 7).nice(10))
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Priority {
+    pub io: Option<(IoClass, u8)>,
+    pub nice: Option<i32>,
+}
+
+impl Priority {
+    /// Create a new priority that keeps the current settings.
+    pub fn new() -> Self {
+        Self { io: None, nice: None }
+    }
+
+    /// Set the IO scheduling class and level.
+    ///
+    /// The level ranges from `0` (highest) to `7` (lowest) and is ignored
+    /// by the [`IoClass::Idle`] class.
+    pub fn io(
+        mut self,
+        class: IoClass,
+        level: u8,
+    ) -> Self {
+        self.io = Some((class, level.min(7)));
+        self
+    }
+
+    /// Set the CPU niceness.
+    ///
+    /// The niceness ranges from `-20` (highest) to `19` (lowest). Note that
+    /// restoring a lower niceness afterwards is not permitted without the
+    /// `CAP_SYS_NICE` capability, so the calling thread then keeps this
+    /// niceness after the process. Run the process on a dedicated thread to
+    /// avoid it.
+    pub fn nice(
+        mut self,
+        nice: i32,
+    ) -> Self {
+        self.nice = Some(nice.clamp(-20, 19));
+        self
+    }
+}
+
+/// Guard restoring the previous priority of the current thread on drop.
+pub(crate) struct PriorityGuard {
+    #[cfg(target_os = "linux")]
+    io: Option<i32>,
+    #[cfg(target_os = "linux")]
+    nice: Option<i32>,
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io;
+
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+
+    const IOPRIO_CLASS_SHIFT: i32 = 13;
+
+    pub(super) fn ioprio(
+        class: i32,
+        level: i32,
+    ) -> i32 {
+        (class << IOPRIO_CLASS_SHIFT) | level
+    }
+
+    pub(super) fn ioprio_get() -> io::Result<i32> {
+        // `who` as `0` targets the calling thread
+        let res: libc::c_long = unsafe {
+            libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0)
+        };
+
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(res as i32)
+    }
+
+    pub(super) fn ioprio_set(ioprio: i32) -> io::Result<()> {
+        let res: libc::c_long = unsafe {
+            libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio)
+        };
+
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn nice_get() -> io::Result<i32> {
+        // `-1` is a valid niceness, errno must be checked instead
+        let res: libc::c_int = unsafe {
+            *libc::__errno_location() = 0;
+            libc::getpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t)
+        };
+
+        let err: io::Error = io::Error::last_os_error();
+
+        if res == -1 && err.raw_os_error() != Some(0) {
+            return Err(err);
+        }
+
+        Ok(res)
+    }
+
+    pub(super) fn nice_set(nice: i32) -> io::Result<()> {
+        let res: libc::c_int = unsafe {
+            libc::setpriority(
+                libc::PRIO_PROCESS,
+                libc::gettid() as libc::id_t,
+                nice,
+            )
+        };
+
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+impl PriorityGuard {
+    /// Apply the priority to the current thread.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn apply(priority: &Priority) -> io::Result<Self> {
+        let mut guard: Self = Self { io: None, nice: None };
+
+        if let Some((class, level)) = priority.io {
+            let previous: i32 = sys::ioprio_get()?;
+
+            sys::ioprio_set(sys::ioprio(class.as_raw(), level as i32))?;
+
+            guard.io = Some(previous);
+        }
+
+        if let Some(nice) = priority.nice {
+            let previous: i32 = sys::nice_get()?;
+
+            sys::nice_set(nice)?;
+
+            guard.nice = Some(previous);
+        }
+
+        Ok(guard)
+    }
+
+    /// Apply the priority to the current thread.
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn apply(_priority: &Priority) -> io::Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        // restoring is best effort, there is no way to report it here; a
+        // lower niceness fails without CAP_SYS_NICE and the thread keeps the
+        // applied one
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(previous) = self.io {
+                let _ = sys::ioprio_set(previous);
+            }
+
+            if let Some(previous) = self.nice {
+                let _ = sys::nice_set(previous);
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{clock::Clock, run_id::RunId};
+
+/// Weight of the latest chunk in the smoothed throughput.
+const SMOOTHING: f64 = 0.3;
+
+/// Progress of a running process, reported after each chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Number of chunks processed.
+    pub chunks_done: usize,
+    /// Total number of chunks.
+    pub total_chunks: usize,
+    /// Number of bytes processed.
+    pub bytes_done: u64,
+    /// Total number of bytes.
+    pub total_bytes: u64,
+    /// Time elapsed since the process started.
+    pub elapsed: Duration,
+    /// Smoothed throughput in bytes per second.
+    pub throughput: f64,
+    /// Estimated time remaining, `None` until the throughput is known.
+    pub eta: Option<Duration>,
+    /// ID of the process reporting the progress.
+    pub run_id: RunId,
+}
+
+impl Progress {
+    /// Get the processed fraction between `0.0` and `1.0`.
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+
+        self.bytes_done as f64 / self.total_bytes as f64
+    }
+}
+
+/// Callback receiving the [`Progress`] of a process.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl ProgressCallback {
+    /// Create a new callback from a function.
+    pub fn new<F: Fn(&Progress) + Send + Sync + 'static>(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub(crate) fn call(
+        &self,
+        progress: &Progress,
+    ) {
+        (self.0)(progress)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Tracker computing the rolling throughput and ETA of a process.
+#[derive(Debug)]
+pub(crate) struct Tracker {
+    progress: Progress,
+    started: Instant,
+    last: Instant,
+    clock: Arc<dyn Clock>,
+}
+
+impl Tracker {
+    pub(crate) fn new(
+        total_chunks: usize,
+        total_bytes: u64,
+        run_id: RunId,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let now: Instant = clock.now();
+
+        Self {
+            progress: Progress {
+                chunks_done: 0,
+                total_chunks,
+                bytes_done: 0,
+                total_bytes,
+                elapsed: Duration::ZERO,
+                throughput: 0.0,
+                eta: None,
+                run_id,
+            },
+            started: now,
+            last: now,
+            clock,
+        }
+    }
+
+    /// Record a processed chunk and get the updated progress.
+    pub(crate) fn advance(
+        &mut self,
+        bytes: u64,
+    ) -> &Progress {
+        let now: Instant = self.clock.now();
+        let secs: f64 = now.duration_since(self.last).as_secs_f64();
+
+        self.last = now;
+
+        let p: &mut Progress = &mut self.progress;
+
+        p.chunks_done += 1;
+        p.bytes_done += bytes;
+        p.elapsed = now.duration_since(self.started);
+
+        if secs > 0.0 {
+            let rate: f64 = bytes as f64 / secs;
+
+            p.throughput = if p.throughput > 0.0 {
+                SMOOTHING * rate + (1.0 - SMOOTHING) * p.throughput
+            } else {
+                rate
+            };
+        }
+
+        let remaining: u64 = p.total_bytes.saturating_sub(p.bytes_done);
+
+        p.eta = if remaining == 0 {
+            Some(Duration::ZERO)
+        } else if p.throughput > 0.0 {
+            Duration::try_from_secs_f64(remaining as f64 / p.throughput).ok()
+        } else {
+            None
+        };
+
+        &self.progress
+    }
+}
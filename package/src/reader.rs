@@ -0,0 +1,152 @@
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// Readable view of chunks as one continuous content.
+///
+/// The chunks are read in the given order without merging them first, so
+/// the content can be inspected before it is written anywhere. The sizes
+/// of the chunks are taken when the reader is opened.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::{io::Read as _, path::PathBuf};
+///
+/// use filego::reader::ChunkedReader;
+///
+/// let dir: PathBuf = PathBuf::from("path").join("to").join("dir");
+///
+/// let mut reader: ChunkedReader =
+///     ChunkedReader::open([dir.join("0"), dir.join("1")]).unwrap();
+///
+/// let mut header: [u8; 16] = [0; 16];
+/// reader.read_exact(&mut header).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ChunkedReader {
+    chunks: Vec<(PathBuf, u64)>,
+    len: u64,
+    pos: u64,
+    current: Option<(usize, fs::File)>,
+}
+
+impl ChunkedReader {
+    /// Open a reader over the chunks in order.
+    pub fn open<P: AsRef<Path>, I: IntoIterator<Item = P>>(
+        chunks: I
+    ) -> io::Result<Self> {
+        let mut entries: Vec<(PathBuf, u64)> = Vec::new();
+        let mut len: u64 = 0;
+
+        for chunk in chunks {
+            let path: PathBuf = chunk.as_ref().to_path_buf();
+            let size: u64 = fs::metadata(&path)?.len();
+
+            len += size;
+            entries.push((path, size));
+        }
+
+        Ok(Self { chunks: entries, len, pos: 0, current: None })
+    }
+
+    /// Get the total size of the content in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check whether the content is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Find the chunk containing the position and the offset in it.
+    fn locate(
+        &self,
+        pos: u64,
+    ) -> Option<(usize, u64)> {
+        let mut start: u64 = 0;
+
+        for (index, (_, size)) in self.chunks.iter().enumerate() {
+            if pos < start + size {
+                return Some((index, pos - start));
+            }
+
+            start += size;
+        }
+
+        None
+    }
+}
+
+impl Read for ChunkedReader {
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let (index, offset) = match self.locate(self.pos) {
+            | Some(found) => found,
+            | None => return Ok(0),
+        };
+
+        let file: &mut fs::File = match self.current {
+            | Some((i, ref mut file)) if i == index => file,
+            | _ => {
+                let mut file: fs::File = fs::File::open(&self.chunks[index].0)?;
+                file.seek(SeekFrom::Start(offset))?;
+
+                &mut self.current.insert((index, file)).1
+            },
+        };
+
+        let remaining: u64 = self.chunks[index].1 - offset;
+        let max: usize =
+            buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+
+        let read: usize = file.read(&mut buf[..max])?;
+
+        // the chunk is shorter than when the reader was opened
+        if read == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for ChunkedReader {
+    fn seek(
+        &mut self,
+        pos: SeekFrom,
+    ) -> io::Result<u64> {
+        let target: Option<u64> = match pos {
+            | SeekFrom::Start(p) => Some(p),
+            | SeekFrom::End(p) => self.len.checked_add_signed(p),
+            | SeekFrom::Current(p) => self.pos.checked_add_signed(p),
+        };
+
+        let target: u64 = match target {
+            | Some(t) => t,
+            | None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid seek to a negative position",
+                ));
+            },
+        };
+
+        // the open chunk is sought again on the next read
+        self.current = None;
+        self.pos = target;
+
+        Ok(target)
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    naming::{ChunkNamer, Plain},
+    reader::ChunkedReader,
+    split::{Split, SplitError, SplitResult},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RechunkError {
+    InDirNotFound,
+    InDirNotDir,
+    InDirNotSet,
+    InDirNotRead,
+    InDirNoFile,
+    InFileNotRead,
+    OutDirIsInDir,
+    /// The error of writing the new chunks.
+    Split(SplitError),
+}
+
+impl RechunkError {
+    /// Get the code of the error as `&str`.
+    pub fn as_code(&self) -> &str {
+        match self {
+            | Self::InDirNotFound => "in_dir_not_found",
+            | Self::InDirNotDir => "in_dir_not_dir",
+            | Self::InDirNotSet => "in_dir_not_set",
+            | Self::InDirNotRead => "in_dir_not_read",
+            | Self::InDirNoFile => "in_dir_no_file",
+            | Self::InFileNotRead => "in_file_not_read",
+            | Self::OutDirIsInDir => "out_dir_is_in_dir",
+            | Self::Split(e) => e.as_code(),
+        }
+    }
+
+    /// Get the code of the error as `String`.
+    pub fn to_code(&self) -> String {
+        self.as_code().to_string()
+    }
+
+    /// Get the message of the error as `&str`.
+    pub fn as_message(&self) -> &str {
+        match self {
+            | Self::InDirNotFound => "The input directory not found.",
+            | Self::InDirNotDir => "The input directory is not a directory.",
+            | Self::InDirNotSet => "The input directory is not set.",
+            | Self::InDirNotRead => "The input directory could not be read.",
+            | Self::InDirNoFile => "The input directory has no file.",
+            | Self::InFileNotRead => "The input file could not be read.",
+            | Self::OutDirIsInDir => {
+                "The output directory is the input directory."
+            },
+            | Self::Split(e) => e.as_message(),
+        }
+    }
+
+    /// Get the message of the error as `String`.
+    pub fn to_message(&self) -> String {
+        self.as_message().to_string()
+    }
+}
+
+impl fmt::Display for RechunkError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(self.as_message())
+    }
+}
+
+impl Error for RechunkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            | Self::Split(e) => Some(e),
+            | _ => None,
+        }
+    }
+}
+
+impl From<SplitError> for RechunkError {
+    fn from(error: SplitError) -> Self {
+        Self::Split(error)
+    }
+}
+
+/// Process to convert chunks to another chunk size.
+///
+/// The chunks are read in order as one continuous content and split again
+/// on the fly, so the original file is never merged to a temporary file.
+/// The new chunks are written by a [`Split`] process, which can be
+/// configured with [`split`](Self::split), e.g. to hash them or to write a
+/// manifest. The output directory must differ from the input directory.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::{rechunk::Rechunk, split::SplitResult};
+///
+/// let result: SplitResult = Rechunk::new()
+///     .in_dir(PathBuf::from("path").join("to").join("dir"))
+///     .out_dir(PathBuf::from("path").join("to").join("new"))
+///     .chunk_size(8 * 1024 * 1024)
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rechunk {
+    pub in_dir: Option<PathBuf>,
+    pub namer: Arc<dyn ChunkNamer>,
+    pub split: Split,
+}
+
+impl Rechunk {
+    /// Create a new rechunk process.
+    pub fn new() -> Self {
+        Self { in_dir: None, namer: Arc::new(Plain), split: Split::new() }
+    }
+
+    /// Set the input directory.
+    pub fn in_dir<InDir: AsRef<Path>>(
+        mut self,
+        path: InDir,
+    ) -> Self {
+        self.in_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the naming convention of the input chunks.
+    ///
+    /// By default, the chunks are named by their index with [`Plain`]. The
+    /// naming of the new chunks is set on the [`split`](Self::split)
+    /// process.
+    pub fn namer<N: ChunkNamer + 'static>(
+        mut self,
+        namer: N,
+    ) -> Self {
+        self.namer = Arc::new(namer);
+        self
+    }
+
+    /// Set the output directory.
+    pub fn out_dir<OutDir: AsRef<Path>>(
+        mut self,
+        path: OutDir,
+    ) -> Self {
+        self.split = self.split.out_dir(path);
+        self
+    }
+
+    /// Set the maximum size of each new chunk.
+    pub fn chunk_size(
+        mut self,
+        size: usize,
+    ) -> Self {
+        self.split = self.split.chunk_size(size);
+        self
+    }
+
+    /// Set the split process writing the new chunks.
+    ///
+    /// Its `in_file` is ignored, as the input is the existing chunks.
+    pub fn split(
+        mut self,
+        split: Split,
+    ) -> Self {
+        self.split = split;
+        self
+    }
+
+    /// Run the rechunk process.
+    pub fn run(&self) -> Result<SplitResult, RechunkError> {
+        let in_dir: &Path = match self.in_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_ref();
+
+                // if in_dir not exists
+                if !p.exists() {
+                    return Err(RechunkError::InDirNotFound);
+                }
+
+                // if in_dir not a directory
+                if !p.is_dir() {
+                    return Err(RechunkError::InDirNotDir);
+                }
+
+                p
+            },
+            | None => return Err(RechunkError::InDirNotSet),
+        };
+
+        // the chunks would be overwritten while they are read
+        if let Some(ref out_dir) = self.split.out_dir {
+            if let (Ok(a), Ok(b)) =
+                (in_dir.canonicalize(), out_dir.canonicalize())
+            {
+                if a == b {
+                    return Err(RechunkError::OutDirIsInDir);
+                }
+            }
+        }
+
+        // get inputs
+        let mut entries: Vec<(usize, PathBuf)> = Vec::new();
+
+        let read_dir: fs::ReadDir = match fs::read_dir(in_dir) {
+            | Ok(read_dir) => read_dir,
+            | Err(_) => return Err(RechunkError::InDirNotRead),
+        };
+
+        for entry in read_dir.filter_map(Result::ok) {
+            let path: PathBuf = entry.path();
+
+            if !path.is_file() {
+                continue;
+            }
+
+            let index: Option<usize> = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| self.namer.parse(n));
+
+            if let Some(index) = index {
+                entries.push((index, path));
+            }
+        }
+
+        if entries.is_empty() {
+            return Err(RechunkError::InDirNoFile);
+        }
+
+        entries.sort_by_key(|(index, _)| *index);
+
+        let reader: ChunkedReader =
+            match ChunkedReader::open(entries.iter().map(|(_, p)| p)) {
+                | Ok(reader) => reader,
+                | Err(_) => return Err(RechunkError::InFileNotRead),
+            };
+
+        Ok(self.split.run_reader(reader)?)
+    }
+}
+
+impl Default for Rechunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
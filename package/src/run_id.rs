@@ -0,0 +1,100 @@
+use std::{
+    fmt,
+    hash::{BuildHasher as _, RandomState},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Counter making the IDs generated in the same instant unique.
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Unique ID of a process, to correlate its events and outputs.
+///
+/// A new process is assigned a random ID, or one can be set, e.g. the ID
+/// of the request being served. The ID is passed in every [`Progress`]
+/// report and recorded in the manifest, so the logs of concurrent
+/// transfers on a busy server can be told apart. It is displayed and
+/// parsed as 32 lowercase hex digits.
+///
+/// [`Progress`]: crate::progress::Progress
+///
+/// ## Example
+///
+/// ```
+/// use filego::run_id::RunId;
+///
+/// let id: RunId = "0123456789abcdef0123456789abcdef".parse().unwrap();
+///
+/// assert_eq!(id.to_string(), "0123456789abcdef0123456789abcdef");
+/// assert_ne!(RunId::generate(), RunId::generate());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RunId(u128);
+
+impl RunId {
+    /// Create an ID from its value.
+    pub fn new(value: u128) -> Self {
+        Self(value)
+    }
+
+    /// Generate a new random ID.
+    pub fn generate() -> Self {
+        let nanos: u128 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let count: u64 = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let high: u64 =
+            RandomState::new().hash_one((nanos, count, std::process::id()));
+        let low: u64 = RandomState::new().hash_one((count, nanos));
+
+        Self((high as u128) << 64 | low as u128)
+    }
+
+    /// Get the value of the ID.
+    pub fn value(&self) -> u128 {
+        self.0
+    }
+}
+
+impl fmt::Display for RunId {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+impl FromStr for RunId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u128::from_str_radix(s, 16).map(Self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for RunId {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for RunId {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D
+    ) -> Result<Self, D::Error> {
+        let s: String = String::deserialize(deserializer)?;
+
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+};
+
+use crate::{Error, control::Control};
+
+/// Run a group of processes, cancelling all of them when one fails.
+///
+/// The processes are spawned on threads with [`Scope::spawn`], each with a
+/// [`Control`] to attach to it. When a process fails or panics, or the
+/// scope is cancelled, the other processes are cancelled at their next
+/// buffer read. The scope returns once all of them have stopped, and the
+/// outputs registered with [`Scope::cleanup`] are removed if any failed,
+/// so a batch never leaves half-written chunk sets behind. Returns the
+/// first error of the processes, the errors of the processes cancelled
+/// because of it are dropped.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::split::Split;
+///
+/// let files: Vec<PathBuf> =
+///     vec![PathBuf::from("file1"), PathBuf::from("file2")];
+///
+/// filego::scope(|s| {
+///     for file in files.iter() {
+///         let out_dir: PathBuf = file.with_extension("chunks");
+///
+///         s.cleanup(&out_dir);
+///         s.spawn(move |control| {
+///             Split::new()
+///                 .in_file(file)
+///                 .out_dir(out_dir)
+///                 .control(control)
+///                 .run()?;
+///
+///             Ok(())
+///         });
+///     }
+/// })
+/// .unwrap();
+/// ```
+pub fn scope<'env, F, T>(f: F) -> Result<T, Error>
+where
+    F: for<'scope> FnOnce(&Scope<'scope, 'env>) -> T,
+{
+    let state: Arc<State> = Arc::new(State {
+        inner: Mutex::new(Inner {
+            controls: Vec::new(),
+            cleanup: Vec::new(),
+            cancelled: false,
+            error: None,
+        }),
+    });
+
+    let value: T =
+        thread::scope(|s| f(&Scope { scope: s, state: Arc::clone(&state) }));
+
+    // all the processes have stopped
+    let mut inner: MutexGuard<'_, Inner> = state.lock();
+
+    match inner.error.take() {
+        | Some(e) => {
+            for path in inner.cleanup.iter() {
+                remove(path);
+            }
+
+            Err(e)
+        },
+        | None => Ok(value),
+    }
+}
+
+/// Group of processes run by [`scope`].
+#[derive(Debug)]
+pub struct Scope<'scope, 'env: 'scope> {
+    scope: &'scope thread::Scope<'scope, 'env>,
+    state: Arc<State>,
+}
+
+impl<'scope> Scope<'scope, '_> {
+    /// Spawn a process on a new thread.
+    ///
+    /// The process must be attached to the given control, so it stops when
+    /// the scope is cancelled. If the scope is already cancelled, the
+    /// control is cancelled before the process starts.
+    pub fn spawn<F>(
+        &self,
+        process: F,
+    ) where
+        F: FnOnce(&Control) -> Result<(), Error> + Send + 'scope,
+    {
+        let control: Control = Control::new();
+
+        {
+            let mut inner: MutexGuard<'_, Inner> = self.state.lock();
+
+            if inner.cancelled {
+                control.cancel();
+            }
+
+            inner.controls.push(control.clone());
+        }
+
+        let state: Arc<State> = Arc::clone(&self.state);
+
+        self.scope.spawn(move || {
+            let _guard: PanicGuard<'_> = PanicGuard { state: &state };
+
+            if let Err(e) = process(&control) {
+                state.fail(e);
+            }
+        });
+    }
+
+    /// Register a file or directory removed when the scope fails.
+    ///
+    /// It is removed after all the processes have stopped, e.g. the output
+    /// directory of a split or the output file of a merge.
+    pub fn cleanup<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) {
+        self.state.lock().cleanup.push(path.as_ref().to_path_buf());
+    }
+
+    /// Cancel all the processes of the scope.
+    ///
+    /// The processes spawned afterwards are cancelled as well.
+    pub fn cancel(&self) {
+        self.state.cancel();
+    }
+
+    /// Check whether the scope is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().cancelled
+    }
+}
+
+/// State shared by the processes of a scope.
+#[derive(Debug)]
+struct State {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    controls: Vec<Control>,
+    cleanup: Vec<PathBuf>,
+    cancelled: bool,
+    error: Option<Error>,
+}
+
+impl State {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Cancel all the processes.
+    fn cancel(&self) {
+        let mut inner: MutexGuard<'_, Inner> = self.lock();
+
+        inner.cancelled = true;
+
+        for control in inner.controls.iter() {
+            control.cancel();
+        }
+    }
+
+    /// Keep the first error and cancel the other processes.
+    fn fail(
+        &self,
+        error: Error,
+    ) {
+        self.lock().error.get_or_insert(error);
+        self.cancel();
+    }
+}
+
+/// Guard cancelling the other processes when a process panics.
+struct PanicGuard<'a> {
+    state: &'a State,
+}
+
+impl Drop for PanicGuard<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.state.cancel();
+        }
+    }
+}
+
+/// Remove a file or directory, ignoring a missing one.
+fn remove(path: &Path) {
+    let _ = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+}
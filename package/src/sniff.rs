@@ -0,0 +1,98 @@
+/// The number of leading bytes needed to sniff a content type.
+pub const SNIFF_LEN: usize = 512;
+
+/// The content type of content which is not recognized.
+pub const UNKNOWN_TYPE: &str = "application/octet-stream";
+
+/// Bytes expected at an offset of the content.
+type Part = (usize, &'static [u8]);
+
+/// Signatures of the recognized content types.
+const SIGNATURES: &[(&str, &[Part])] = &[
+    ("image/jpeg", &[(0, b"\xff\xd8\xff")]),
+    ("image/png", &[(0, b"\x89PNG\r\n\x1a\n")]),
+    ("image/gif", &[(0, b"GIF87a")]),
+    ("image/gif", &[(0, b"GIF89a")]),
+    ("image/webp", &[(0, b"RIFF"), (8, b"WEBP")]),
+    ("image/bmp", &[(0, b"BM")]),
+    ("audio/wav", &[(0, b"RIFF"), (8, b"WAVE")]),
+    ("audio/mpeg", &[(0, b"ID3")]),
+    ("audio/ogg", &[(0, b"OggS")]),
+    ("audio/flac", &[(0, b"fLaC")]),
+    ("video/mp4", &[(4, b"ftyp")]),
+    ("video/webm", &[(0, b"\x1a\x45\xdf\xa3")]),
+    ("application/pdf", &[(0, b"%PDF-")]),
+    ("application/zip", &[(0, b"PK\x03\x04")]),
+    ("application/zip", &[(0, b"PK\x05\x06")]),
+    ("application/gzip", &[(0, b"\x1f\x8b")]),
+    ("application/x-7z-compressed", &[(0, b"7z\xbc\xaf\x27\x1c")]),
+    ("application/x-tar", &[(257, b"ustar")]),
+    ("application/x-executable", &[(0, b"\x7fELF")]),
+    ("application/x-msdownload", &[(0, b"MZ")]),
+];
+
+/// Sniff the content type from the leading bytes of a content.
+///
+/// The type is recognized from the signatures of common formats, and text
+/// without control characters is `text/plain`. Other content is
+/// [`UNKNOWN_TYPE`]. At most [`SNIFF_LEN`] bytes are inspected.
+///
+/// ## Example
+///
+/// ```
+/// use filego::sniff;
+///
+/// assert_eq!(sniff::sniff(b"%PDF-1.7"), "application/pdf");
+/// assert_eq!(sniff::sniff(b"hello"), "text/plain");
+/// ```
+pub fn sniff(head: &[u8]) -> &'static str {
+    let head: &[u8] = &head[..head.len().min(SNIFF_LEN)];
+
+    for (content_type, parts) in SIGNATURES {
+        if parts.iter().all(|(offset, bytes)| {
+            head.get(*offset..offset + bytes.len()) == Some(*bytes)
+        }) {
+            return content_type;
+        }
+    }
+
+    if is_text(head) {
+        return "text/plain";
+    }
+
+    UNKNOWN_TYPE
+}
+
+/// Check whether a content type matches an allowed type.
+///
+/// The allowed type may end with `/*` to match a whole category, e.g.
+/// `image/*`, or be `*/*` to match anything.
+pub fn matches(
+    content_type: &str,
+    allowed: &str,
+) -> bool {
+    match allowed.strip_suffix("/*") {
+        | Some("*") => true,
+        | Some(category) => content_type
+            .split_once('/')
+            .is_some_and(|(c, _)| c.eq_ignore_ascii_case(category)),
+        | None => content_type.eq_ignore_ascii_case(allowed),
+    }
+}
+
+fn is_text(head: &[u8]) -> bool {
+    if head.is_empty() {
+        return false;
+    }
+
+    // a multi-byte character may be cut at the end
+    let valid: &[u8] = match std::str::from_utf8(head) {
+        | Ok(_) => head,
+        | Err(e) if e.error_len().is_none() => &head[..e.valid_up_to()],
+        | Err(_) => return false,
+    };
+
+    valid
+        .iter()
+        .all(|b| !b.is_ascii_control() || matches!(b, b'\t' | b'\n' | b'\r'))
+}
@@ -1,10 +1,46 @@
 use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt,
     fs::{self, File},
-    io::{self as io, BufWriter, Read as _, Write as _},
+    io::{self as io, BufWriter, Read, Seek as _, SeekFrom, Write as _},
     path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::SystemTime,
 };
 
-use crate::{BUFFER_CAPACITY_MAX_DEFAULT, CHUNK_SIZE_DEFAULT};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BUFFER_CAPACITY_MAX_DEFAULT, CHUNK_SIZE_DEFAULT, advise,
+    check::COMPARE_BUFFER_SIZE,
+    clock::{Clock, SystemClock},
+    control::{Control, ControlGuard},
+    device,
+    durability::Durability,
+    error::IoError,
+    follow::Follow,
+    hash::{self, HashAlgorithm, HashPipeline, Hasher},
+    memory,
+    naming::{self, ChunkNamer, Plain},
+    plan,
+    priority::{Priority, PriorityGuard},
+    run_id::RunId,
+    throttle::Throttle,
+};
+
+#[cfg(target_os = "linux")]
+use crate::copy;
+#[cfg(target_os = "linux")]
+use crate::direct::{self, AlignedBuffer};
+#[cfg(feature = "manifest")]
+use crate::manifest::{
+    MANIFEST_FILE_NAME, MANIFEST_VERSION, Manifest, ManifestChunk, RUN_ID_KEY,
+};
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+use crate::mmap::Mmap;
 
 /// Run asynchronously with `async_std` feature.
 ///
@@ -32,27 +68,80 @@ pub mod tokio {
     pub use crate::tokio::split::SplitAsyncExt;
 }
 
+/// What the split process does when the output directory is not empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum IfNotEmpty {
+    /// The entries are kept and the chunks are written among them.
+    #[default]
+    Reuse,
+    /// The entries are removed before any chunk is written.
+    Clean,
+    /// The split fails with [`SplitError::OutDirNotEmpty`].
+    Error,
+}
+
+/// How the chunks of a previous run are validated before resuming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ResumeCheck {
+    /// A chunk is kept when it has the expected size.
+    Size,
+    /// A chunk is kept when it has the expected size and the same SHA-256
+    /// hash as its section of the input file, which is read to compare it.
+    Hash,
+}
+
 /// Result of the split process.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SplitResult {
     /// Size of the original file in bytes.
     pub file_size: usize,
     /// The total number of chunks splitted from the original file.
     pub total_chunks: usize,
+    /// Hashes of the chunks in hex, in order, if hashing is enabled.
+    pub hashes: Option<Vec<String>>,
+    /// Hash of the original file in hex, if the hash algorithm is set.
+    pub file_hash: Option<String>,
+    /// Paths of the chunks, in order.
+    ///
+    /// It is empty when no chunk is written, e.g. by a
+    /// [`Pipeline`](crate::pipeline::Pipeline).
+    pub chunk_paths: Vec<PathBuf>,
+    /// Sizes of the chunks in bytes, in order.
+    pub chunk_sizes: Vec<usize>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum SplitError {
     InFileNotFound,
     InFileNotFile,
     InFileNotSet,
-    InFileNotOpened,
-    InFileNotRead,
-    OutDirNotCreated,
+    InFileNotOpened { path: PathBuf, source: IoError },
+    InFileNotRead { path: PathBuf, source: IoError },
+    OutDirNotCreated { path: PathBuf, source: IoError },
     OutDirNotDir,
     OutDirNotSet,
-    OutFileNotOpened,
-    OutFileNotWritten,
+    OutDirNotEmpty,
+    OutDirNotCleaned { path: PathBuf, source: IoError },
+    OutFileNotOpened { path: PathBuf, source: IoError },
+    OutFileNotWritten { path: PathBuf, source: IoError },
+    OutFileNotSynced { path: PathBuf, source: IoError },
+    PriorityNotSet { source: IoError },
+    TooManyChunks,
+    Cancelled,
+    ChunkNotHashed,
+    ManifestNotWritten { path: PathBuf, source: IoError },
+    InFileChanged,
+    ChunkNameInvalid,
+    ChunkNameCollision,
+    ChunkNameNotUnique,
+    InsufficientSpace,
 }
 
 impl SplitError {
@@ -62,13 +151,26 @@ impl SplitError {
             | Self::InFileNotFound => "in_file_not_found",
             | Self::InFileNotFile => "in_file_not_file",
             | Self::InFileNotSet => "in_file_not_set",
-            | Self::InFileNotOpened => "in_file_not_opened",
-            | Self::InFileNotRead => "in_file_not_read",
-            | Self::OutDirNotCreated => "out_dir_not_created",
+            | Self::InFileNotOpened { .. } => "in_file_not_opened",
+            | Self::InFileNotRead { .. } => "in_file_not_read",
+            | Self::OutDirNotCreated { .. } => "out_dir_not_created",
             | Self::OutDirNotDir => "out_dir_not_dir",
             | Self::OutDirNotSet => "out_dir_not_set",
-            | Self::OutFileNotOpened => "out_file_not_opened",
-            | Self::OutFileNotWritten => "out_file_not_written",
+            | Self::OutDirNotEmpty => "out_dir_not_empty",
+            | Self::OutDirNotCleaned { .. } => "out_dir_not_cleaned",
+            | Self::OutFileNotOpened { .. } => "out_file_not_opened",
+            | Self::OutFileNotWritten { .. } => "out_file_not_written",
+            | Self::OutFileNotSynced { .. } => "out_file_not_synced",
+            | Self::PriorityNotSet { .. } => "priority_not_set",
+            | Self::TooManyChunks => "too_many_chunks",
+            | Self::Cancelled => "cancelled",
+            | Self::ChunkNotHashed => "chunk_not_hashed",
+            | Self::ManifestNotWritten { .. } => "manifest_not_written",
+            | Self::InFileChanged => "in_file_changed",
+            | Self::ChunkNameInvalid => "chunk_name_invalid",
+            | Self::ChunkNameCollision => "chunk_name_collision",
+            | Self::ChunkNameNotUnique => "chunk_name_not_unique",
+            | Self::InsufficientSpace => "insufficient_space",
         }
     }
 
@@ -83,19 +185,50 @@ impl SplitError {
             | Self::InFileNotFound => "The input file not found.",
             | Self::InFileNotFile => "The input file is not a file.",
             | Self::InFileNotSet => "The input file is not set.",
-            | Self::InFileNotOpened => "The input file could not be opened.",
-            | Self::InFileNotRead => "The input file could not be read.",
-            | Self::OutDirNotCreated => {
+            | Self::InFileNotOpened { .. } => {
+                "The input file could not be opened."
+            },
+            | Self::InFileNotRead { .. } => "The input file could not be read.",
+            | Self::OutDirNotCreated { .. } => {
                 "The output directory could not be created."
             },
             | Self::OutDirNotDir => "The output directory is not a directory.",
             | Self::OutDirNotSet => "The output directory is not set.",
-            | Self::OutFileNotOpened => {
+            | Self::OutDirNotEmpty => "The output directory is not empty.",
+            | Self::OutDirNotCleaned { .. } => {
+                "The output directory could not be cleaned."
+            },
+            | Self::OutFileNotOpened { .. } => {
                 "The output file could not be created or opened."
             },
-            | Self::OutFileNotWritten => {
+            | Self::OutFileNotWritten { .. } => {
                 "The output file could not be written."
             },
+            | Self::OutFileNotSynced { .. } => {
+                "The output file could not be synced to the disk."
+            },
+            | Self::PriorityNotSet { .. } => "The priority could not be set.",
+            | Self::TooManyChunks => "The number of chunks exceeds the limit.",
+            | Self::Cancelled => "The process was cancelled.",
+            | Self::ChunkNotHashed => "The chunk could not be hashed.",
+            | Self::ManifestNotWritten { .. } => {
+                "The manifest could not be written."
+            },
+            | Self::InFileChanged => {
+                "The input file was changed during the split."
+            },
+            | Self::ChunkNameInvalid => {
+                "The chunk name is not a valid file name on Windows."
+            },
+            | Self::ChunkNameCollision => {
+                "The chunk names collide when the case is ignored."
+            },
+            | Self::ChunkNameNotUnique => {
+                "The namer gives several chunks the same name."
+            },
+            | Self::InsufficientSpace => {
+                "The output directory has not enough free space."
+            },
         }
     }
 
@@ -103,6 +236,57 @@ impl SplitError {
     pub fn to_message(&self) -> String {
         self.as_message().to_string()
     }
+
+    /// Get the path involved in the error, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.io().map(|(path, _)| path)
+    }
+
+    /// Get the path and the I/O error of the error, if any.
+    fn io(&self) -> Option<(&Path, &IoError)> {
+        match self {
+            | Self::InFileNotOpened { path, source }
+            | Self::InFileNotRead { path, source }
+            | Self::OutDirNotCreated { path, source }
+            | Self::OutDirNotCleaned { path, source }
+            | Self::OutFileNotOpened { path, source }
+            | Self::OutFileNotWritten { path, source }
+            | Self::OutFileNotSynced { path, source }
+            | Self::ManifestNotWritten { path, source } => Some((path, source)),
+            | _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self.io() {
+            | Some((path, source)) => write!(
+                f,
+                "{} ({}: {})",
+                self.as_message(),
+                path.display(),
+                source
+            ),
+            | None => f.write_str(self.as_message()),
+        }
+    }
+}
+
+impl Error for SplitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            | Self::PriorityNotSet { source } => {
+                Some(source.as_ref() as &(dyn Error + 'static))
+            },
+            | _ => self
+                .io()
+                .map(|(_, source)| source.as_ref() as &(dyn Error + 'static)),
+        }
+    }
 }
 
 /// Process to split file from a path to a directory.
@@ -126,6 +310,34 @@ pub struct Split {
     pub out_dir: Option<PathBuf>,
     pub chunk_size: usize,
     pub cap_max: usize,
+    pub namer: Arc<dyn ChunkNamer>,
+    pub portable_names: bool,
+    pub case_insensitive: bool,
+    pub empty_chunk: bool,
+    pub passthrough: bool,
+    pub mmap: bool,
+    pub max_chunks: Option<usize>,
+    pub hash: bool,
+    pub hash_workers: Option<usize>,
+    pub hash_algorithm: Option<HashAlgorithm>,
+    pub follow: Option<Follow>,
+    #[cfg(feature = "manifest")]
+    pub manifest: bool,
+    pub metadata: BTreeMap<String, String>,
+    pub dedup: bool,
+    pub guard_input: bool,
+    pub cache_hints: bool,
+    pub direct_io: bool,
+    pub durability: Durability,
+    pub check_space: bool,
+    pub if_not_empty: IfNotEmpty,
+    pub resume: Option<ResumeCheck>,
+    pub priority: Option<Priority>,
+    pub throttle: Option<u64>,
+    pub clock: Arc<dyn Clock>,
+    pub concurrency: usize,
+    pub control: Option<Control>,
+    pub run_id: RunId,
 }
 
 impl Split {
@@ -136,6 +348,34 @@ impl Split {
             out_dir: None,
             chunk_size: CHUNK_SIZE_DEFAULT,
             cap_max: BUFFER_CAPACITY_MAX_DEFAULT,
+            namer: Arc::new(Plain),
+            portable_names: cfg!(windows),
+            case_insensitive: cfg!(any(windows, target_os = "macos")),
+            empty_chunk: false,
+            passthrough: false,
+            mmap: false,
+            max_chunks: None,
+            hash: false,
+            hash_workers: None,
+            hash_algorithm: None,
+            follow: None,
+            #[cfg(feature = "manifest")]
+            manifest: false,
+            metadata: BTreeMap::new(),
+            dedup: false,
+            guard_input: false,
+            cache_hints: false,
+            direct_io: false,
+            durability: Durability::Flush,
+            check_space: false,
+            if_not_empty: IfNotEmpty::Reuse,
+            resume: None,
+            priority: None,
+            throttle: None,
+            clock: Arc::new(SystemClock),
+            concurrency: 1,
+            control: None,
+            run_id: RunId::generate(),
         }
     }
 
@@ -187,6 +427,420 @@ impl Split {
         self
     }
 
+    /// Set the maximum size of the buffer capacity from the available memory.
+    ///
+    /// It is meant for memory-constrained environments, e.g. containers,
+    /// where `concurrency` processes run at the same time. See
+    /// [`memory::buffer_capacity`] for how it is derived.
+    pub fn max_buffer_capacity_auto(
+        mut self,
+        concurrency: usize,
+    ) -> Self {
+        self.cap_max = memory::buffer_capacity(concurrency);
+        self
+    }
+
+    /// Set the naming convention of the chunks.
+    ///
+    /// By default, the chunks are named by their index with [`Plain`].
+    pub fn namer<N: ChunkNamer + 'static>(
+        mut self,
+        namer: N,
+    ) -> Self {
+        self.namer = Arc::new(namer);
+        self
+    }
+
+    /// Set whether the chunk names must be valid file names on Windows.
+    ///
+    /// Enabled by default on Windows. When enabled, a name refused by
+    /// [`naming::is_portable`], e.g. `nul` or `part:0`, returns
+    /// [`SplitError::ChunkNameInvalid`] before the chunk is written, and
+    /// before any chunk is written when the size of the input is known.
+    pub fn portable_names(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.portable_names = enabled;
+        self
+    }
+
+    /// Set whether the chunk names must differ when the case is ignored.
+    ///
+    /// Enabled by default on macOS and Windows, whose file systems are
+    /// case-insensitive by default, so a custom namer returning `a` and `A`
+    /// would overwrite a chunk with the next one. When enabled, such names
+    /// return [`SplitError::ChunkNameCollision`] before any chunk is written.
+    /// The names are not checked when the size of the input is not known
+    /// upfront, e.g. when it is followed or read from a reader.
+    pub fn case_insensitive(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    /// Set whether an empty input file produces a single empty chunk.
+    ///
+    /// By default, an empty input file produces no chunk and `total_chunks`
+    /// is `0`, which the merge process rejects as there is nothing to merge.
+    /// When enabled, an empty chunk `0` is written instead, so the chunks
+    /// can be checked and merged back into an empty file.
+    pub fn empty_chunk(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.empty_chunk = enabled;
+        self
+    }
+
+    /// Set whether a file fitting in a single chunk is linked instead of
+    /// copied.
+    ///
+    /// When the input file is not larger than `chunk_size` and on the same
+    /// device as the output directory, it is hard linked as chunk `0`. It
+    /// falls back to copying when the devices differ or linking fails. Note
+    /// that the chunk then shares its content with the input file, so
+    /// modifying one modifies the other. It has no effect when
+    /// [`hash`](Self::hash) is enabled or the
+    /// [`hash_algorithm`](Self::hash_algorithm) is set, as the input is read
+    /// anyway.
+    pub fn passthrough(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.passthrough = enabled;
+        self
+    }
+
+    /// Set whether the input file is mapped into memory.
+    ///
+    /// By default, the input file is read into a buffer. When enabled with
+    /// the `mmap` feature on Linux, the chunks are written straight from the
+    /// mapped input file, which is faster on local SSDs. The input file must
+    /// not be truncated meanwhile. It falls back to reading when the file
+    /// cannot be mapped, and has no effect when the input is followed.
+    pub fn mmap(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.mmap = enabled;
+        self
+    }
+
+    /// Set the maximum number of chunks to create.
+    ///
+    /// By default, the number of chunks is unlimited. When the input file
+    /// would produce more chunks, [`SplitError::TooManyChunks`] is returned
+    /// before any chunk is written.
+    pub fn max_chunks(
+        mut self,
+        chunks: usize,
+    ) -> Self {
+        self.max_chunks = Some(chunks);
+        self
+    }
+
+    /// Set whether the chunks are hashed.
+    ///
+    /// The chunks are hashed with SHA-256 unless another algorithm is set
+    /// with [`hash_algorithm`](Self::hash_algorithm). The hashes are
+    /// returned in [`SplitResult::hashes`]. Each chunk is hashed on a worker
+    /// thread while the next one is read and written, so hashing does not
+    /// slow down the split process on fast storage.
+    pub fn hash(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.hash = enabled;
+        self
+    }
+
+    /// Set the maximum number of chunks hashed at the same time.
+    ///
+    /// By default, it is the available parallelism of the system. Each
+    /// pending chunk is held in memory until it is hashed. BLAKE3 hashes one
+    /// chunk at a time with all the workers instead, as it hashes the parts
+    /// of a chunk in parallel.
+    pub fn hash_workers(
+        mut self,
+        workers: usize,
+    ) -> Self {
+        self.hash_workers = Some(workers);
+        self
+    }
+
+    /// Set the algorithm to hash the original file and the chunks with.
+    ///
+    /// When set, the original file is hashed while it is split, and the
+    /// digest is returned in [`SplitResult::file_hash`], e.g. to verify the
+    /// file merged by a receiver. The chunks are hashed with the same
+    /// algorithm when [`hash`](Self::hash) is enabled, by default with
+    /// [`HashAlgorithm::Sha256`].
+    pub fn hash_algorithm(
+        mut self,
+        algorithm: HashAlgorithm,
+    ) -> Self {
+        self.hash_algorithm = Some(algorithm);
+        self
+    }
+
+    /// Follow the input file while it is still being written.
+    ///
+    /// Instead of stopping at the end of the input file, the split process
+    /// polls it for new data and writes a chunk whenever `chunk_size` bytes
+    /// are available. It finishes with the remaining data once
+    /// [`Follow::end`] is called. The limit of
+    /// [`max_chunks`](Self::max_chunks) is enforced as the chunks are
+    /// written, and `passthrough` has no effect.
+    pub fn follow(
+        mut self,
+        follow: &Follow,
+    ) -> Self {
+        self.follow = Some(follow.clone());
+        self
+    }
+
+    #[cfg(feature = "manifest")]
+    /// Set whether a manifest is written into the output directory.
+    ///
+    /// The manifest is written as [`MANIFEST_FILE_NAME`] once all the chunks
+    /// are written. It records the file size, the chunk size and the name
+    /// and size of each chunk, and their hashes when [`hash`](Self::hash) is
+    /// enabled, so the output directory is self-describing.
+    pub fn with_manifest(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.manifest = enabled;
+        self
+    }
+
+    /// Add a metadata entry to the manifest, e.g. the uploader or the
+    /// content type.
+    ///
+    /// The entries are written only with [`with_manifest`](Self::with_manifest)
+    /// and are available from [`CheckResult`](crate::check::CheckResult)
+    /// when the chunks are checked against the manifest.
+    pub fn metadata<K: Into<String>, V: Into<String>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set whether identical chunks from a prior run are kept.
+    ///
+    /// When enabled, a chunk is not written if the output directory already
+    /// contains a chunk at the same index with the same size and SHA-256
+    /// hash, so splitting a slowly changing file again mostly rewrites the
+    /// changed chunks only.
+    pub fn dedup(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.dedup = enabled;
+        self
+    }
+
+    /// Set whether the split continues after the chunks of a previous run.
+    ///
+    /// By default, all the chunks are written again. When set, the leading
+    /// chunks in the output directory that pass the check are kept, and the
+    /// input file is split from the first missing or invalid chunk, e.g.
+    /// after an interrupted split of a huge file. The kept chunks are read
+    /// again only to hash them when hashing is enabled. It applies to the
+    /// synchronous split of an input file that is not followed.
+    pub fn resume(
+        mut self,
+        check: ResumeCheck,
+    ) -> Self {
+        self.resume = Some(check);
+        self
+    }
+
+    /// Set whether the input file is checked for changes during the split.
+    ///
+    /// By default, the input file is assumed not to change. When enabled,
+    /// its size and modification time are compared before and after the
+    /// chunks are written, and [`SplitError::InFileChanged`] is returned if
+    /// they differ, as the chunks may then mix old and new content. When the
+    /// [`hash_algorithm`](Self::hash_algorithm) is set too, the input file is
+    /// also hashed again and compared with the hash of the chunks, which
+    /// catches a change within the resolution of the modification time at
+    /// the cost of reading it twice. It has no effect when the input is
+    /// followed or a reader.
+    pub fn guard_input(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.guard_input = enabled;
+        self
+    }
+
+    /// Set whether the page cache is hinted about how the input file is
+    /// read.
+    ///
+    /// By default, the input file is cached like any other file. When
+    /// enabled, it is hinted to be read sequentially, and its cached pages
+    /// are dropped once the chunks are written, so a large split does not
+    /// evict the cache of the rest of the system. It is supported on Linux,
+    /// and with the synchronous `run` on Windows.
+    pub fn cache_hints(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.cache_hints = enabled;
+        self
+    }
+
+    /// Set whether the files are read and written without the page cache.
+    ///
+    /// By default, the files go through the page cache. When enabled on
+    /// Linux, the input file is read and the chunks are written with
+    /// `O_DIRECT` through an aligned buffer, so a split of terabytes does
+    /// not touch the cache at all, only the last partial block of each chunk
+    /// is written through it. The chunk size must be a multiple of 4096
+    /// bytes. It falls back to the page cache when the file system does not
+    /// support it, e.g. `tmpfs`, and has no effect when the chunks are
+    /// hashed, deduplicated, throttled or the input is followed.
+    pub fn direct_io(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.direct_io = enabled;
+        self
+    }
+
+    /// Set how far the chunks are persisted before the split returns.
+    ///
+    /// By default, the chunks are only flushed to the operating system, so
+    /// they may be lost on a power failure. With [`Durability::Fsync`], the
+    /// chunks and the manifest are written to the disk, and with
+    /// [`Durability::FsyncDir`], so is the output directory, so a crash
+    /// after the split returns cannot lose them. A failure returns
+    /// [`SplitError::OutFileNotSynced`].
+    pub fn durability(
+        mut self,
+        durability: Durability,
+    ) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Set whether the free space of the output directory is checked
+    /// before any chunk is written.
+    ///
+    /// By default, a full disk is only noticed when a chunk cannot be
+    /// written, leaving the chunks written so far behind. When enabled, the
+    /// split fails early with [`SplitError::InsufficientSpace`] if the file
+    /// system has less free space than the size of the input file. It is
+    /// supported on Linux, and has no effect with a reader as the input.
+    pub fn check_space(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.check_space = enabled;
+        self
+    }
+
+    /// Set what happens when the output directory is not empty.
+    ///
+    /// By default, the chunks are written among the existing entries, so
+    /// chunks left by a previous run past the last new chunk are kept and
+    /// may be merged with them. [`IfNotEmpty::Clean`] removes all the
+    /// entries of the directory first, and [`IfNotEmpty::Error`] returns
+    /// [`SplitError::OutDirNotEmpty`] before any chunk is written.
+    pub fn if_not_empty(
+        mut self,
+        policy: IfNotEmpty,
+    ) -> Self {
+        self.if_not_empty = policy;
+        self
+    }
+
+    /// Set the maximum rate of IO in bytes per second.
+    ///
+    /// By default, the rate is unlimited. The split process sleeps whenever it
+    /// gets ahead of the rate, so background jobs do not saturate the disk.
+    /// Short bursts of up to one second of IO are allowed.
+    pub fn throttle_bytes_per_sec(
+        mut self,
+        rate: u64,
+    ) -> Self {
+        self.throttle = Some(rate);
+        self
+    }
+
+    /// Set the number of chunks written at the same time.
+    ///
+    /// By default, the chunks are written one at a time. With the `tokio`
+    /// feature, `run_async` writes up to `concurrency` chunks in parallel on
+    /// tasks, which suits fast disks. With the `rayon` feature,
+    /// [`run`](Self::run) reads the sections of the input file and writes
+    /// their chunks from a thread pool of `concurrency` threads, unless the
+    /// input is followed, throttled or hashed as a whole with
+    /// [`hash_algorithm`](Self::hash_algorithm). Other implementations write
+    /// the chunks one at a time regardless.
+    pub fn concurrency(
+        mut self,
+        concurrency: usize,
+    ) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Set the clock measuring the time of the split process.
+    ///
+    /// By default, it is the [`SystemClock`]. It is used by the throttle
+    /// of the synchronous process, e.g. to run tests with a mock clock
+    /// without waiting. The asynchronous processes wait with the timers of
+    /// their runtime.
+    pub fn clock<C: Clock + 'static>(
+        mut self,
+        clock: C,
+    ) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Set the priority of the thread running the split process.
+    ///
+    /// It is only applied by the synchronous `run`, as an async task may be
+    /// moved between threads. See [`Priority`] for more details.
+    pub fn priority(
+        mut self,
+        priority: Priority,
+    ) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Set the ID of the split process.
+    ///
+    /// By default, a new process is assigned a random ID. It is recorded in
+    /// the metadata of the manifest under [`RUN_ID_KEY`], unless an entry
+    /// with that key is set, see [`RunId`].
+    pub fn run_id(
+        mut self,
+        id: RunId,
+    ) -> Self {
+        self.run_id = id;
+        self
+    }
+
+    /// Attach a control handle to pause, resume and cancel the split process.
+    pub fn control(
+        mut self,
+        control: &Control,
+    ) -> Self {
+        self.control = Some(control.clone());
+        self
+    }
+
     /// Run the split process.
     pub fn run(&self) -> Result<SplitResult, SplitError> {
         let in_file: &Path = match self.in_file {
@@ -208,98 +862,1363 @@ impl Split {
             | None => return Err(SplitError::InFileNotSet),
         };
 
-        let out_dir: &Path = match self.out_dir {
-            | Some(ref p) => {
-                let p: &Path = p.as_path();
-
-                // if out_dir not exists
-                if !p.exists() {
-                    if fs::create_dir_all(p).is_err() {
-                        return Err(SplitError::OutDirNotDir);
-                    }
-                } else {
-                    // if out_dir not a directory
-                    if p.is_file() {
-                        return Err(SplitError::OutDirNotDir);
-                    }
-                }
+        let out_dir: &Path = self.prepare_out_dir()?;
 
-                p
+        let _priority: Option<PriorityGuard> = match self.priority {
+            | Some(ref p) => match PriorityGuard::apply(p) {
+                | Ok(g) => Some(g),
+                | Err(e) => {
+                    return Err(SplitError::PriorityNotSet {
+                        source: e.into(),
+                    });
+                },
             },
-            | None => return Err(SplitError::OutDirNotSet),
+            | None => None,
         };
 
+        let _control: Option<ControlGuard> =
+            self.control.as_ref().map(Control::run);
+
         let chunk_size: usize = self.chunk_size;
 
-        let buffer_capacity: usize = chunk_size.min(self.cap_max);
+        let mut options: fs::OpenOptions = fs::OpenOptions::new();
+        options.read(true);
 
-        let input: fs::File =
-            match fs::OpenOptions::new().read(true).open(in_file) {
-                | Ok(f) => f,
-                | Err(_) => return Err(SplitError::InFileNotOpened),
-            };
+        if self.cache_hints {
+            advise::sequential_scan(&mut options);
+        }
 
-        let file_size: usize = match input.metadata() {
-            | Ok(m) => m.len() as usize,
-            | Err(_) => return Err(SplitError::InFileNotRead),
+        let input: fs::File = match options.open(in_file) {
+            | Ok(f) => f,
+            | Err(e) => {
+                return Err(SplitError::InFileNotOpened {
+                    path: in_file.to_path_buf(),
+                    source: e.into(),
+                });
+            },
         };
 
-        let mut reader: io::BufReader<fs::File> =
-            io::BufReader::with_capacity(buffer_capacity, input);
+        if self.cache_hints {
+            advise::sequential(&input);
+        }
 
-        let mut buffer: Vec<u8> = vec![0; chunk_size];
+        let metadata: fs::Metadata = match input.metadata() {
+            | Ok(m) => m,
+            | Err(e) => {
+                return Err(SplitError::InFileNotRead {
+                    path: in_file.to_path_buf(),
+                    source: e.into(),
+                });
+            },
+        };
 
-        let mut total_chunks: usize = 0;
+        let file_size: usize = metadata.len() as usize;
 
-        loop {
-            let mut offset: usize = 0;
+        // refuse to create more chunks than allowed
+        if let Some(max) = self.max_chunks {
+            let total: usize = plan::plan_split(file_size, chunk_size)
+                .map_or(0, |p| p.total_chunks());
 
-            while offset < chunk_size {
-                let bytes_read: usize = match reader.read(&mut buffer[offset..])
-                {
-                    | Ok(n) => n,
-                    | Err(_) => return Err(SplitError::InFileNotRead),
-                };
+            if total > max {
+                return Err(SplitError::TooManyChunks);
+            }
+        }
 
-                if bytes_read == 0 {
-                    break;
-                }
+        // refuse the names before any chunk is written
+        self.check_names(file_size)?;
+        self.ensure_space(out_dir, file_size)?;
 
-                offset += bytes_read;
-            }
+        // link the input as the only chunk instead of copying it
+        if self.passthrough
+            && !self.hash
+            && self.hash_algorithm.is_none()
+            && self.follow.is_none()
+            && !self.namer.uses_content()
+            && file_size > 0
+            && file_size <= chunk_size
+            && device::same_device(in_file, out_dir)
+        {
+            let output_path: PathBuf = out_dir.join(self.namer.name(0));
 
-            if offset == 0 {
-                break;
-            }
+            let _ = fs::remove_file(&output_path);
 
-            let output_path: PathBuf = out_dir.join(total_chunks.to_string());
+            if fs::hard_link(in_file, &output_path).is_ok() {
+                // describe the chunks next to them
+                #[cfg(feature = "manifest")]
+                if self.manifest {
+                    let manifest: Manifest =
+                        self.to_manifest(file_size, &[file_size], None);
 
-            let output: File = match fs::OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(output_path)
-            {
-                | Ok(f) => f,
-                | Err(_) => return Err(SplitError::OutFileNotOpened),
-            };
+                    let manifest_path: PathBuf =
+                        out_dir.join(MANIFEST_FILE_NAME);
+
+                    if let Err(e) = manifest.write(&manifest_path) {
+                        return Err(SplitError::ManifestNotWritten {
+                            path: manifest_path,
+                            source: e.into(),
+                        });
+                    }
+                }
 
-            let mut writer: BufWriter<File> =
-                io::BufWriter::with_capacity(buffer_capacity, output);
+                if let Some(ref c) = self.control {
+                    c.record(0, file_size as u64);
+                }
 
-            if writer.write_all(&buffer[..offset]).is_err() {
-                return Err(SplitError::OutFileNotWritten);
-            }
+                self.persist(out_dir, std::slice::from_ref(&output_path))?;
 
-            if writer.flush().is_err() {
-                return Err(SplitError::OutFileNotWritten);
+                return Ok(SplitResult {
+                    file_size,
+                    total_chunks: 1,
+                    hashes: None,
+                    file_hash: None,
+                    chunk_paths: vec![output_path],
+                    chunk_sizes: vec![file_size],
+                });
             }
-
-            total_chunks += 1;
         }
 
-        Ok(SplitResult { file_size, total_chunks })
-    }
+        let split_result: SplitResult =
+            self.split_file(input, out_dir, file_size)?;
+
+        self.persist(out_dir, &split_result.chunk_paths)?;
+
+        // the chunks may mix old and new content
+        if self.guard_input && self.follow.is_none() {
+            let changed: bool = match fs::metadata(in_file) {
+                | Ok(m) => InputStamp::new(&m) != InputStamp::new(&metadata),
+                | Err(e) => {
+                    return Err(SplitError::InFileNotRead {
+                        path: in_file.to_path_buf(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if changed {
+                return Err(SplitError::InFileChanged);
+            }
+
+            if let (Some(algorithm), Some(expected)) =
+                (self.hash_algorithm, split_result.file_hash.as_ref())
+            {
+                match hash_file(in_file, algorithm, self.cap_max) {
+                    | Ok(h) if h == *expected => {},
+                    | Ok(_) => return Err(SplitError::InFileChanged),
+                    | Err(e) => {
+                        return Err(SplitError::InFileNotRead {
+                            path: in_file.to_path_buf(),
+                            source: e.into(),
+                        });
+                    },
+                }
+            }
+        }
+
+        // the input is not read again
+        if self.cache_hints {
+            if let Ok(f) = fs::File::open(in_file) {
+                advise::dont_need(&f);
+            }
+        }
+
+        Ok(split_result)
+    }
+
+    /// Run the split process with a reader as the input.
+    ///
+    /// The reader is consumed until its end, e.g. the body of a download, so
+    /// it is split on the fly without writing it to a file first. Data is
+    /// only read as fast as the chunks are written. As the size is not known
+    /// upfront, the limit of [`max_chunks`](Self::max_chunks) is enforced as
+    /// the chunks are written, and `in_file`, `passthrough` and `follow`
+    /// have no effect.
+    pub fn run_reader<R: Read>(
+        &self,
+        reader: R,
+    ) -> Result<SplitResult, SplitError> {
+        let out_dir: &Path = self.prepare_out_dir()?;
+
+        let _priority: Option<PriorityGuard> = match self.priority {
+            | Some(ref p) => match PriorityGuard::apply(p) {
+                | Ok(g) => Some(g),
+                | Err(e) => {
+                    return Err(SplitError::PriorityNotSet {
+                        source: e.into(),
+                    });
+                },
+            },
+            | None => None,
+        };
+
+        let _control: Option<ControlGuard> =
+            self.control.as_ref().map(Control::run);
+
+        let split_result: SplitResult =
+            self.split_from(reader, out_dir, None, None, &[])?;
+
+        self.persist(out_dir, &split_result.chunk_paths)?;
+
+        Ok(split_result)
+    }
+
+    /// Get the output directory, creating it if it does not exist.
+    fn prepare_out_dir(&self) -> Result<&Path, SplitError> {
+        self.check_namer()?;
+
+        match self.out_dir {
+            | Some(ref p) => {
+                let p: &Path = p.as_path();
+
+                // if out_dir not exists
+                if !p.exists() {
+                    if let Err(e) = fs::create_dir_all(p) {
+                        return Err(SplitError::OutDirNotCreated {
+                            path: p.to_path_buf(),
+                            source: e.into(),
+                        });
+                    }
+                } else {
+                    // if out_dir not a directory
+                    if p.is_file() {
+                        return Err(SplitError::OutDirNotDir);
+                    }
+
+                    self.handle_entries(p)?;
+                }
+
+                Ok(p)
+            },
+            | None => Err(SplitError::OutDirNotSet),
+        }
+    }
+
+    /// Split the input into chunks.
+    ///
+    /// The `file_size` is reported unless the input is followed or unknown,
+    /// then the size read is reported instead. The `kept` chunks of a
+    /// previous run are reported without being written, and the input must
+    /// be positioned after them.
+    fn split_from<R: Read>(
+        &self,
+        input: R,
+        out_dir: &Path,
+        file_size: Option<usize>,
+        follow: Option<&Follow>,
+        kept: &[usize],
+    ) -> Result<SplitResult, SplitError> {
+        let chunk_size: usize = self.chunk_size;
+
+        let buffer_capacity: usize = chunk_size.min(self.cap_max);
+
+        let mut reader: io::BufReader<R> =
+            io::BufReader::with_capacity(buffer_capacity, input);
+
+        let mut buffer: Vec<u8> = vec![0; chunk_size];
+
+        let mut total_chunks: usize = 0;
+        let mut bytes_done: usize = 0;
+        let mut sizes: Vec<usize> = Vec::new();
+        let mut paths: Vec<PathBuf> = Vec::new();
+        let mut throttle: Option<Throttle> =
+            self.throttle.map(|r| Throttle::new(r, self.clock.clone()));
+        let mut file_hasher: Option<Hasher> =
+            self.hash_algorithm.map(|a| a.hasher());
+
+        let mut hashes: Option<HashPipeline> = if self.hash {
+            Some(HashPipeline::new(
+                self.hash_algorithm.unwrap_or_default(),
+                self.hash_workers.unwrap_or_else(hash::default_workers),
+            ))
+        } else {
+            None
+        };
+
+        for size in kept.iter() {
+            let output_path: PathBuf =
+                out_dir.join(self.checked_name(self.namer.name(total_chunks))?);
+
+            // the kept chunks are only read to hash them
+            if file_hasher.is_some() || hashes.is_some() {
+                let content: Vec<u8> = match fs::read(&output_path) {
+                    | Ok(c) => c,
+                    | Err(e) => {
+                        return Err(SplitError::OutFileNotOpened {
+                            path: output_path,
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                if let Some(ref mut h) = file_hasher {
+                    h.update(&content);
+                }
+
+                if let Some(ref mut h) = hashes {
+                    if h.push(content).is_err() {
+                        return Err(SplitError::ChunkNotHashed);
+                    }
+                }
+            }
+
+            if let Some(ref c) = self.control {
+                c.record(total_chunks, *size as u64);
+            }
+
+            bytes_done += size;
+            sizes.push(*size);
+            paths.push(output_path);
+            total_chunks += 1;
+        }
+
+        loop {
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(SplitError::Cancelled);
+                }
+            }
+
+            let mut offset: usize = 0;
+
+            while offset < chunk_size {
+                // checked while the chunk is filled, not only after it
+                if self.control.as_ref().is_some_and(Control::is_cancelled) {
+                    return Err(SplitError::Cancelled);
+                }
+
+                // checked before reading, so no data written before the end
+                // is missed
+                let ended: bool = follow.is_none_or(Follow::is_ended);
+
+                let bytes_read: usize = match reader.read(&mut buffer[offset..])
+                {
+                    | Ok(n) => n,
+                    | Err(e) => {
+                        return Err(SplitError::InFileNotRead {
+                            path: self.in_file.clone().unwrap_or_default(),
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                if bytes_read == 0 {
+                    let f: &Follow = match follow {
+                        | Some(f) if !ended => f,
+                        | _ => break,
+                    };
+
+                    if self.control.as_ref().is_some_and(Control::is_cancelled)
+                    {
+                        return Err(SplitError::Cancelled);
+                    }
+
+                    thread::sleep(f.interval);
+
+                    continue;
+                }
+
+                offset += bytes_read;
+            }
+
+            // an empty file produces no chunk unless `empty_chunk` is set
+            if offset == 0 && (total_chunks > 0 || !self.empty_chunk) {
+                break;
+            }
+
+            // the size of a followed file or a reader is not known upfront
+            if self.max_chunks.is_some_and(|max| total_chunks >= max) {
+                return Err(SplitError::TooManyChunks);
+            }
+
+            bytes_done += offset;
+
+            if let Some(ref mut h) = file_hasher {
+                h.update(&buffer[..offset]);
+            }
+
+            sizes.push(offset);
+
+            let output_path: PathBuf =
+                out_dir.join(self.chunk_name(total_chunks, &buffer[..offset])?);
+
+            // keep an identical chunk from a prior run
+            if !(self.dedup && is_duplicate(&output_path, &buffer[..offset])) {
+                let output: File = match fs::OpenOptions::new()
+                    .create(true)
+                    .truncate(true)
+                    .write(true)
+                    .open(&output_path)
+                {
+                    | Ok(f) => f,
+                    | Err(e) => {
+                        return Err(SplitError::OutFileNotOpened {
+                            path: output_path,
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                let mut writer: BufWriter<File> =
+                    io::BufWriter::with_capacity(buffer_capacity, output);
+
+                if let Err(e) = writer.write_all(&buffer[..offset]) {
+                    return Err(SplitError::OutFileNotWritten {
+                        path: output_path,
+                        source: e.into(),
+                    });
+                }
+
+                if let Err(e) = writer.flush() {
+                    return Err(SplitError::OutFileNotWritten {
+                        path: output_path,
+                        source: e.into(),
+                    });
+                }
+            }
+
+            paths.push(output_path);
+
+            if let Some(ref mut t) = throttle {
+                t.wait(offset as u64);
+            }
+
+            if let Some(ref mut h) = hashes {
+                if h.push(buffer[..offset].to_vec()).is_err() {
+                    return Err(SplitError::ChunkNotHashed);
+                }
+            }
+
+            if let Some(ref c) = self.control {
+                c.record(total_chunks, offset as u64);
+            }
+
+            total_chunks += 1;
+        }
+
+        let hashes: Option<Vec<String>> = match hashes.map(HashPipeline::finish)
+        {
+            | Some(Ok(h)) => Some(h),
+            | Some(Err(_)) => return Err(SplitError::ChunkNotHashed),
+            | None => None,
+        };
+
+        // a followed file has grown since it was opened
+        let file_size: usize = match file_size {
+            | Some(size) if follow.is_none() => size,
+            | _ => bytes_done,
+        };
+
+        // describe the chunks next to them
+        #[cfg(feature = "manifest")]
+        if self.manifest {
+            let manifest: Manifest =
+                self.to_manifest(file_size, &sizes, hashes.as_deref());
+
+            let manifest_path: PathBuf = out_dir.join(MANIFEST_FILE_NAME);
+
+            if let Err(e) = manifest.write(&manifest_path) {
+                return Err(SplitError::ManifestNotWritten {
+                    path: manifest_path,
+                    source: e.into(),
+                });
+            }
+        }
+
+        Ok(SplitResult {
+            file_size,
+            total_chunks,
+            hashes,
+            file_hash: file_hasher.map(Hasher::finalize_hex),
+            chunk_paths: paths,
+            chunk_sizes: sizes,
+        })
+    }
+}
+
+impl Split {
+    /// Split the opened input file into chunks.
+    fn split_file(
+        &self,
+        mut input: File,
+        out_dir: &Path,
+        file_size: usize,
+    ) -> Result<SplitResult, SplitError> {
+        // continue after the chunks kept from a previous run
+        if let (Some(check), None) = (self.resume, self.follow.as_ref()) {
+            let kept: Vec<usize> =
+                self.kept_chunks(&mut input, out_dir, file_size, check)?;
+
+            // the comparison may have read past the kept chunks
+            let offset: u64 = kept.iter().sum::<usize>() as u64;
+
+            if let Err(e) = input.seek(SeekFrom::Start(offset)) {
+                return Err(SplitError::InFileNotRead {
+                    path: self.in_file.clone().unwrap_or_default(),
+                    source: e.into(),
+                });
+            }
+
+            if !kept.is_empty() {
+                return self.split_from(
+                    input,
+                    out_dir,
+                    Some(file_size),
+                    None,
+                    &kept,
+                );
+            }
+        }
+
+        // each chunk is read before it is named
+        if self.namer.uses_content() {
+            return self.split_from(
+                input,
+                out_dir,
+                Some(file_size),
+                self.follow.as_ref(),
+                &[],
+            );
+        }
+
+        // read and write the chunks without the page cache
+        #[cfg(target_os = "linux")]
+        if self.direct_io
+            && !self.hash
+            && self.hash_algorithm.is_none()
+            && !self.dedup
+            && self.throttle.is_none()
+            && self.follow.is_none()
+            && self.chunk_size > 0
+            && self.chunk_size % direct::ALIGNMENT == 0
+            && file_size > 0
+        {
+            if let Some(r) = self.split_direct(out_dir, file_size)? {
+                return Ok(r);
+            }
+        }
+
+        // create the chunks within the file system, without reading them,
+        // unless another mode was asked for
+        #[cfg(target_os = "linux")]
+        if !self.hash
+            && self.hash_algorithm.is_none()
+            && !self.dedup
+            && !self.mmap
+            && self.concurrency <= 1
+            && self.throttle.is_none()
+            && self.follow.is_none()
+            && self.chunk_size > 0
+            && file_size > 0
+        {
+            if let Some(r) = self.split_copied(&input, out_dir, file_size)? {
+                return Ok(r);
+            }
+        }
+
+        // write the chunks straight from the mapped input
+        #[cfg(all(feature = "mmap", target_os = "linux"))]
+        if self.mmap
+            && self.follow.is_none()
+            && self.chunk_size > 0
+            && file_size > 0
+        {
+            if let Ok(map) = Mmap::map(&input, file_size) {
+                return self.split_mapped(&map, out_dir);
+            }
+        }
+
+        // write the chunks from a thread pool, each reading its own section
+        #[cfg(feature = "rayon")]
+        if self.concurrency > 1
+            && self.chunk_size > 0
+            && self.follow.is_none()
+            && self.hash_algorithm.is_none()
+            && self.throttle.is_none()
+            && file_size > 0
+        {
+            if let Ok(pool) = rayon::ThreadPoolBuilder::new()
+                .num_threads(self.concurrency)
+                .build()
+            {
+                return pool
+                    .install(|| self.split_parallel(out_dir, file_size));
+            }
+        }
+
+        self.split_from(
+            input,
+            out_dir,
+            Some(file_size),
+            self.follow.as_ref(),
+            &[],
+        )
+    }
+
+    /// Get the sizes of the leading chunks of a previous run to keep.
+    ///
+    /// The input file is read from its start when the chunks are compared
+    /// to it, and must be positioned again afterwards.
+    fn kept_chunks(
+        &self,
+        input: &mut File,
+        out_dir: &Path,
+        file_size: usize,
+        check: ResumeCheck,
+    ) -> Result<Vec<usize>, SplitError> {
+        let mut kept: Vec<usize> = Vec::new();
+
+        // the names of the chunks may depend on their content
+        self.namer.scan(out_dir);
+
+        let chunks: Vec<plan::ChunkPlan> =
+            match plan::plan_split(file_size, self.chunk_size) {
+                | Some(p) => p.collect(),
+                | None => return Ok(kept),
+            };
+
+        for chunk in chunks {
+            let path: PathBuf =
+                out_dir.join(self.checked_name(self.namer.name(chunk.index))?);
+
+            if !fs::metadata(&path)
+                .is_ok_and(|m| m.is_file() && m.len() == chunk.size as u64)
+            {
+                break;
+            }
+
+            // compared a block at a time, so a huge chunk is not held in
+            // memory, and an unreadable input is reported when it is split
+            if check == ResumeCheck::Hash
+                && !same_content(&path, &mut *input, chunk.size as u64)
+                    .unwrap_or(false)
+            {
+                break;
+            }
+
+            kept.push(chunk.size);
+        }
+
+        Ok(kept)
+    }
+
+    /// Split the input file by copying its ranges into the chunks.
+    ///
+    /// Returns `None` to fall back to reading the input file when the file
+    /// system cannot copy the ranges, after the chunks copied so far are
+    /// removed. The progress is only recorded once every range is copied, so
+    /// the fallback does not count the chunks twice.
+    #[cfg(target_os = "linux")]
+    fn split_copied(
+        &self,
+        input: &File,
+        out_dir: &Path,
+        file_size: usize,
+    ) -> Result<Option<SplitResult>, SplitError> {
+        let chunks: Vec<plan::ChunkPlan> =
+            plan::plan_split(file_size, self.chunk_size)
+                .map(Iterator::collect)
+                .unwrap_or_default();
+
+        let mut paths: Vec<PathBuf> = Vec::with_capacity(chunks.len());
+
+        for chunk in chunks.iter() {
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(SplitError::Cancelled);
+                }
+            }
+
+            let output_path: PathBuf =
+                out_dir.join(self.checked_name(self.namer.name(chunk.index))?);
+
+            let output: File = match File::create(&output_path) {
+                | Ok(f) => f,
+                | Err(e) => {
+                    return Err(SplitError::OutFileNotOpened {
+                        path: output_path,
+                        source: e.into(),
+                    });
+                },
+            };
+
+            paths.push(output_path);
+
+            if copy::copy_range(
+                input,
+                &output,
+                chunk.offset as u64,
+                chunk.size as u64,
+            )
+            .is_err()
+            {
+                drop(output);
+
+                for path in paths.iter() {
+                    let _ = fs::remove_file(path);
+                }
+
+                return Ok(None);
+            }
+        }
+
+        if let Some(ref c) = self.control {
+            for chunk in chunks.iter() {
+                c.record(chunk.index, chunk.size as u64);
+            }
+        }
+
+        let sizes: Vec<usize> = chunks.iter().map(|c| c.size).collect();
+
+        // describe the chunks next to them
+        #[cfg(feature = "manifest")]
+        if self.manifest {
+            let manifest: Manifest = self.to_manifest(file_size, &sizes, None);
+
+            let manifest_path: PathBuf = out_dir.join(MANIFEST_FILE_NAME);
+
+            if let Err(e) = manifest.write(&manifest_path) {
+                return Err(SplitError::ManifestNotWritten {
+                    path: manifest_path,
+                    source: e.into(),
+                });
+            }
+        }
+
+        Ok(Some(SplitResult {
+            file_size,
+            total_chunks: chunks.len(),
+            hashes: None,
+            file_hash: None,
+            chunk_paths: paths,
+            chunk_sizes: sizes,
+        }))
+    }
+
+    /// Split the input file without the page cache.
+    ///
+    /// Returns `None` to fall back to the page cache when the file system
+    /// does not support it.
+    #[cfg(target_os = "linux")]
+    fn split_direct(
+        &self,
+        out_dir: &Path,
+        file_size: usize,
+    ) -> Result<Option<SplitResult>, SplitError> {
+        let in_file: &Path = self.in_file.as_deref().unwrap_or(Path::new(""));
+
+        let mut input: File = match direct::open(in_file) {
+            | Ok(f) => f,
+            | Err(_) => return Ok(None),
+        };
+
+        let chunks: Vec<plan::ChunkPlan> =
+            plan::plan_split(file_size, self.chunk_size)
+                .map(Iterator::collect)
+                .unwrap_or_default();
+
+        let mut buffer: AlignedBuffer = AlignedBuffer::new(self.chunk_size);
+        let mut paths: Vec<PathBuf> = Vec::with_capacity(chunks.len());
+
+        for chunk in chunks.iter() {
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(SplitError::Cancelled);
+                }
+            }
+
+            let read: usize =
+                match direct::read_full(&mut input, buffer.as_mut_slice()) {
+                    | Ok(n) => n,
+                    | Err(e) => {
+                        return Err(SplitError::InFileNotRead {
+                            path: in_file.to_path_buf(),
+                            source: e.into(),
+                        });
+                    },
+                };
+
+            // the input was truncated since its size was read
+            if read < chunk.size {
+                return Err(SplitError::InFileChanged);
+            }
+
+            let output_path: PathBuf =
+                out_dir.join(self.namer.name(chunk.index));
+
+            let mut output: File = match direct::create(&output_path) {
+                | Ok(f) => f,
+                // the output directory may not support it either
+                | Err(_) if chunk.index == 0 => return Ok(None),
+                | Err(e) => {
+                    return Err(SplitError::OutFileNotOpened {
+                        path: output_path,
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if let Err(e) =
+                direct::write(&mut output, &buffer.as_slice()[..chunk.size])
+            {
+                return Err(SplitError::OutFileNotWritten {
+                    path: output_path,
+                    source: e.into(),
+                });
+            }
+
+            paths.push(output_path);
+
+            if let Some(ref c) = self.control {
+                c.record(chunk.index, chunk.size as u64);
+            }
+        }
+
+        let sizes: Vec<usize> = chunks.iter().map(|c| c.size).collect();
+
+        // describe the chunks next to them
+        #[cfg(feature = "manifest")]
+        if self.manifest {
+            let manifest: Manifest = self.to_manifest(file_size, &sizes, None);
+
+            let manifest_path: PathBuf = out_dir.join(MANIFEST_FILE_NAME);
+
+            if let Err(e) = manifest.write(&manifest_path) {
+                return Err(SplitError::ManifestNotWritten {
+                    path: manifest_path,
+                    source: e.into(),
+                });
+            }
+        }
+
+        Ok(Some(SplitResult {
+            file_size,
+            total_chunks: chunks.len(),
+            hashes: None,
+            file_hash: None,
+            chunk_paths: paths,
+            chunk_sizes: sizes,
+        }))
+    }
+
+    /// Split the input file mapped into memory.
+    ///
+    /// The chunks are written from the mapped pages, without copying them
+    /// into a buffer first.
+    #[cfg(all(feature = "mmap", target_os = "linux"))]
+    fn split_mapped(
+        &self,
+        map: &Mmap,
+        out_dir: &Path,
+    ) -> Result<SplitResult, SplitError> {
+        let content: &[u8] = map.as_slice();
+
+        let mut sizes: Vec<usize> = Vec::new();
+        let mut paths: Vec<PathBuf> = Vec::new();
+        let mut throttle: Option<Throttle> =
+            self.throttle.map(|r| Throttle::new(r, self.clock.clone()));
+        let mut file_hasher: Option<Hasher> =
+            self.hash_algorithm.map(|a| a.hasher());
+
+        let mut hashes: Option<HashPipeline> = if self.hash {
+            Some(HashPipeline::new(
+                self.hash_algorithm.unwrap_or_default(),
+                self.hash_workers.unwrap_or_else(hash::default_workers),
+            ))
+        } else {
+            None
+        };
+
+        for (index, chunk) in content.chunks(self.chunk_size).enumerate() {
+            if let Some(ref c) = self.control {
+                if !c.wait() {
+                    return Err(SplitError::Cancelled);
+                }
+            }
+
+            if let Some(ref mut h) = file_hasher {
+                h.update(chunk);
+            }
+
+            sizes.push(chunk.len());
+
+            let output_path: PathBuf = out_dir.join(self.namer.name(index));
+
+            // keep an identical chunk from a prior run
+            if !(self.dedup && is_duplicate(&output_path, chunk)) {
+                let mut output: File = match File::create(&output_path) {
+                    | Ok(f) => f,
+                    | Err(e) => {
+                        return Err(SplitError::OutFileNotOpened {
+                            path: output_path,
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                if let Err(e) = output.write_all(chunk) {
+                    return Err(SplitError::OutFileNotWritten {
+                        path: output_path,
+                        source: e.into(),
+                    });
+                }
+            }
+
+            paths.push(output_path);
+
+            if let Some(ref mut t) = throttle {
+                t.wait(chunk.len() as u64);
+            }
+
+            if let Some(ref mut h) = hashes {
+                if h.push(chunk.to_vec()).is_err() {
+                    return Err(SplitError::ChunkNotHashed);
+                }
+            }
+
+            if let Some(ref c) = self.control {
+                c.record(index, chunk.len() as u64);
+            }
+        }
+
+        let hashes: Option<Vec<String>> = match hashes.map(HashPipeline::finish)
+        {
+            | Some(Ok(h)) => Some(h),
+            | Some(Err(_)) => return Err(SplitError::ChunkNotHashed),
+            | None => None,
+        };
+
+        // describe the chunks next to them
+        #[cfg(feature = "manifest")]
+        if self.manifest {
+            let manifest: Manifest =
+                self.to_manifest(content.len(), &sizes, hashes.as_deref());
+
+            let manifest_path: PathBuf = out_dir.join(MANIFEST_FILE_NAME);
+
+            if let Err(e) = manifest.write(&manifest_path) {
+                return Err(SplitError::ManifestNotWritten {
+                    path: manifest_path,
+                    source: e.into(),
+                });
+            }
+        }
+
+        Ok(SplitResult {
+            file_size: content.len(),
+            total_chunks: sizes.len(),
+            hashes,
+            file_hash: file_hasher.map(Hasher::finalize_hex),
+            chunk_paths: paths,
+            chunk_sizes: sizes,
+        })
+    }
+
+    /// Split the input file into chunks from the current thread pool.
+    ///
+    /// The chunks may be completed in any order, so the file is not hashed
+    /// as a whole.
+    #[cfg(feature = "rayon")]
+    fn split_parallel(
+        &self,
+        out_dir: &Path,
+        file_size: usize,
+    ) -> Result<SplitResult, SplitError> {
+        use rayon::prelude::*;
+
+        let in_file: &Path = self.in_file.as_deref().unwrap_or(Path::new(""));
+
+        let chunks: Vec<plan::ChunkPlan> =
+            plan::plan_split(file_size, self.chunk_size)
+                .map(Iterator::collect)
+                .unwrap_or_default();
+
+        let hashes: Vec<Option<String>> = chunks
+            .par_iter()
+            .map(|chunk| self.split_section(in_file, out_dir, chunk))
+            .collect::<Result<_, _>>()?;
+
+        let sizes: Vec<usize> = chunks.iter().map(|c| c.size).collect();
+        let hashes: Option<Vec<String>> = self.hash.then(|| {
+            hashes.into_iter().map(Option::unwrap_or_default).collect()
+        });
+
+        // describe the chunks next to them
+        #[cfg(feature = "manifest")]
+        if self.manifest {
+            let manifest: Manifest =
+                self.to_manifest(file_size, &sizes, hashes.as_deref());
+
+            let manifest_path: PathBuf = out_dir.join(MANIFEST_FILE_NAME);
+
+            if let Err(e) = manifest.write(&manifest_path) {
+                return Err(SplitError::ManifestNotWritten {
+                    path: manifest_path,
+                    source: e.into(),
+                });
+            }
+        }
+
+        Ok(SplitResult {
+            file_size,
+            total_chunks: chunks.len(),
+            hashes,
+            file_hash: None,
+            chunk_paths: chunks
+                .iter()
+                .map(|c| out_dir.join(self.namer.name(c.index)))
+                .collect(),
+            chunk_sizes: sizes,
+        })
+    }
+
+    /// Write the chunk read from its section of the input file.
+    ///
+    /// Returns the hash of the chunk if hashing is enabled.
+    #[cfg(feature = "rayon")]
+    fn split_section(
+        &self,
+        in_file: &Path,
+        out_dir: &Path,
+        chunk: &plan::ChunkPlan,
+    ) -> Result<Option<String>, SplitError> {
+        if let Some(ref c) = self.control {
+            if !c.wait() {
+                return Err(SplitError::Cancelled);
+            }
+        }
+
+        let mut input: File = match File::open(in_file) {
+            | Ok(f) => f,
+            | Err(e) => {
+                return Err(SplitError::InFileNotOpened {
+                    path: in_file.to_path_buf(),
+                    source: e.into(),
+                });
+            },
+        };
+
+        let mut buffer: Vec<u8> = vec![0; chunk.size];
+
+        if let Err(e) = input
+            .seek(SeekFrom::Start(chunk.offset as u64))
+            .and_then(|_| input.read_exact(&mut buffer))
+        {
+            return Err(SplitError::InFileNotRead {
+                path: in_file.to_path_buf(),
+                source: e.into(),
+            });
+        }
+
+        let output_path: PathBuf = out_dir.join(self.namer.name(chunk.index));
+
+        // keep an identical chunk from a prior run
+        if !(self.dedup && is_duplicate(&output_path, &buffer)) {
+            let mut output: File = match File::create(&output_path) {
+                | Ok(f) => f,
+                | Err(e) => {
+                    return Err(SplitError::OutFileNotOpened {
+                        path: output_path,
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if let Err(e) = output.write_all(&buffer) {
+                return Err(SplitError::OutFileNotWritten {
+                    path: output_path,
+                    source: e.into(),
+                });
+            }
+        }
+
+        if let Some(ref c) = self.control {
+            c.record(chunk.index, chunk.size as u64);
+        }
+
+        Ok(self.hash.then(|| HashAlgorithm::default().hex(&buffer)))
+    }
+
+    /// Persist the chunks and the manifest as set by the durability.
+    pub(crate) fn persist(
+        &self,
+        out_dir: &Path,
+        chunk_paths: &[PathBuf],
+    ) -> Result<(), SplitError> {
+        if self.durability == Durability::Flush {
+            return Ok(());
+        }
+
+        #[cfg_attr(not(feature = "manifest"), allow(unused_mut))]
+        let mut files: Vec<PathBuf> = chunk_paths.to_vec();
+
+        #[cfg(feature = "manifest")]
+        if self.manifest {
+            files.push(out_dir.join(MANIFEST_FILE_NAME));
+        }
+
+        match self.durability.sync(&files, out_dir) {
+            | Ok(()) => Ok(()),
+            | Err((path, e)) => {
+                Err(SplitError::OutFileNotSynced { path, source: e.into() })
+            },
+        }
+    }
+
+    /// Apply the policy to the entries of an existing output directory.
+    pub(crate) fn handle_entries(
+        &self,
+        out_dir: &Path,
+    ) -> Result<(), SplitError> {
+        if self.if_not_empty == IfNotEmpty::Reuse {
+            return Ok(());
+        }
+
+        let entries: fs::ReadDir = match fs::read_dir(out_dir) {
+            | Ok(entries) => entries,
+            | Err(e) => {
+                return Err(SplitError::OutDirNotCleaned {
+                    path: out_dir.to_path_buf(),
+                    source: e.into(),
+                });
+            },
+        };
+
+        for entry in entries {
+            let path: PathBuf = match entry {
+                | Ok(entry) => entry.path(),
+                | Err(e) => {
+                    return Err(SplitError::OutDirNotCleaned {
+                        path: out_dir.to_path_buf(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if self.if_not_empty == IfNotEmpty::Error {
+                return Err(SplitError::OutDirNotEmpty);
+            }
+
+            // a symbolic link is removed, not the directory it points to
+            let removed: io::Result<()> = match fs::symlink_metadata(&path) {
+                | Ok(m) if m.is_dir() => fs::remove_dir_all(&path),
+                | Ok(_) => fs::remove_file(&path),
+                | Err(e) => Err(e),
+            };
+
+            if let Err(e) = removed {
+                return Err(SplitError::OutDirNotCleaned {
+                    path,
+                    source: e.into(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the chunks fit in the free space of the output directory.
+    pub(crate) fn ensure_space<P: AsRef<Path>>(
+        &self,
+        out_dir: P,
+        file_size: usize,
+    ) -> Result<(), SplitError> {
+        if !self.check_space {
+            return Ok(());
+        }
+
+        match device::free_space(out_dir) {
+            | Some(free) if free < file_size as u64 => {
+                Err(SplitError::InsufficientSpace)
+            },
+            | _ => Ok(()),
+        }
+    }
+
+    /// Get the name of the chunk at the index from its content.
+    pub(crate) fn chunk_name(
+        &self,
+        index: usize,
+        content: &[u8],
+    ) -> Result<String, SplitError> {
+        self.checked_name(self.namer.name_content(index, content))
+    }
+
+    /// Check that the name of a chunk is valid.
+    fn checked_name(
+        &self,
+        name: String,
+    ) -> Result<String, SplitError> {
+        if self.portable_names && !naming::is_portable(&name) {
+            return Err(SplitError::ChunkNameInvalid);
+        }
+
+        Ok(name)
+    }
+
+    /// Check that the namer gives each chunk its own name, e.g. a template
+    /// without the index placeholder would write every chunk over the last.
+    pub(crate) fn check_namer(&self) -> Result<(), SplitError> {
+        if self.namer.name(0) == self.namer.name(1) {
+            return Err(SplitError::ChunkNameNotUnique);
+        }
+
+        Ok(())
+    }
+
+    /// Check the names of all the chunks of the input file.
+    pub(crate) fn check_names(
+        &self,
+        file_size: usize,
+    ) -> Result<(), SplitError> {
+        if !(self.portable_names || self.case_insensitive)
+            || self.follow.is_some()
+        {
+            return Ok(());
+        }
+
+        // an empty input file may still produce a chunk
+        let total: usize = plan::plan_split(file_size, self.chunk_size)
+            .map_or(0, |p| p.total_chunks())
+            .max(1);
+
+        let names: Vec<String> = (0..total)
+            .map(|i| self.checked_name(self.namer.name(i)))
+            .collect::<Result<_, _>>()?;
+
+        if self.case_insensitive
+            && naming::find_collision(names.iter().map(String::as_str))
+                .is_some()
+        {
+            return Err(SplitError::ChunkNameCollision);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "manifest")]
+    /// Build the manifest of the chunks written by the process.
+    pub(crate) fn to_manifest(
+        &self,
+        file_size: usize,
+        sizes: &[usize],
+        hashes: Option<&[String]>,
+    ) -> Manifest {
+        let mut metadata: BTreeMap<String, String> = self.metadata.clone();
+
+        metadata
+            .entry(RUN_ID_KEY.to_string())
+            .or_insert_with(|| self.run_id.to_string());
+
+        Manifest {
+            version: MANIFEST_VERSION,
+            file_size,
+            chunk_size: self.chunk_size,
+            total_chunks: sizes.len(),
+            chunks: sizes
+                .iter()
+                .enumerate()
+                .map(|(index, size)| ManifestChunk {
+                    index,
+                    name: self.namer.name(index),
+                    size: *size,
+                    sha256: match self.hash_algorithm.unwrap_or_default() {
+                        | HashAlgorithm::Sha256 => {
+                            hashes.and_then(|h| h.get(index).cloned())
+                        },
+                        #[cfg(feature = "blake3")]
+                        | _ => None,
+                    },
+                    blake3: match self.hash_algorithm.unwrap_or_default() {
+                        #[cfg(feature = "blake3")]
+                        | HashAlgorithm::Blake3 => {
+                            hashes.and_then(|h| h.get(index).cloned())
+                        },
+                        | _ => None,
+                    },
+                })
+                .collect(),
+            metadata,
+        }
+    }
+}
+
+/// The size and the modification time of the input file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InputStamp {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl InputStamp {
+    /// Create the stamp from the metadata of the input file.
+    pub(crate) fn new(metadata: &fs::Metadata) -> Self {
+        Self { size: metadata.len(), modified: metadata.modified().ok() }
+    }
+}
+
+/// Hash the whole file in hex.
+fn hash_file(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    buffer_capacity: usize,
+) -> io::Result<String> {
+    let mut input: File = File::open(path)?;
+    let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+    let mut hasher: Hasher = algorithm.hasher();
+
+    loop {
+        let n: usize = input.read(&mut buffer)?;
+
+        if n == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(hasher.finalize_hex())
+}
+
+/// Check whether the chunk at the path has the same content.
+///
+/// The sizes are compared first, so a changed chunk is usually detected
+/// without reading it.
+fn is_duplicate(
+    path: &Path,
+    mut content: &[u8],
+) -> bool {
+    let len: u64 = content.len() as u64;
+
+    fs::metadata(path).is_ok_and(|m| m.len() == len)
+        && same_content(path, &mut content, len).unwrap_or(false)
+}
+
+/// Check whether the chunk at the path starts with the next `len` bytes of
+/// the reader, comparing them a block at a time.
+fn same_content<R: Read>(
+    path: &Path,
+    reader: &mut R,
+    len: u64,
+) -> io::Result<bool> {
+    let mut chunk: File = File::open(path)?;
+
+    let mut chunk_buffer: Vec<u8> = vec![0; COMPARE_BUFFER_SIZE];
+    let mut reader_buffer: Vec<u8> = vec![0; COMPARE_BUFFER_SIZE];
+    let mut remaining: u64 = len;
+
+    while remaining > 0 {
+        let n: usize = remaining.min(COMPARE_BUFFER_SIZE as u64) as usize;
+
+        chunk.read_exact(&mut chunk_buffer[..n])?;
+        reader.read_exact(&mut reader_buffer[..n])?;
+
+        if chunk_buffer[..n] != reader_buffer[..n] {
+            return Ok(false);
+        }
+
+        remaining -= n as u64;
+    }
+
+    Ok(true)
 }
 
 impl Default for Split {
@@ -0,0 +1,395 @@
+use std::{
+    fs,
+    io::{self, BufWriter, Read as _, Write as _},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    throttle::Throttle,
+};
+
+/// Content pattern of a generated file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// Every byte is zero.
+    Zeros,
+    /// Pseudo-random bytes, the same seed always produces the same bytes.
+    Random { seed: u64 },
+    /// The block is repeated until the size is reached.
+    Repeat(Vec<u8>),
+}
+
+/// Generate a file with the given size and pattern.
+///
+/// The parent directories are created if they do not exist.
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use filego::test_util::{self, Pattern};
+///
+/// test_util::generate_file(
+///     PathBuf::from("path").join("to").join("file"),
+///     10 * 1024 * 1024,
+///     &Pattern::Random { seed: 42 },
+/// )
+/// .unwrap();
+/// ```
+pub fn generate_file<P: AsRef<Path>>(
+    path: P,
+    size: usize,
+    pattern: &Pattern,
+) -> io::Result<()> {
+    let path: &Path = path.as_ref();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut writer: BufWriter<fs::File> =
+        BufWriter::new(fs::File::create(path)?);
+
+    let mut buffer: Vec<u8> = vec![0; 64 * 1024];
+    let mut state: u64 = match pattern {
+        | Pattern::Random { seed } => *seed,
+        | _ => 0,
+    };
+    let mut written: usize = 0;
+
+    while written < size {
+        let len: usize = buffer.len().min(size - written);
+
+        match pattern {
+            | Pattern::Zeros => buffer[..len].fill(0),
+            | Pattern::Random { .. } => {
+                for chunk in buffer[..len].chunks_mut(8) {
+                    let bytes: [u8; 8] = splitmix64(&mut state).to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+            },
+            | Pattern::Repeat(block) => {
+                if block.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the repeated block is empty",
+                    ));
+                }
+
+                for (i, b) in buffer[..len].iter_mut().enumerate() {
+                    *b = block[(written + i) % block.len()];
+                }
+            },
+        }
+
+        writer.write_all(&buffer[..len])?;
+
+        written += len;
+    }
+
+    writer.flush()
+}
+
+/// Assert that the chunks in a directory merge into the given file.
+///
+/// The chunks are read in order from `0` until the next index is missing.
+///
+/// ## Panics
+///
+/// Panics when the content is different or the files could not be read.
+pub fn assert_file_eq_chunks<F: AsRef<Path>, D: AsRef<Path>>(
+    file: F,
+    chunk_dir: D,
+) {
+    let file: &Path = file.as_ref();
+    let chunk_dir: &Path = chunk_dir.as_ref();
+
+    let expected: Vec<u8> = match fs::read(file) {
+        | Ok(b) => b,
+        | Err(e) => panic!("could not read {}: {}", file.display(), e),
+    };
+
+    let mut offset: usize = 0;
+    let mut index: usize = 0;
+
+    loop {
+        let chunk: PathBuf = chunk_dir.join(index.to_string());
+
+        if !chunk.is_file() {
+            break;
+        }
+
+        let mut actual: Vec<u8> = Vec::new();
+
+        if let Err(e) =
+            fs::File::open(&chunk).and_then(|mut f| f.read_to_end(&mut actual))
+        {
+            panic!("could not read {}: {}", chunk.display(), e);
+        }
+
+        let end: usize = offset + actual.len();
+
+        assert!(
+            end <= expected.len() && expected[offset..end] == actual[..],
+            "chunk {} does not match the file at offset {}",
+            index,
+            offset
+        );
+
+        offset = end;
+        index += 1;
+    }
+
+    assert_eq!(
+        offset,
+        expected.len(),
+        "the chunks cover {} of {} bytes",
+        offset,
+        expected.len()
+    );
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+
+    let mut z: u64 = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+    z ^ (z >> 31)
+}
+
+/// Clock which only moves forward when told to.
+///
+/// Sleeping advances it instantly instead of blocking, so processes
+/// throttled with it never wait. Clones share the same time.
+///
+/// ## Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use filego::{clock::Clock as _, test_util::MockClock};
+///
+/// let clock: MockClock = MockClock::new();
+/// let started = clock.now();
+///
+/// clock.advance(Duration::from_secs(60));
+///
+/// assert_eq!(clock.now() - started, Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    instant: Instant,
+    system: SystemTime,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    /// Create a new clock starting at the current time.
+    pub fn new() -> Self {
+        Self {
+            instant: Instant::now(),
+            system: SystemTime::now(),
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(
+        &self,
+        duration: Duration,
+    ) {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.instant + self.elapsed()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        self.system + self.elapsed()
+    }
+
+    fn sleep(
+        &self,
+        duration: Duration,
+    ) {
+        self.advance(duration)
+    }
+}
+
+/// Reader or writer simulating a slow disk or network.
+///
+/// Each read and write waits for the latency first, and the bytes moved
+/// are throttled to the bandwidth, so progress reports, timeouts and
+/// retries can be exercised against slow storage without special hardware.
+/// It wraps synchronous readers and writers, e.g. the input of
+/// [`Split::run_reader`](crate::split::Split::run_reader) or the sink of an
+/// uploader.
+///
+/// ## Example
+///
+/// ```
+/// use std::{io::Read as _, time::Duration};
+///
+/// use filego::test_util::{MockClock, SlowIo};
+///
+/// let clock: MockClock = MockClock::new();
+///
+/// let mut reader: SlowIo<&[u8]> = SlowIo::new(&[0u8; 1024][..])
+///     .latency(Duration::from_millis(20))
+///     .bandwidth(1024)
+///     .clock(clock.clone());
+///
+/// let mut content: Vec<u8> = Vec::new();
+///
+/// reader.read_to_end(&mut content).unwrap();
+///
+/// assert_eq!(content.len(), 1024);
+/// ```
+#[derive(Debug)]
+pub struct SlowIo<T> {
+    inner: T,
+    latency: Duration,
+    rate: Option<u64>,
+    throttle: Option<Throttle>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<T> SlowIo<T> {
+    /// Wrap a reader or writer without any limit.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            latency: Duration::ZERO,
+            rate: None,
+            throttle: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Set the time waited before each read and write.
+    pub fn latency(
+        mut self,
+        latency: Duration,
+    ) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Set the maximum rate of IO in bytes per second.
+    ///
+    /// Like the throttle of the processes, bursts of up to one second of IO
+    /// are allowed.
+    pub fn bandwidth(
+        mut self,
+        rate: u64,
+    ) -> Self {
+        self.rate = Some(rate);
+        self.throttle = None;
+        self
+    }
+
+    /// Set the clock waiting for the latency and the bandwidth.
+    ///
+    /// By default, it is the [`SystemClock`], so the IO really blocks. With
+    /// a [`MockClock`], the time waited is added to the clock instead.
+    pub fn clock<C: Clock + 'static>(
+        mut self,
+        clock: C,
+    ) -> Self {
+        self.clock = Arc::new(clock);
+        self.throttle = None;
+        self
+    }
+
+    /// Get a reference to the wrapped reader or writer.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped reader or writer.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the reader or writer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Wait for the latency before an IO.
+    fn delay(&self) {
+        if !self.latency.is_zero() {
+            self.clock.sleep(self.latency);
+        }
+    }
+
+    /// Wait until the bytes moved fit in the bandwidth.
+    fn limit(
+        &mut self,
+        bytes: usize,
+    ) {
+        let rate: u64 = match self.rate {
+            | Some(rate) => rate,
+            | None => return,
+        };
+
+        let clock: &Arc<dyn Clock> = &self.clock;
+
+        self.throttle
+            .get_or_insert_with(|| Throttle::new(rate, clock.clone()))
+            .wait(bytes as u64);
+    }
+}
+
+impl<R: io::Read> io::Read for SlowIo<R> {
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        self.delay();
+
+        let n: usize = self.inner.read(buf)?;
+
+        self.limit(n);
+
+        Ok(n)
+    }
+}
+
+impl<W: io::Write> io::Write for SlowIo<W> {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> io::Result<usize> {
+        self.delay();
+
+        let n: usize = self.inner.write(buf)?;
+
+        self.limit(n);
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::clock::Clock;
+
+/// Token bucket limiting the rate of IO.
+///
+/// The bucket holds up to one second of bytes, so short bursts are allowed
+/// while the average rate never exceeds the limit.
+#[derive(Debug, Clone)]
+pub(crate) struct Throttle {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+    clock: Arc<dyn Clock>,
+}
+
+impl Throttle {
+    /// Create a new throttle with the rate in bytes per second.
+    pub(crate) fn new(
+        rate: u64,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let rate: f64 = rate.max(1) as f64;
+
+        Self { rate, tokens: rate, last: clock.now(), clock }
+    }
+
+    /// Take the bytes from the bucket.
+    ///
+    /// Returns how long to wait before the next IO, the time waited refills
+    /// the bucket on the next call.
+    pub(crate) fn take(
+        &mut self,
+        bytes: u64,
+    ) -> Duration {
+        let now: Instant = self.clock.now();
+        let elapsed: f64 = now.duration_since(self.last).as_secs_f64();
+
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.tokens -= bytes as f64;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    /// Take the bytes from the bucket and block while it is empty.
+    pub(crate) fn wait(
+        &mut self,
+        bytes: u64,
+    ) {
+        let delay: Duration = self.take(bytes);
+
+        self.clock.sleep(delay);
+    }
+}
@@ -1,171 +1,963 @@
 use std::{
-    fs::Metadata,
-    path::{Path, PathBuf},
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
 };
 
 use tokio::{
-    fs::{self, DirEntry, ReadDir},
+    fs::{self, ReadDir},
     io::{self, AsyncReadExt, AsyncWriteExt},
+    task, time,
 };
 
-use crate::merge::{Merge, MergeError};
+use crate::{
+    advise,
+    clock::SystemClock,
+    control::{Control, ControlGuard},
+    device,
+    durability::Durability,
+    follow::Follow,
+    hash::{self, Hasher, Sha256},
+    lock::{DirLock, LOCK_FILE_NAME},
+    merge::{Merge, MergeError, MergeResult, PreFinalize},
+    naming,
+    progress::Tracker,
+    reader::ChunkedReader,
+    sniff,
+    throttle::Throttle,
+};
 
 /// Trait for running the merge process.
 pub trait MergeAsyncExt {
     /// Run the check process asynchronously.
     fn run_async(
         &self
-    ) -> impl std::future::Future<Output = Result<bool, MergeError>> + Send;
+    ) -> impl std::future::Future<Output = Result<MergeResult, MergeError>> + Send;
 }
 
 impl MergeAsyncExt for Merge {
-    async fn run_async(&self) -> Result<bool, MergeError> {
-        let in_dir: &Path = match self.in_dir {
-            | Some(ref p) => {
-                let p: &Path = p.as_ref();
-
-                // if in_dir not exists
-                if !p.exists() {
-                    return Err(MergeError::InDirNotFound);
-                }
+    async fn run_async(&self) -> Result<MergeResult, MergeError> {
+        let merge_result: MergeResult = merge(self).await?;
 
-                // if in_dir not a directory
-                if !p.is_dir() {
-                    return Err(MergeError::InDirNotDir);
-                }
+        self.flush_sinks()?;
+
+        let durability: Durability = self.durability;
+        let out_file: PathBuf = merge_result.out_file.clone();
+
+        let synced: Result<(), (PathBuf, io::Error)> =
+            match task::spawn_blocking(move || durability.sync_file(&out_file))
+                .await
+            {
+                | Ok(synced) => synced,
+                | Err(e) => {
+                    Err((merge_result.out_file.clone(), io::Error::other(e)))
+                },
+            };
+
+        if let Err((path, e)) = synced {
+            return Err(MergeError::OutFileNotSynced {
+                path,
+                source: e.into(),
+            });
+        }
+
+        Ok(merge_result)
+    }
+}
+
+/// Merge the chunks into the output file.
+async fn merge(process: &Merge) -> Result<MergeResult, MergeError> {
+    let in_dir: &Path = match process.in_dir {
+        | Some(ref p) => {
+            let p: &Path = p.as_ref();
+
+            // if in_dir not exists
+            if !p.exists() {
+                return Err(MergeError::InDirNotFound);
+            }
+
+            // if in_dir not a directory
+            if !p.is_dir() {
+                return Err(MergeError::InDirNotDir);
+            }
+
+            p
+        },
+        | None => return Err(MergeError::InDirNotSet),
+    };
+
+    let out_file: &Path = match process.out_file {
+        | Some(ref p) => p.as_ref(),
+        | None => return Err(MergeError::OutFileNotSet),
+    };
+
+    // if the chunks cannot be verified
+    if process.require_verify
+        && process.verify.as_ref().is_none_or(|v| v.is_empty())
+    {
+        return Err(MergeError::VerifyNotSet);
+    }
+
+    // if out_file may escape its directory
+    if process.reject_unsafe_paths
+        && out_file.components().any(|c| c == Component::ParentDir)
+    {
+        return Err(MergeError::OutFileNotSafe);
+    }
+
+    // if out_file cannot be created on Windows
+    if process.portable_names
+        && !out_file
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(naming::is_portable)
+    {
+        return Err(MergeError::OutFileNameInvalid);
+    }
+
+    let _control: Option<ControlGuard> =
+        process.control.as_ref().map(Control::run);
+
+    let _lock: Option<DirLock> = if process.lock {
+        match DirLock::acquire(in_dir) {
+            | Ok(l) => Some(l),
+            | Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                return Err(MergeError::Locked);
+            },
+            | Err(e) => {
+                return Err(MergeError::LockNotCreated {
+                    path: in_dir.join(LOCK_FILE_NAME),
+                    source: e.into(),
+                });
+            },
+        }
+    } else {
+        None
+    };
+
+    // merge the chunks as they arrive
+    if let Some(ref f) = process.follow {
+        return merge_following(process, in_dir, out_file, f).await;
+    }
+
+    // get inputs
+    // only the indices are kept and the paths are derived from the
+    // namer, so millions of chunks do not hold as many paths in memory
+    let mut indices: Vec<usize> = Vec::new();
 
-                p
+    let mut read_dir: ReadDir = match fs::read_dir(in_dir).await {
+        | Ok(read_dir) => read_dir,
+        | Err(e) => {
+            return Err(MergeError::InDirNotRead {
+                path: in_dir.to_path_buf(),
+                source: e.into(),
+            });
+        },
+    };
+
+    while let Some(ref entry) =
+        read_dir.next_entry().await.map_err(|e| MergeError::InDirNotRead {
+            path: in_dir.to_path_buf(),
+            source: e.into(),
+        })?
+    {
+        // if entry is a symlink that may point outside in_dir
+        if process.reject_unsafe_paths
+            && entry.file_type().await.is_ok_and(|t| t.is_symlink())
+        {
+            return Err(MergeError::InFileNotSafe);
+        }
+
+        let path: PathBuf = entry.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let name: &str = match path.file_name().and_then(|n| n.to_str()) {
+            | Some(name) => name,
+            | None if process.reject_invalid_names => {
+                return Err(MergeError::InFileNameInvalid);
             },
-            | None => return Err(MergeError::InDirNotSet),
+            | None => continue,
         };
 
-        let out_file: &Path = match self.out_file {
-            | Some(ref p) => p.as_ref(),
-            | None => return Err(MergeError::OutFileNotSet),
+        if let Some(index) = process.namer.parse(name) {
+            indices.push(index);
+        }
+    }
+
+    indices.sort_unstable();
+
+    if let Some(w) = indices.windows(2).find(|w| w[0] == w[1]) {
+        return Err(MergeError::InDirDuplicateIndex { index: w[0] });
+    }
+
+    if process.max_chunks.is_some_and(|max| indices.len() > max) {
+        return Err(MergeError::TooManyChunks);
+    }
+
+    if process.contiguous
+        && indices.iter().enumerate().any(|(i, index)| i != *index)
+    {
+        return Err(MergeError::InDirNotContiguous);
+    }
+
+    // the names of the chunks may depend on their content
+    super::scan(&process.namer, in_dir).await;
+
+    // the chunks may vary in size, so the largest one sets the capacity
+    let mut sizes: Vec<u64> = Vec::with_capacity(indices.len());
+
+    for index in indices.iter() {
+        let file: PathBuf = in_dir.join(process.namer.name(*index));
+
+        match fs::metadata(&file).await {
+            | Ok(metadata) => sizes.push(metadata.len()),
+            | Err(e) => {
+                return Err(MergeError::InFileNotRead {
+                    path: file,
+                    source: e.into(),
+                });
+            },
+        }
+    }
+
+    let input_size: usize = match sizes.iter().max() {
+        | Some(size) => *size as usize,
+        | None => return Err(MergeError::InDirNoFile),
+    };
+
+    let buffer_capacity: usize = input_size.min(process.cap_max);
+
+    // total size for progress
+    let mut tracker: Option<Tracker> =
+        process.on_progress.is_some().then(|| {
+            Tracker::new(
+                indices.len(),
+                sizes.iter().sum(),
+                process.run_id,
+                process.clock.clone(),
+            )
+        });
+
+    // sniff the content type from the first chunks
+    if let Some(ref allowed) = process.allowed_types {
+        let mut head: Vec<u8> = Vec::with_capacity(sniff::SNIFF_LEN);
+
+        for index in indices.iter() {
+            if head.len() >= sniff::SNIFF_LEN {
+                break;
+            }
+
+            let entry: PathBuf = in_dir.join(process.namer.name(*index));
+
+            let input: fs::File = match fs::File::open(&entry).await {
+                | Ok(file) => file,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry.clone(),
+                        source: e.into(),
+                    });
+                },
+            };
+
+            if let Err(e) = input
+                .take((sniff::SNIFF_LEN - head.len()) as u64)
+                .read_to_end(&mut head)
+                .await
+            {
+                return Err(MergeError::InFileNotRead {
+                    path: entry.clone(),
+                    source: e.into(),
+                });
+            }
+        }
+
+        let content_type: &str = sniff::sniff(&head);
+
+        if !allowed.iter().any(|a| sniff::matches(content_type, a)) {
+            return Err(MergeError::ContentRejected);
+        }
+    }
+
+    // let the hook inspect the content before it is written
+    if let Some(ref hook) = process.pre_finalize {
+        let hook: PreFinalize = hook.clone();
+        let chunks: Vec<PathBuf> = indices
+            .iter()
+            .map(|i| in_dir.join(process.namer.name(*i)))
+            .collect();
+
+        let accepted: io::Result<bool> = match task::spawn_blocking(move || {
+            let mut reader: ChunkedReader = ChunkedReader::open(chunks)?;
+
+            Ok(hook.call(&mut reader))
+        })
+        .await
+        {
+            | Ok(accepted) => accepted,
+            | Err(e) => Err(io::Error::other(e)),
+        };
+
+        let accepted: bool = match accepted {
+            | Ok(accepted) => accepted,
+            | Err(e) => {
+                return Err(MergeError::InFileNotRead {
+                    path: in_dir.to_path_buf(),
+                    source: e.into(),
+                });
+            },
         };
 
-        // check file size for buffer capacity
-        let input_size: usize = {
-            let mut read_dir: ReadDir = match fs::read_dir(in_dir).await {
-                | Ok(read_dir) => read_dir,
-                | Err(_) => return Err(MergeError::InDirNotRead),
+        if !accepted {
+            return Err(MergeError::ContentRejected);
+        }
+    }
+
+    // catch gross corruption before the output is touched
+    if let Some(count) = process.verify_sample {
+        let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+
+        for index in process.sample(&indices, count) {
+            let expected: &[u8; 32] = match process.expected_hash(index) {
+                | Some(e) => e,
+                | None => continue,
+            };
+
+            let entry: PathBuf = in_dir.join(process.namer.name(index));
+
+            let mut input: fs::File = match fs::File::open(&entry).await {
+                | Ok(f) => f,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
             };
 
-            let dir_entry: DirEntry = match read_dir.next_entry().await {
-                | Ok(Some(entry)) => entry,
-                | Ok(None) => return Err(MergeError::InDirNoFile),
-                | Err(_) => return Err(MergeError::InDirNotRead),
+            // streamed, so a huge chunk is not held in memory
+            let mut chunk_hasher: Hasher =
+                process.verify_algorithm_of(index).hasher();
+
+            loop {
+                let read: usize = match input.read(&mut buffer).await {
+                    | Ok(0) => break,
+                    | Ok(n) => n,
+                    | Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    | Err(e) => {
+                        return Err(MergeError::InFileNotRead {
+                            path: entry,
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                chunk_hasher.update(&buffer[..read]);
+            }
+
+            if chunk_hasher.finalize() != *expected {
+                return Err(MergeError::ChunkCorrupted { index });
+            }
+        }
+    }
+
+    // keep the output if it is already merged
+    if process.skip_merged {
+        if let Some(r) = merged_result(
+            process,
+            in_dir,
+            out_file,
+            &indices,
+            &sizes,
+            buffer_capacity,
+        )
+        .await?
+        {
+            return Ok(r);
+        }
+    }
+
+    // keep or refuse an existing output
+    if let Some(r) = process.existing_output(out_file, indices.len())? {
+        return Ok(r);
+    }
+
+    process.ensure_space(out_file, sizes.iter().sum())?;
+
+    // replace an existing output file
+    if out_file.exists() {
+        if let Err(e) = fs::remove_file(&out_file).await {
+            return Err(MergeError::OutFileNotRemoved {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        }
+    }
+
+    // create outpath
+    if let Some(parent) = out_file.parent() {
+        if let Err(e) = fs::create_dir_all(parent).await {
+            return Err(MergeError::OutDirNotCreated {
+                path: parent.to_path_buf(),
+                source: e.into(),
+            });
+        }
+    }
+
+    // move the only chunk into place instead of copying it
+    let first: PathBuf = in_dir.join(process.namer.name(indices[0]));
+
+    if process.passthrough
+        && !process.hash
+        && process.verify.is_none()
+        && process.sinks.is_empty()
+        && indices.len() == 1
+        && process.max_file_size.is_none_or(|max| input_size as u64 <= max)
+        && device::same_device(&first, out_file)
+        && fs::rename(&first, out_file).await.is_ok()
+    {
+        if let Some(ref c) = process.control {
+            c.record(indices[0], input_size as u64);
+        }
+
+        if let (Some(t), Some(cb)) = (tracker.as_mut(), &process.on_progress) {
+            cb.call(t.advance(input_size as u64));
+        }
+
+        return Ok(MergeResult {
+            file_size: input_size,
+            total_chunks: 1,
+            sha256: None,
+            out_file: out_file.to_path_buf(),
+            skipped: false,
+        });
+    }
+
+    let output: fs::File = match fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(out_file)
+        .await
+    {
+        | Ok(file) => file,
+        | Err(e) => {
+            return Err(MergeError::OutFileNotOpened {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        },
+    };
+
+    // reserve the whole output before writing it
+    if process.preallocate {
+        if let Err(e) = device::preallocate(&output, sizes.iter().sum()) {
+            drop(output);
+            let _ = fs::remove_file(out_file).await;
+
+            return Err(MergeError::OutFileNotWritten {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        }
+    }
+
+    // writer
+    let mut writer: io::BufWriter<fs::File> =
+        io::BufWriter::with_capacity(buffer_capacity, output);
+
+    let total_chunks: usize = indices.len();
+    let mut merged: u64 = 0;
+    let mut hasher: Option<Sha256> = process.hash.then(Sha256::new);
+    let mut throttle: Option<Throttle> =
+        process.throttle.map(|r| Throttle::new(r, Arc::new(SystemClock)));
+
+    let copy: bool = process.copies();
+
+    // reused by every chunk, so a merge allocates it once
+    let mut buffer: Vec<u8> =
+        if copy { Vec::new() } else { vec![0; buffer_capacity] };
+
+    // merge
+    for index in indices {
+        let entry: PathBuf = in_dir.join(process.namer.name(index));
+
+        if let Some(ref c) = process.control {
+            if !c.wait_async().await {
+                return Err(MergeError::Cancelled);
+            }
+        }
+
+        let mut input: fs::File =
+            match fs::OpenOptions::new().read(true).open(&entry).await {
+                | Ok(file) => file,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
             };
 
-            let metadata: Metadata = match fs::metadata(dir_entry.path()).await
+        if process.cache_hints {
+            advise::sequential(&input);
+        }
+
+        // let the standard library copy the chunk, e.g. within the kernel
+        if copy {
+            // the copy does not tell a failed read from a failed write
+            let chunk_bytes: u64 = match io::copy(&mut input, &mut writer).await
             {
-                | Ok(meta) => meta,
-                | Err(_) => return Err(MergeError::InDirNotRead),
+                | Ok(n) => n,
+                | Err(e) => {
+                    return Err(MergeError::OutFileNotWritten {
+                        path: out_file.to_path_buf(),
+                        source: e.into(),
+                    });
+                },
             };
 
-            if metadata.is_file() {
-                metadata.len() as usize
-            } else {
-                return Err(MergeError::InDirNoFile);
+            merged += chunk_bytes;
+
+            if process.cache_hints {
+                advise::dont_need(&input);
             }
-        };
 
-        let buffer_capacity: usize = input_size.min(self.cap_max);
+            if let (Some(t), Some(cb)) =
+                (tracker.as_mut(), &process.on_progress)
+            {
+                cb.call(t.advance(chunk_bytes));
+            }
+
+            continue;
+        }
 
-        // delete outpath target if exists
-        if out_file.exists() {
-            if out_file.is_dir() {
-                if fs::remove_dir_all(&out_file).await.is_err() {
-                    return Err(MergeError::OutFileNotRemoved);
+        let mut chunk_bytes: u64 = 0;
+        let mut chunk_hasher: Option<Hasher> = process
+            .expected_hash(index)
+            .filter(|_| process.verify_sample.is_none())
+            .map(|_| process.verify_algorithm_of(index).hasher());
+
+        loop {
+            // a large chunk is slow to merge, so checked for each buffer
+            if process.control.as_ref().is_some_and(Control::is_cancelled) {
+                return Err(MergeError::Cancelled);
+            }
+
+            let mut read: usize = 0;
+
+            // a short read ends the fill unless it is the end of the chunk
+            while read < buffer.len() {
+                let n: usize = match input.read(&mut buffer[read..]).await {
+                    | Ok(n) => n,
+                    | Err(e) => {
+                        return Err(MergeError::InFileNotRead {
+                            path: entry,
+                            source: e.into(),
+                        });
+                    },
+                };
+
+                read += n;
+
+                if n == 0 || !process.fill_buffer {
+                    break;
                 }
-            } else if fs::remove_file(&out_file).await.is_err() {
-                return Err(MergeError::OutFileNotRemoved);
             }
-        }
 
-        // create outpath
-        if let Some(parent) = out_file.parent() {
-            if fs::create_dir_all(parent).await.is_err() {
-                return Err(MergeError::OutDirNotCreated);
+            if read == 0 {
+                break;
             }
-        }
 
-        let output: fs::File = match fs::OpenOptions::new()
-            .create(true)
-            .truncate(false)
-            .write(true)
-            .open(out_file)
-            .await
-        {
-            | Ok(file) => file,
-            | Err(_) => return Err(MergeError::OutFileNotOpened),
-        };
+            merged += read as u64;
 
-        // writer
-        let mut writer: io::BufWriter<fs::File> =
-            io::BufWriter::with_capacity(buffer_capacity, output);
+            // stop before the output exceeds the limit
+            if process.max_file_size.is_some_and(|max| merged > max) {
+                drop(writer);
+                let _ = fs::remove_file(out_file).await;
 
-        // get inputs
-        let mut entries: Vec<PathBuf> = Vec::new();
+                return Err(MergeError::FileTooLarge);
+            }
 
-        let mut read_dir: ReadDir = match fs::read_dir(in_dir).await {
-            | Ok(read_dir) => read_dir,
-            | Err(_) => return Err(MergeError::InDirNotRead),
-        };
+            process.write_sinks(&buffer[..read])?;
+
+            if let Err(e) = writer.write_all(&buffer[..read]).await {
+                return Err(MergeError::OutFileNotWritten {
+                    path: out_file.to_path_buf(),
+                    source: e.into(),
+                });
+            }
+
+            if let Some(ref mut h) = hasher {
+                h.update(&buffer[..read]);
+            }
+
+            if let Some(ref mut h) = chunk_hasher {
+                h.update(&buffer[..read]);
+            }
+
+            chunk_bytes += read as u64;
+
+            if let Some(ref mut t) = throttle {
+                time::sleep(t.take(read as u64)).await;
+            }
+        }
 
-        while let Some(ref entry) =
-            read_dir.next_entry().await.map_err(|_| MergeError::InDirNotRead)?
+        if process.cache_hints {
+            advise::dont_need(&input);
+        }
+
+        // stop before the corrupted chunk is used
+        if let (Some(h), Some(expected)) =
+            (chunk_hasher, process.expected_hash(index))
         {
-            let path: PathBuf = entry.path();
+            if h.finalize() != *expected {
+                drop(writer);
+                let _ = fs::remove_file(out_file).await;
 
-            if path.is_file() {
-                entries.push(path);
+                return Err(MergeError::ChunkCorrupted { index });
             }
         }
 
-        entries.sort_by_key(|entry| {
-            entry
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .parse::<usize>()
-                .unwrap()
+        if let Some(ref c) = process.control {
+            c.record(index, chunk_bytes);
+        }
+
+        if let (Some(t), Some(cb)) = (tracker.as_mut(), &process.on_progress) {
+            cb.call(t.advance(chunk_bytes));
+        }
+    }
+
+    if let Err(e) = writer.flush().await {
+        return Err(MergeError::OutFileNotWritten {
+            path: out_file.to_path_buf(),
+            source: e.into(),
         });
+    }
 
-        // merge
-        for entry in entries {
-            let input: fs::File =
-                match fs::OpenOptions::new().read(true).open(&entry).await {
-                    | Ok(file) => file,
-                    | Err(_) => return Err(MergeError::InFileNotOpened),
-                };
+    if process.cache_hints {
+        advise::dont_need(writer.get_ref());
+    }
 
-            let mut reader: io::BufReader<fs::File> =
-                io::BufReader::with_capacity(buffer_capacity, input);
+    Ok(MergeResult {
+        file_size: merged as usize,
+        total_chunks,
+        sha256: hasher.map(|h| hash::to_hex(&h.finalize())),
+        out_file: out_file.to_path_buf(),
+        skipped: false,
+    })
+}
 
-            let mut buffer: Vec<u8> = vec![0; buffer_capacity];
+/// Get the result of the merge if the output file is already equal the
+/// chunks.
+async fn merged_result(
+    process: &Merge,
+    in_dir: &Path,
+    out_file: &Path,
+    indices: &[usize],
+    sizes: &[u64],
+    buffer_capacity: usize,
+) -> Result<Option<MergeResult>, MergeError> {
+    let file_size: u64 = sizes.iter().sum();
 
-            loop {
-                let read: usize = match reader.read(&mut buffer).await {
-                    | Ok(read) => read,
-                    | Err(_) => return Err(MergeError::InFileNotRead),
-                };
+    if !fs::metadata(out_file)
+        .await
+        .is_ok_and(|m| m.is_file() && m.len() == file_size)
+    {
+        return Ok(None);
+    }
 
-                if read == 0 {
-                    break;
+    // merged again if the output cannot be read
+    let mut output: fs::File = match fs::File::open(out_file).await {
+        | Ok(file) => file,
+        | Err(_) => return Ok(None),
+    };
+
+    let mut output_buffer: Vec<u8> = vec![0; buffer_capacity];
+    let mut chunk_buffer: Vec<u8> = vec![0; buffer_capacity];
+    let mut hasher: Option<Sha256> = process.hash.then(Sha256::new);
+
+    for (index, size) in indices.iter().zip(sizes) {
+        if let Some(ref c) = process.control {
+            if !c.wait_async().await {
+                return Err(MergeError::Cancelled);
+            }
+        }
+
+        let entry: PathBuf = in_dir.join(process.namer.name(*index));
+
+        let expected: Option<&[u8; 32]> = process.expected_hash(*index);
+        let mut chunk_hasher: Option<Hasher> =
+            expected.map(|_| process.verify_algorithm_of(*index).hasher());
+
+        let mut input: Option<fs::File> = match chunk_hasher {
+            | Some(_) => None,
+            | None => match fs::File::open(&entry).await {
+                | Ok(file) => Some(file),
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
+            },
+        };
+
+        let mut remaining: usize = *size as usize;
+
+        while remaining > 0 {
+            let n: usize = remaining.min(buffer_capacity);
+
+            if output.read_exact(&mut output_buffer[..n]).await.is_err() {
+                return Ok(None);
+            }
+
+            if let Some(ref mut h) = hasher {
+                h.update(&output_buffer[..n]);
+            }
+
+            if let Some(ref mut h) = chunk_hasher {
+                h.update(&output_buffer[..n]);
+            }
+
+            if let Some(ref mut f) = input {
+                if let Err(e) = f.read_exact(&mut chunk_buffer[..n]).await {
+                    return Err(MergeError::InFileNotRead {
+                        path: entry,
+                        source: e.into(),
+                    });
                 }
 
-                if writer.write(&buffer[..read]).await.is_err() {
-                    return Err(MergeError::OutFileNotWritten);
+                if output_buffer[..n] != chunk_buffer[..n] {
+                    return Ok(None);
                 }
             }
+
+            remaining -= n;
         }
 
-        if writer.flush().await.is_err() {
-            return Err(MergeError::OutFileNotWritten);
+        if let (Some(h), Some(e)) = (chunk_hasher, expected) {
+            if h.finalize() != *e {
+                return Ok(None);
+            }
         }
+    }
+
+    Ok(Some(MergeResult {
+        file_size: file_size as usize,
+        total_chunks: indices.len(),
+        sha256: hasher.map(|h| hash::to_hex(&h.finalize())),
+        out_file: out_file.to_path_buf(),
+        skipped: true,
+    }))
+}
 
-        Ok(true)
+/// Merge the chunks in order as they arrive, until the input is ended.
+///
+/// A chunk is merged once the next one exists or the input is ended, as it
+/// may still be written before.
+async fn merge_following(
+    process: &Merge,
+    in_dir: &Path,
+    out_file: &Path,
+    follow: &Follow,
+) -> Result<MergeResult, MergeError> {
+    // keep or refuse an existing output
+    if let Some(r) = process.existing_output(out_file, 0)? {
+        return Ok(r);
     }
+
+    // replace an existing output file
+    if out_file.exists() {
+        if let Err(e) = fs::remove_file(out_file).await {
+            return Err(MergeError::OutFileNotRemoved {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        }
+    }
+
+    // create outpath
+    if let Some(parent) = out_file.parent() {
+        if let Err(e) = fs::create_dir_all(parent).await {
+            return Err(MergeError::OutDirNotCreated {
+                path: parent.to_path_buf(),
+                source: e.into(),
+            });
+        }
+    }
+
+    let mut output: fs::File = match fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(out_file)
+        .await
+    {
+        | Ok(file) => file,
+        | Err(e) => {
+            return Err(MergeError::OutFileNotOpened {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        },
+    };
+
+    let mut index: usize = 0;
+    let mut merged: u64 = 0;
+    let mut hasher: Option<Sha256> = process.hash.then(Sha256::new);
+    let mut throttle: Option<Throttle> =
+        process.throttle.map(|r| Throttle::new(r, Arc::new(SystemClock)));
+
+    loop {
+        if let Some(ref c) = process.control {
+            if !c.wait_async().await {
+                return Err(MergeError::Cancelled);
+            }
+        }
+
+        // checked before the chunks, so no chunk written before the end is
+        // missed
+        let ended: bool = follow.is_ended();
+
+        // the names of the chunks may depend on their content
+        super::scan(&process.namer, in_dir).await;
+
+        let entry: PathBuf = in_dir.join(process.namer.name(index));
+
+        let complete: bool = entry.is_file()
+            && (ended || in_dir.join(process.namer.name(index + 1)).exists());
+
+        if !complete {
+            if ended {
+                break;
+            }
+
+            time::sleep(follow.interval).await;
+
+            continue;
+        }
+
+        if process.max_chunks.is_some_and(|max| index >= max) {
+            return Err(MergeError::TooManyChunks);
+        }
+
+        // if entry is a symlink that may point outside in_dir
+        if process.reject_unsafe_paths
+            && entry.symlink_metadata().is_ok_and(|m| m.is_symlink())
+        {
+            return Err(MergeError::InFileNotSafe);
+        }
+
+        let mut input: fs::File =
+            match fs::OpenOptions::new().read(true).open(&entry).await {
+                | Ok(file) => file,
+                | Err(e) => {
+                    return Err(MergeError::InFileNotOpened {
+                        path: entry,
+                        source: e.into(),
+                    });
+                },
+            };
+
+        merged += match input.metadata().await {
+            | Ok(metadata) => metadata.len(),
+            | Err(e) => {
+                return Err(MergeError::InFileNotRead {
+                    path: entry,
+                    source: e.into(),
+                });
+            },
+        };
+
+        // stop before the output exceeds the limit
+        if process.max_file_size.is_some_and(|max| merged > max) {
+            drop(output);
+            let _ = fs::remove_file(out_file).await;
+
+            return Err(MergeError::FileTooLarge);
+        }
+
+        let expected: Option<&[u8; 32]> = process.expected_hash(index);
+
+        let written: u64 = if hasher.is_some()
+            || expected.is_some()
+            || !process.sinks.is_empty()
+        {
+            let mut content: Vec<u8> = Vec::new();
+
+            if let Err(e) = input.read_to_end(&mut content).await {
+                return Err(MergeError::InFileNotRead {
+                    path: entry,
+                    source: e.into(),
+                });
+            }
+
+            // stop before the corrupted chunk is used
+            if expected.is_some_and(|e| {
+                process.verify_algorithm_of(index).digest(&content) != *e
+            }) {
+                drop(output);
+                let _ = fs::remove_file(out_file).await;
+
+                return Err(MergeError::ChunkCorrupted { index });
+            }
+
+            if let Some(ref mut h) = hasher {
+                h.update(&content);
+            }
+
+            if let Err(e) = output.write_all(&content).await {
+                return Err(MergeError::OutFileNotWritten {
+                    path: out_file.to_path_buf(),
+                    source: e.into(),
+                });
+            }
+
+            process.write_sinks(&content)?;
+
+            content.len() as u64
+        } else {
+            match io::copy(&mut input, &mut output).await {
+                | Ok(written) => written,
+                | Err(e) => {
+                    return Err(MergeError::OutFileNotWritten {
+                        path: out_file.to_path_buf(),
+                        source: e.into(),
+                    });
+                },
+            }
+        };
+
+        if let Err(e) = output.flush().await {
+            return Err(MergeError::OutFileNotWritten {
+                path: out_file.to_path_buf(),
+                source: e.into(),
+            });
+        }
+
+        if let Some(ref c) = process.control {
+            c.record(index, written);
+        }
+
+        if let Some(ref mut t) = throttle {
+            time::sleep(t.take(written)).await;
+        }
+
+        index += 1;
+    }
+
+    if index == 0 {
+        return Err(MergeError::InDirNoFile);
+    }
+
+    Ok(MergeResult {
+        file_size: merged as usize,
+        total_chunks: index,
+        sha256: hasher.map(|h| hash::to_hex(&h.finalize())),
+        out_file: out_file.to_path_buf(),
+        skipped: false,
+    })
 }
@@ -0,0 +1,99 @@
+use std::io::{self, ErrorKind, IoSlice, Write};
+
+/// Writer gathering the read buffers into a single vectored write.
+///
+/// Each buffer is filled by a read, and the buffers are written at once
+/// when the last of them is filled, saving a system call per read. Like a
+/// `BufWriter`, the pending buffers are written when it is dropped.
+#[derive(Debug)]
+pub(crate) struct VectoredWriter<W: Write> {
+    inner: W,
+    buffers: Vec<Vec<u8>>,
+    lens: Vec<usize>,
+}
+
+impl<W: Write> VectoredWriter<W> {
+    /// Create a new writer with `count` buffers of `capacity` bytes.
+    pub(crate) fn new(
+        inner: W,
+        count: usize,
+        capacity: usize,
+    ) -> Self {
+        let count: usize = count.max(1);
+
+        Self {
+            inner,
+            buffers: vec![vec![0; capacity]; count],
+            lens: Vec::with_capacity(count),
+        }
+    }
+
+    /// Get the next buffer to read into.
+    pub(crate) fn buffer(&mut self) -> &mut [u8] {
+        &mut self.buffers[self.lens.len()]
+    }
+
+    /// Get the inner writer, e.g. to copy into it.
+    ///
+    /// The pending buffers are not written, so it must only be used while
+    /// no buffer is pending.
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Keep the first `len` bytes of the buffer, and write the buffers once
+    /// all of them are filled.
+    pub(crate) fn commit(
+        &mut self,
+        len: usize,
+    ) -> io::Result<()> {
+        self.lens.push(len);
+
+        if self.lens.len() == self.buffers.len() {
+            self.write_pending()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the pending buffers and flush the inner writer.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        self.inner.flush()
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        // the buffers are released even if the write fails
+        let mut lens: Vec<usize> = std::mem::take(&mut self.lens);
+
+        let mut slices: Vec<IoSlice<'_>> = self
+            .buffers
+            .iter()
+            .zip(lens.iter())
+            .filter(|(_, len)| **len > 0)
+            .map(|(buffer, len)| IoSlice::new(&buffer[..*len]))
+            .collect();
+
+        let mut pending: &mut [IoSlice<'_>] = &mut slices;
+
+        while !pending.is_empty() {
+            match self.inner.write_vectored(pending) {
+                | Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                | Ok(n) => IoSlice::advance_slices(&mut pending, n),
+                | Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                | Err(e) => return Err(e),
+            }
+        }
+
+        lens.clear();
+        self.lens = lens;
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for VectoredWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.write_pending();
+    }
+}
@@ -1,15 +1,24 @@
 #[cfg(test)]
 mod tests {
-    use std::env;
+    use std::{env, time::Duration};
 
-    use async_std::{fs, path::PathBuf, stream::StreamExt as _};
+    use async_std::{
+        fs,
+        path::PathBuf,
+        stream::StreamExt as _,
+        task::{self, JoinHandle},
+    };
 
     use filego::{
         check::{
             Check, CheckResult, CheckResultErrorType,
             async_std::CheckAsyncExt as _,
         },
-        merge::{Merge, async_std::MergeAsyncExt as _},
+        follow::Follow,
+        hash::HashAlgorithm,
+        merge::{
+            Merge, MergeError, MergeResult, async_std::MergeAsyncExt as _,
+        },
         split::{Split, SplitResult, async_std::SplitAsyncExt as _},
     };
 
@@ -50,7 +59,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_split_file_creates_chunks() {
-        let (_, cache_dir, _, _) = setup("split_file_creates_chunks").await;
+        let (_, cache_dir, _, split_result) =
+            setup("split_file_creates_chunks").await;
 
         let chunk_count: i32 = fs::read_dir(&cache_dir)
             .await
@@ -60,6 +70,13 @@ mod tests {
             .await;
 
         assert!(chunk_count > 0, "No chunks were created.");
+
+        assert_eq!(split_result.chunk_paths.len(), split_result.total_chunks);
+        assert_eq!(
+            split_result.chunk_sizes.iter().sum::<usize>(),
+            split_result.file_size
+        );
+        assert!(split_result.chunk_paths.iter().all(|p| p.is_file()));
     }
 
     #[tokio::test]
@@ -120,10 +137,7 @@ mod tests {
             .await
             .unwrap();
 
-        assert!(
-            check_result.success == true,
-            "Check should succeed with no errors."
-        );
+        assert!(check_result.success, "Check should succeed with no errors.");
     }
 
     #[tokio::test]
@@ -172,4 +186,90 @@ mod tests {
             "Merge should fail with an empty cache directory."
         );
     }
+
+    #[tokio::test]
+    async fn test_split_with_hashes() {
+        let root: PathBuf = env::current_dir().unwrap().into();
+
+        let split: Split = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .chunk_size(1024 * 1024)
+            .hash(true)
+            .hash_workers(2)
+            .hash_algorithm(HashAlgorithm::Sha256);
+
+        let expected: SplitResult = Split::from(split.clone())
+            .out_dir(
+                root.join(".media")
+                    .join("cache")
+                    .join("async_std")
+                    .join("hashes_sync"),
+            )
+            .run()
+            .unwrap();
+
+        let split_result: SplitResult = split
+            .out_dir(
+                root.join(".media")
+                    .join("cache")
+                    .join("async_std")
+                    .join("hashes"),
+            )
+            .run_async()
+            .await
+            .unwrap();
+
+        assert_eq!(split_result.total_chunks, expected.total_chunks);
+        assert_eq!(split_result.hashes, expected.hashes);
+        assert_eq!(split_result.file_hash, expected.file_hash);
+        assert!(split_result.file_hash.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_merge_follows_arriving_chunks() {
+        let root: PathBuf = env::current_dir().unwrap().into();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("async_std").join("live");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("async_std")
+            .join("live")
+            .join("live.bin");
+
+        let _ = fs::remove_dir_all(&cache_dir).await;
+        fs::create_dir_all(&cache_dir).await.unwrap();
+
+        let follow: Follow = Follow::new().interval(Duration::from_millis(5));
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .follow(&follow);
+
+        let handle: JoinHandle<Result<MergeResult, MergeError>> =
+            task::spawn(async move { merge.run_async().await });
+
+        for i in 0..3u8 {
+            task::sleep(Duration::from_millis(10)).await;
+
+            fs::write(cache_dir.join(i.to_string()), [i; 10]).await.unwrap();
+        }
+
+        follow.end();
+
+        let merge_result: MergeResult = handle.await.unwrap();
+
+        assert_eq!(merge_result.file_size, 30);
+        assert_eq!(merge_result.total_chunks, 3);
+
+        let mut expected: Vec<u8> = Vec::new();
+
+        for i in 0..3u8 {
+            expected.extend([i; 10]);
+        }
+
+        assert_eq!(fs::read(&output_path).await.unwrap(), expected);
+    }
 }
@@ -4,14 +4,89 @@ pub mod tokio;
 
 #[cfg(test)]
 mod tests {
-    use std::{env, fs, path::PathBuf};
+    use std::{
+        collections::{BTreeMap, HashMap},
+        env,
+        error::Error,
+        fs,
+        io::{ErrorKind, Read as _, Seek as _, SeekFrom, Write as _},
+        path::PathBuf,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        thread,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    };
 
     use filego::{
-        check::{Check, CheckResult, CheckResultErrorType},
-        merge::Merge,
-        split::{Split, SplitResult},
+        BUFFER_CAPACITY_MAX_DEFAULT, MAX_FILE_SIZE_UNTRUSTED_DEFAULT,
+        check::{
+            Check, CheckError, CheckResult, CheckResultError,
+            CheckResultErrorType,
+        },
+        chunk::ChunkInfo,
+        cleanup,
+        clock::Clock as _,
+        conformance::{
+            Conformance, ConformanceResult, ConformanceResultErrorType,
+            TestVector, TestVectorChunk,
+        },
+        control::{Control, Partial, SignalGuard, Status},
+        durability::Durability,
+        follow::Follow,
+        hash::HashAlgorithm,
+        lock::DirLock,
+        manifest::{
+            MANIFEST_FILE_NAME, Manifest, ManifestChunk, ManifestDelta,
+        },
+        memory,
+        merge::{IfExists, Merge, MergeError, MergeResult},
+        migrate::{Migrate, MigrateError, MigrateResult},
+        naming::{
+            self, ChunkNamer, Gnu, Padded, Prefixed, Template, TemplateError,
+        },
+        pipeline::{Chunk, ChunkReceiver, Pipeline, PipelineError},
+        plan::{self, ChunkPlan, MergePlan},
+        priority::{IoClass, Priority},
+        progress::Progress,
+        reader::ChunkedReader,
+        rechunk::{Rechunk, RechunkError},
+        run_id::RunId,
+        sniff,
+        split::{IfNotEmpty, ResumeCheck, Split, SplitError, SplitResult},
+        test_util::{self, MockClock, Pattern, SlowIo},
     };
 
+    fn test_vector() -> TestVector {
+        let chunk_size: usize = 1024 * 1024;
+        let file_size: usize = 8218228;
+
+        let hashes: [&str; 8] = [
+            "114b5074a9e860d1e69700533c785e5c20a12d9deee6c72fcb904c52a431f618",
+            "9a770c8f022085448702eac2d4817a27b35e3f7010115ff8944386e99ab4c9d3",
+            "b89a6661a16bcdbcbfe980ae59e022947fb9860be3162b374cbbd60c83bd75b6",
+            "d93ebbb8ba6dcc820ecbd49ff3717f253930165f56a36042270a0bb5d8baace2",
+            "ad1a0c5ba75836874667cfb6340723c5c787b50553d7840836cf39878645289c",
+            "d7c9626c1c8493bc3c4086a4c4f77aa7093d460c23db543b98ddc6c6a5a8496f",
+            "47b65cbfd8f588f55704f7d847d5ef7a02a56c864a37d080c3d46f5569599cec",
+            "702359a3039d283bd71b78facc3cc658dfd0b005f2f604a036159da21ef6a109",
+        ];
+
+        TestVector {
+            file_size,
+            chunk_size,
+            chunks: hashes
+                .iter()
+                .enumerate()
+                .map(|(i, h)| TestVectorChunk {
+                    size: chunk_size.min(file_size - i * chunk_size),
+                    sha256: h.to_string(),
+                })
+                .collect(),
+        }
+    }
+
     fn setup(cache_name: &str) -> (PathBuf, PathBuf, PathBuf, SplitResult) {
         let root: PathBuf = env::current_dir().unwrap();
         let file_name: &str = "test.jpg";
@@ -43,12 +118,20 @@ mod tests {
 
     #[tokio::test]
     async fn test_split_file_creates_chunks() {
-        let (_, cache_dir, _, _) = setup("split_file_creates_chunks");
+        let (_, cache_dir, _, split_result) =
+            setup("split_file_creates_chunks");
 
         let chunk_count: usize =
             fs::read_dir(&cache_dir).unwrap().filter_map(Result::ok).count();
 
         assert!(chunk_count > 0, "No chunks were created.");
+
+        assert_eq!(split_result.chunk_paths.len(), split_result.total_chunks);
+        assert_eq!(
+            split_result.chunk_sizes.iter().sum::<usize>(),
+            split_result.file_size
+        );
+        assert!(split_result.chunk_paths.iter().all(|p| p.is_file()));
     }
 
     #[tokio::test]
@@ -105,10 +188,7 @@ mod tests {
             .run()
             .unwrap();
 
-        assert!(
-            check_result.success == true,
-            "Check should succeed with no errors."
-        );
+        assert!(check_result.success, "Check should succeed with no errors.");
     }
 
     #[tokio::test]
@@ -147,4 +227,3692 @@ mod tests {
             "Merge should fail with an empty cache directory."
         );
     }
+
+    #[tokio::test]
+    async fn test_split_with_priority() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("priority");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .priority(Priority::new().io(IoClass::BestEffort, 7))
+            .run()
+            .unwrap();
+
+        assert!(split_result.total_chunks > 0, "No chunks were created.");
+    }
+
+    #[tokio::test]
+    async fn test_split_paused_and_resumed() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("control");
+
+        let control: Control = Control::new();
+
+        control.pause();
+
+        let split: Split = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .control(&control);
+
+        let handle = thread::spawn(move || split.run());
+
+        thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(control.status(), Status::Paused);
+
+        control.resume();
+
+        assert!(handle.join().unwrap().is_ok(), "Split should succeed.");
+        assert_eq!(control.status(), Status::Finished);
+    }
+
+    #[tokio::test]
+    async fn test_conformance_with_test_vector() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let result: ConformanceResult = Conformance::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .test_vector(test_vector())
+            .run()
+            .unwrap();
+
+        assert!(result.success, "Conformance should succeed.");
+    }
+
+    #[tokio::test]
+    async fn test_conformance_with_hash_error() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let mut vector: TestVector = test_vector();
+        vector.chunks[3].sha256 = "0".repeat(64);
+
+        let result: ConformanceResult = Conformance::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .test_vector(vector)
+            .run()
+            .unwrap();
+
+        assert!(!result.success, "Conformance should fail due to hash.");
+        if let Some(e) = result.error {
+            assert_eq!(e.error_type, ConformanceResultErrorType::ChunkHash);
+            assert_eq!(e.index, Some(3));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_generated_file() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("std").join("random");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("generated");
+
+        test_util::generate_file(
+            &in_file,
+            300_000,
+            &Pattern::Random { seed: 42 },
+        )
+        .unwrap();
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(64 * 1024)
+            .run()
+            .unwrap();
+
+        test_util::assert_file_eq_chunks(&in_file, &cache_dir);
+    }
+
+    #[tokio::test]
+    async fn test_plan_split_edge_cases() {
+        assert!(plan::plan_split(10, 0).is_none());
+        assert_eq!(plan::plan_split(0, 1).unwrap().total_chunks(), 0);
+        assert_eq!(plan::plan_split(3, 1).unwrap().total_chunks(), 3);
+
+        let last: ChunkPlan = plan::plan_split(usize::MAX, usize::MAX / 2)
+            .unwrap()
+            .last()
+            .unwrap();
+
+        assert_eq!(last.index, 2);
+        assert_eq!(last.offset + last.size, usize::MAX);
+    }
+
+    #[tokio::test]
+    async fn test_plan_merge_and_parse_chunk_name() {
+        let max: String = usize::MAX.to_string();
+
+        let plan: MergePlan<&str> =
+            plan::plan_merge(["1", "00", "manifest.json", max.as_str()]);
+
+        assert_eq!(plan.chunks, vec![(1, "1"), (usize::MAX, max.as_str())]);
+        assert_eq!(plan.missing, vec![0..1, 2..usize::MAX]);
+        assert_eq!(plan.ignored, vec!["00", "manifest.json"]);
+
+        assert_eq!(plan::parse_chunk_name("0"), Some(0));
+        assert_eq!(plan::parse_chunk_name("+1"), None);
+        assert_eq!(plan::parse_chunk_name(""), None);
+        assert_eq!(plan::parse_chunk_name("18446744073709551616"), None);
+    }
+
+    #[tokio::test]
+    async fn test_merge_ignores_other_files() {
+        let (_, cache_dir, output_path, split_result) =
+            setup("merge_ignores_other_files");
+
+        fs::write(cache_dir.join("notes.txt"), b"not a chunk").unwrap();
+
+        Merge::new().in_dir(&cache_dir).out_file(&output_path).run().unwrap();
+
+        assert_eq!(
+            fs::metadata(&output_path).unwrap().len() as usize,
+            split_result.file_size
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge_empty_file() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("std").join("empty");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("empty_file");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("empty_file")
+            .join("empty");
+
+        test_util::generate_file(&in_file, 0, &Pattern::Zeros).unwrap();
+
+        let split_result: SplitResult =
+            Split::new().in_file(&in_file).out_dir(&cache_dir).run().unwrap();
+
+        assert_eq!(split_result.total_chunks, 0);
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .empty_chunk(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(split_result.total_chunks, 1);
+
+        Merge::new().in_dir(&cache_dir).out_file(&output_path).run().unwrap();
+
+        assert_eq!(fs::metadata(&output_path).unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge_passthrough() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("std").join("small");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("passthrough");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("passthrough")
+            .join("small");
+
+        test_util::generate_file(&in_file, 1000, &Pattern::Random { seed: 1 })
+            .unwrap();
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .passthrough(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(split_result.total_chunks, 1);
+        test_util::assert_file_eq_chunks(&in_file, &cache_dir);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .passthrough(true)
+            .run()
+            .unwrap();
+
+        assert!(!cache_dir.join("0").exists(), "Chunk should be moved.");
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_chunks_exceeded() {
+        let (root, cache_dir, output_path, split_result) =
+            setup("max_chunks_exceeded");
+
+        let split_error: SplitError = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1)
+            .max_chunks(split_result.total_chunks)
+            .run()
+            .unwrap_err();
+
+        assert_eq!(split_error, SplitError::TooManyChunks);
+
+        let check_error: CheckError = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(usize::MAX)
+            .max_chunks(split_result.total_chunks)
+            .run()
+            .unwrap_err();
+
+        assert_eq!(check_error, CheckError::TooManyChunks);
+
+        let merge_error: MergeError = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .max_chunks(split_result.total_chunks - 1)
+            .run()
+            .unwrap_err();
+
+        assert_eq!(merge_error, MergeError::TooManyChunks);
+        assert!(!output_path.exists(), "Output file should not be created.");
+    }
+
+    #[tokio::test]
+    async fn test_merge_strict_untrusted() {
+        let (_, cache_dir, output_path, _) = setup("merge_strict_untrusted");
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .strict_untrusted();
+
+        assert_eq!(merge.max_file_size, Some(MAX_FILE_SIZE_UNTRUSTED_DEFAULT));
+        assert_eq!(merge.run().unwrap_err(), MergeError::VerifyNotSet);
+
+        let merge: Merge =
+            Merge::from(merge).verify(HashMap::from([(0, [0; 32])]));
+
+        let unsafe_path: PathBuf =
+            output_path.parent().unwrap().join("..").join("escaped");
+
+        let merge_error: MergeError = Merge::from(merge.clone())
+            .out_file(&unsafe_path)
+            .run()
+            .unwrap_err();
+
+        assert_eq!(merge_error, MergeError::OutFileNotSafe);
+
+        fs::remove_file(cache_dir.join("1")).unwrap();
+
+        let merge_error: MergeError = merge.run().unwrap_err();
+
+        assert_eq!(merge_error, MergeError::InDirNotContiguous);
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_locked_dir() {
+        let (_, cache_dir, output_path, _) = setup("merge_with_locked_dir");
+
+        let lock: DirLock = DirLock::acquire(&cache_dir).unwrap();
+
+        let merge: Merge =
+            Merge::new().in_dir(&cache_dir).out_file(&output_path).lock(true);
+
+        assert_eq!(merge.run().unwrap_err(), MergeError::Locked);
+
+        drop(lock);
+
+        assert!(merge.run().is_ok(), "Merge should succeed once unlocked.");
+        assert!(!cache_dir.join(".filego.lock").exists());
+    }
+
+    #[tokio::test]
+    async fn test_gnu_and_padded_namers() {
+        let gnu: Gnu = Gnu::new();
+
+        assert_eq!(gnu.name(0), "xaa");
+        assert_eq!(gnu.name(649), "xyz");
+        assert_eq!(gnu.name(650), "xzaaa");
+        assert_eq!(Gnu::new().numeric(true).name(90), "x9000");
+        assert_eq!(gnu.parse("xza"), None);
+
+        let padded: Padded = Padded::new(3);
+
+        assert_eq!(padded.name(7), "007");
+        assert_eq!(padded.name(1234), "1234");
+        assert_eq!(padded.parse("0123"), None);
+
+        for i in (0..20_000).chain([usize::MAX - 1, usize::MAX]) {
+            assert_eq!(gnu.parse(&gnu.name(i)), Some(i));
+            assert_eq!(padded.parse(&padded.name(i)), Some(i));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_check_and_merge_with_namer() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("gnu_namer");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("gnu_namer")
+            .join("test.jpg");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .namer(Gnu::new())
+            .run()
+            .unwrap();
+
+        assert!(cache_dir.join("xaa").exists(), "Chunk should use GNU name.");
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .namer(Gnu::new())
+            .run()
+            .unwrap();
+
+        assert!(check_result.success, "Check should succeed with no errors.");
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .namer(Gnu::new())
+            .run()
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_reports_progress() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("progress");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("progress")
+            .join("test.jpg");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .unwrap();
+
+        let events: Arc<Mutex<Vec<Progress>>> =
+            Arc::new(Mutex::new(Vec::new()));
+        let recorded: Arc<Mutex<Vec<Progress>>> = Arc::clone(&events);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .on_progress(move |p| recorded.lock().unwrap().push(*p))
+            .run()
+            .unwrap();
+
+        let events: Vec<Progress> = events.lock().unwrap().clone();
+
+        assert_eq!(events.len(), split_result.total_chunks);
+
+        for (i, p) in events.iter().enumerate() {
+            assert_eq!(p.chunks_done, i + 1);
+            assert_eq!(p.total_chunks, split_result.total_chunks);
+            assert_eq!(p.total_bytes, split_result.file_size as u64);
+        }
+
+        let last: &Progress = events.last().unwrap();
+
+        assert_eq!(last.bytes_done, split_result.file_size as u64);
+        assert_eq!(last.eta, Some(Duration::ZERO));
+        assert_eq!(last.fraction(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_merge_cancelled_with_partial_result() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("cancelled");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("cancelled")
+            .join("test.jpg");
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .unwrap();
+
+        let control: Control = Control::new();
+        let canceller: Control = control.clone();
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .control(&control)
+            .on_progress(move |p| {
+                if p.chunks_done == 2 {
+                    canceller.cancel();
+                }
+            })
+            .run();
+
+        assert_eq!(result, Err(MergeError::Cancelled));
+        assert_eq!(control.status(), Status::Cancelled);
+        assert_eq!(
+            control.partial(),
+            Partial {
+                chunks_done: 2,
+                bytes_done: 2 * 1024 * 1024,
+                last_index: Some(1),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_with_hashes() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let vector: TestVector = test_vector();
+
+        for workers in [1, 3] {
+            let split_result: SplitResult = Split::new()
+                .in_file(root.join("assets").join("test.jpg"))
+                .out_dir(
+                    root.join(".media")
+                        .join("cache")
+                        .join("std")
+                        .join(format!("hashes_{}", workers)),
+                )
+                .chunk_size(vector.chunk_size)
+                .hash(true)
+                .hash_workers(workers)
+                .run()
+                .unwrap();
+
+            let expected: Vec<String> =
+                vector.chunks.iter().map(|c| c.sha256.clone()).collect();
+
+            assert_eq!(split_result.hashes, Some(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_follows_growing_file() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("growing.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("growing");
+
+        test_util::generate_file(&in_file, 100, &Pattern::Random { seed: 1 })
+            .unwrap();
+
+        let follow: Follow = Follow::new().interval(Duration::from_millis(5));
+
+        let split: Split = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(64)
+            .follow(&follow);
+
+        let handle: thread::JoinHandle<SplitResult> =
+            thread::spawn(move || split.run().unwrap());
+
+        for seed in 2..5 {
+            thread::sleep(Duration::from_millis(20));
+
+            let mut file: fs::File =
+                fs::OpenOptions::new().append(true).open(&in_file).unwrap();
+
+            for _ in 0..10 {
+                file.write_all(&[seed as u8; 10]).unwrap();
+            }
+        }
+
+        follow.end();
+
+        let split_result: SplitResult = handle.join().unwrap();
+
+        assert_eq!(split_result.file_size, 400);
+        assert_eq!(split_result.total_chunks, 7);
+
+        test_util::assert_file_eq_chunks(&in_file, &cache_dir);
+    }
+
+    #[tokio::test]
+    async fn test_merge_follows_arriving_chunks() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("live.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("live");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("live")
+            .join("live.bin");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        test_util::generate_file(&in_file, 0, &Pattern::Zeros).unwrap();
+
+        let split_follow: Follow =
+            Follow::new().interval(Duration::from_millis(5));
+        let merge_follow: Follow =
+            Follow::new().interval(Duration::from_millis(5));
+
+        let split: Split = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(64)
+            .follow(&split_follow);
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .follow(&merge_follow);
+
+        let split_handle: thread::JoinHandle<SplitResult> =
+            thread::spawn(move || split.run().unwrap());
+        let merge_handle: thread::JoinHandle<Result<MergeResult, MergeError>> =
+            thread::spawn(move || merge.run());
+
+        for seed in 0..5 {
+            thread::sleep(Duration::from_millis(10));
+
+            let mut file: fs::File =
+                fs::OpenOptions::new().append(true).open(&in_file).unwrap();
+
+            file.write_all(&[seed as u8; 100]).unwrap();
+        }
+
+        split_follow.end();
+
+        assert_eq!(split_handle.join().unwrap().total_chunks, 8);
+
+        merge_follow.end();
+
+        let merge_result: MergeResult = merge_handle.join().unwrap().unwrap();
+
+        assert_eq!(merge_result.file_size, 500);
+        assert_eq!(merge_result.total_chunks, 8);
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_pre_finalize_hook() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("pre_finalize");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("pre_finalize")
+            .join("test.jpg");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .unwrap();
+
+        let _ = fs::remove_file(&output_path);
+
+        let rejected: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .pre_finalize(|_| false)
+            .run();
+
+        assert_eq!(rejected, Err(MergeError::ContentRejected));
+        assert!(!output_path.exists(), "Output should not be written.");
+
+        let file_size: u64 = split_result.file_size as u64;
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .pre_finalize(move |reader: &mut ChunkedReader| {
+                let mut magic: [u8; 2] = [0; 2];
+                reader.read_exact(&mut magic).unwrap();
+
+                // read across the boundary of the first two chunks
+                let mut boundary: [u8; 4] = [0; 4];
+                reader.seek(SeekFrom::Start(1024 * 1024 - 2)).unwrap();
+                reader.read_exact(&mut boundary).unwrap();
+
+                let expected: Vec<u8> = fs::read(in_file.clone()).unwrap();
+
+                reader.len() == file_size
+                    && magic == [0xff, 0xd8]
+                    && boundary[..]
+                        == expected[1024 * 1024 - 2..1024 * 1024 + 2]
+            })
+            .run()
+            .unwrap();
+
+        assert!(output_path.exists(), "Output should be written.");
+    }
+
+    #[tokio::test]
+    async fn test_merge_allowed_types() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("allowed_types");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("allowed_types")
+            .join("test.jpg");
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("header.jpg");
+
+        test_util::generate_file(
+            &in_file,
+            100,
+            &Pattern::Repeat(vec![0xff, 0xd8, 0xff, 0xe0]),
+        )
+        .unwrap();
+
+        // the signature is split across the first two chunks
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(2)
+            .run()
+            .unwrap();
+
+        let rejected: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .allowed_types(["application/pdf", "text/*"])
+            .run();
+
+        assert_eq!(rejected, Err(MergeError::ContentRejected));
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .allowed_types(["image/*"])
+            .run()
+            .unwrap();
+
+        assert_eq!(sniff::sniff(b"\x89PNG\r\n\x1a\n"), "image/png");
+        assert_eq!(sniff::sniff(b"\x00\x01"), sniff::UNKNOWN_TYPE);
+        assert!(sniff::matches("image/jpeg", "image/*"));
+        assert!(!sniff::matches("image/jpeg", "image/png"));
+    }
+
+    #[tokio::test]
+    async fn test_merge_max_file_size() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("max_file_size");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("max_file_size")
+            .join("test.jpg");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run()
+            .unwrap();
+
+        let file_size: u64 = split_result.file_size as u64;
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .max_file_size(file_size - 1)
+            .run();
+
+        assert_eq!(result, Err(MergeError::FileTooLarge));
+        assert!(!output_path.exists(), "Partial output should be removed.");
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .max_file_size(file_size)
+            .run()
+            .unwrap();
+
+        assert_eq!(fs::metadata(&output_path).unwrap().len(), file_size);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_orphans() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let base_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("orphans");
+        let chunk_dir: PathBuf = base_dir.join("upload");
+
+        let _ = fs::remove_dir_all(&base_dir);
+        fs::create_dir_all(&chunk_dir).unwrap();
+
+        let temp: PathBuf = cleanup::temp_path(chunk_dir.join("file"));
+
+        fs::write(&temp, b"partial").unwrap();
+        fs::write(chunk_dir.join("0"), b"chunk").unwrap();
+        std::mem::forget(DirLock::acquire(&chunk_dir).unwrap());
+
+        assert_eq!(
+            cleanup::cleanup_orphans(&base_dir, Duration::from_secs(3600))
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            cleanup::cleanup_orphans(&base_dir, Duration::ZERO).unwrap(),
+            2
+        );
+
+        assert!(!temp.exists(), "Temporary file should be removed.");
+        assert!(chunk_dir.join("0").exists(), "Chunk should be kept.");
+        assert!(DirLock::acquire(&chunk_dir).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_split_with_manifest() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("manifest");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("manifest")
+            .join("test.jpg");
+
+        let vector: TestVector = test_vector();
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(vector.chunk_size)
+            .namer(Padded::new(3))
+            .hash(true)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        assert_eq!(manifest.file_size, split_result.file_size);
+        assert_eq!(manifest.chunk_size, vector.chunk_size);
+        assert_eq!(manifest.total_chunks, split_result.total_chunks);
+
+        for (chunk, expected) in manifest.chunks.iter().zip(&vector.chunks) {
+            assert_eq!(chunk.name, format!("{:03}", chunk.index));
+            assert_eq!(chunk.size, expected.size);
+            assert_eq!(chunk.sha256.as_ref(), Some(&expected.sha256));
+        }
+
+        // the manifest is not merged as a chunk
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .namer(Padded::new(3))
+            .run()
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_from_reader() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("reader");
+
+        let content: Vec<u8> = fs::read(&in_file).unwrap();
+
+        let split_result: SplitResult = Split::new()
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run_reader(&content[..])
+            .unwrap();
+
+        assert_eq!(split_result.file_size, content.len());
+        assert_eq!(split_result.total_chunks, 8);
+
+        test_util::assert_file_eq_chunks(&in_file, &cache_dir);
+
+        let result: Result<SplitResult, SplitError> = Split::new()
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .max_chunks(7)
+            .run_reader(&content[..]);
+
+        assert_eq!(result.unwrap_err(), SplitError::TooManyChunks);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_uploads_with_bounded_buffers() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let vector: TestVector = test_vector();
+
+        let uploaded: Mutex<Vec<Chunk>> = Mutex::new(Vec::new());
+        let in_flight: Mutex<(usize, usize)> = Mutex::new((0, 0));
+
+        let split_result: SplitResult = Pipeline::new()
+            .in_file(&in_file)
+            .chunk_size(vector.chunk_size)
+            .concurrency(4)
+            .max_buffers(2)
+            .hash(true)
+            .run(|chunk| {
+                {
+                    let mut in_flight = in_flight.lock().unwrap();
+                    in_flight.0 += 1;
+                    in_flight.1 = in_flight.1.max(in_flight.0);
+                }
+
+                thread::sleep(Duration::from_millis(10));
+
+                in_flight.lock().unwrap().0 -= 1;
+                uploaded.lock().unwrap().push(chunk);
+
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(split_result.file_size, vector.file_size);
+        assert_eq!(split_result.total_chunks, vector.chunks.len());
+        assert!(in_flight.lock().unwrap().1 <= 2);
+
+        let mut uploaded: Vec<Chunk> = uploaded.into_inner().unwrap();
+        uploaded.sort_by_key(|c| c.index);
+
+        let content: Vec<u8> = fs::read(&in_file).unwrap();
+
+        for (chunk, expected) in uploaded.iter().zip(&vector.chunks) {
+            assert_eq!(chunk.data.len(), expected.size);
+            assert_eq!(chunk.sha256.as_ref(), Some(&expected.sha256));
+            assert_eq!(
+                chunk.data,
+                content[chunk.offset..chunk.offset + expected.size]
+            );
+        }
+
+        assert_eq!(
+            split_result.hashes,
+            Some(vector.chunks.iter().map(|c| c.sha256.clone()).collect())
+        );
+
+        let result: Result<SplitResult, PipelineError> = Pipeline::new()
+            .in_file(&in_file)
+            .chunk_size(vector.chunk_size)
+            .run(|chunk| match chunk.index {
+                | 3 => Err(std::io::Error::other("upload failed")),
+                | _ => Ok(()),
+            });
+
+        assert_eq!(result.unwrap_err(), PipelineError::ChunkNotUploaded);
+    }
+
+    #[tokio::test]
+    async fn test_check_with_manifest_hashes() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("std")
+            .join("check_manifest");
+
+        Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .hash(true)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let check: Check = Check::new()
+            .in_dir(&cache_dir)
+            .manifest(cache_dir.join(MANIFEST_FILE_NAME));
+
+        assert!(check.run().unwrap().success);
+
+        // flip a bit without changing the size
+        let chunk_path: PathBuf = cache_dir.join("2");
+        let mut content: Vec<u8> = fs::read(&chunk_path).unwrap();
+        content[100] ^= 1;
+        fs::write(&chunk_path, content).unwrap();
+
+        let result: CheckResult = check.run().unwrap();
+
+        assert!(!result.success);
+
+        let error: CheckResultError = result.error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Hash);
+        assert_eq!(error.mismatched, Some(vec![2]));
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_hash() {
+        let (_, cache_dir, output_path, split_result) =
+            setup("merge_with_hash");
+
+        let expected: &str =
+            "6b9ef7560111bea5c9d510f9f2af6d0f5562b7a2b89a03456f2426d2512fa1a3";
+
+        let merge_result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .hash(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(merge_result.file_size, split_result.file_size);
+        assert_eq!(merge_result.total_chunks, split_result.total_chunks);
+        assert_eq!(merge_result.sha256.as_deref(), Some(expected));
+        assert_eq!(merge_result.out_file, output_path);
+
+        // the same hash while following ended chunks
+        let follow: Follow = Follow::new();
+        follow.end();
+
+        let merge_result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(output_path.with_file_name("followed.jpg"))
+            .follow(&follow)
+            .hash(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(merge_result.sha256.as_deref(), Some(expected));
+
+        let merge_result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(output_path.with_file_name("unhashed.jpg"))
+            .run()
+            .unwrap();
+
+        assert_eq!(merge_result.sha256, None);
+    }
+
+    #[tokio::test]
+    async fn test_split_dedup_keeps_identical_chunks() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("dedup.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("dedup");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        test_util::generate_file(&in_file, 4096, &Pattern::Random { seed: 7 })
+            .unwrap();
+
+        let split: Split = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024)
+            .dedup(true);
+
+        split.run().unwrap();
+
+        let past: SystemTime = UNIX_EPOCH + Duration::from_secs(1000);
+
+        for i in 0..4 {
+            fs::File::options()
+                .write(true)
+                .open(cache_dir.join(i.to_string()))
+                .unwrap()
+                .set_modified(past)
+                .unwrap();
+        }
+
+        // change the third chunk only
+        let mut file: fs::File =
+            fs::OpenOptions::new().write(true).open(&in_file).unwrap();
+        file.seek(SeekFrom::Start(2048)).unwrap();
+        file.write_all(&[0xff; 16]).unwrap();
+
+        split.run().unwrap();
+
+        let modified = |i: usize| {
+            fs::metadata(cache_dir.join(i.to_string()))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+
+        assert_eq!(modified(0), past);
+        assert_eq!(modified(1), past);
+        assert_ne!(modified(2), past);
+        assert_eq!(modified(3), past);
+
+        test_util::assert_file_eq_chunks(&in_file, &cache_dir);
+    }
+
+    #[tokio::test]
+    async fn test_split_and_check_with_blake3() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("blake3.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("blake3");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let block: Vec<u8> = (0..251).collect();
+
+        test_util::generate_file(&in_file, 1025, &Pattern::Repeat(block))
+            .unwrap();
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024)
+            .hash(true)
+            .hash_algorithm(HashAlgorithm::Blake3)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let expected: Vec<String> = vec![
+            "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7"
+                .to_string(),
+            "ca9fba296cab1ffbd9597311a1d79a06dfaf0909995a00a98a7e361fb67158e9"
+                .to_string(),
+        ];
+
+        assert_eq!(split_result.hashes, Some(expected.clone()));
+        assert_eq!(
+            split_result.file_hash.as_deref(),
+            Some(
+                "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444"
+            )
+        );
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        for (chunk, expected) in manifest.chunks.iter().zip(&expected) {
+            assert_eq!(chunk.sha256, None);
+            assert_eq!(chunk.blake3.as_ref(), Some(expected));
+        }
+
+        let check: Check = Check::new()
+            .in_dir(&cache_dir)
+            .manifest(cache_dir.join(MANIFEST_FILE_NAME));
+
+        assert!(check.run().unwrap().success);
+
+        fs::write(cache_dir.join("1"), [21]).unwrap();
+
+        let error: CheckResultError = check.run().unwrap().error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Hash);
+        assert_eq!(error.mismatched, Some(vec![1]));
+    }
+
+    #[tokio::test]
+    async fn test_manifest_delta() {
+        let chunk = |index: usize, size: usize, sha256: &str| ManifestChunk {
+            index,
+            name: index.to_string(),
+            size,
+            sha256: Some(sha256.to_string()),
+            blake3: None,
+        };
+
+        let manifest = |chunks: Vec<ManifestChunk>| Manifest {
+            version: 1,
+            file_size: chunks.iter().map(|c| c.size).sum(),
+            chunk_size: 4,
+            total_chunks: chunks.len(),
+            chunks,
+            metadata: BTreeMap::new(),
+        };
+
+        let old: Manifest = manifest(vec![
+            chunk(0, 4, "aa"),
+            chunk(1, 4, "bb"),
+            chunk(2, 4, "cc"),
+            chunk(3, 2, "dd"),
+        ]);
+        let new: Manifest = manifest(vec![
+            chunk(0, 4, "aa"),
+            chunk(1, 4, "b2"),
+            chunk(2, 3, "cc"),
+        ]);
+
+        let delta: ManifestDelta = new.diff(&old);
+
+        assert_eq!(delta.file_size, 11);
+        assert_eq!(delta.total_chunks, 3);
+        assert_eq!(delta.changed(), vec![1, 2]);
+        assert_eq!(
+            delta.changes.iter().map(|c| c.index).collect::<Vec<usize>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(delta.changes[0].old.as_ref(), Some(&old.chunks[1]));
+        assert_eq!(delta.changes[0].new.as_ref(), Some(&new.chunks[1]));
+        assert_eq!(delta.changes[2].new, None);
+
+        assert_eq!(delta.apply(&old), new);
+        assert_eq!(ManifestDelta::from_json(&delta.to_json()).unwrap(), delta);
+
+        // the old manifest is rebuilt from the reverse delta
+        assert_eq!(old.diff(&new).apply(&new), old);
+        assert!(new.diff(&new).changes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_split_with_file_hash() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let split: Split = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(
+                root.join(".media").join("cache").join("std").join("file_hash"),
+            )
+            .chunk_size(1024 * 1024);
+
+        assert_eq!(split.clone().run().unwrap().file_hash, None);
+
+        let split_result: SplitResult =
+            split.hash_algorithm(HashAlgorithm::Sha256).run().unwrap();
+
+        assert_eq!(
+            split_result.file_hash.as_deref(),
+            Some(
+                "6b9ef7560111bea5c9d510f9f2af6d0f5562b7a2b89a03456f2426d2512fa1a3"
+            )
+        );
+        assert_eq!(split_result.hashes, None);
+    }
+
+    #[tokio::test]
+    async fn test_rechunk_to_another_chunk_size() {
+        let (root, cache_dir, _, split_result) = setup("rechunk");
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let out_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("rechunked");
+
+        let _ = fs::remove_dir_all(&out_dir);
+
+        let rechunk_result: SplitResult = Rechunk::new()
+            .in_dir(&cache_dir)
+            .out_dir(&out_dir)
+            .chunk_size(3 * 1024 * 1024)
+            .run()
+            .unwrap();
+
+        assert_eq!(rechunk_result.file_size, split_result.file_size);
+        assert_eq!(rechunk_result.total_chunks, 3);
+
+        test_util::assert_file_eq_chunks(&in_file, &out_dir);
+
+        let error: RechunkError = Rechunk::new()
+            .in_dir(&cache_dir)
+            .out_dir(&cache_dir)
+            .run()
+            .unwrap_err();
+
+        assert_eq!(error, RechunkError::OutDirIsInDir);
+    }
+
+    #[tokio::test]
+    async fn test_merge_verifies_chunks() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("verify");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("verify")
+            .join("test.jpg");
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .hash(true)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .verify_manifest(&manifest);
+
+        merge.run().unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        // flip a bit without changing the size
+        let chunk_path: PathBuf = cache_dir.join("3");
+        let mut content: Vec<u8> = fs::read(&chunk_path).unwrap();
+        content[100] ^= 1;
+        fs::write(&chunk_path, content).unwrap();
+
+        assert_eq!(merge.run(), Err(MergeError::ChunkCorrupted { index: 3 }));
+        assert!(!output_path.exists());
+
+        // the same while following ended chunks
+        let follow: Follow = Follow::new();
+        follow.end();
+
+        assert_eq!(
+            Merge::from(merge).follow(&follow).run(),
+            Err(MergeError::ChunkCorrupted { index: 3 })
+        );
+        assert!(!output_path.exists());
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .verify(HashMap::from([(0, [0; 32])]))
+            .run();
+
+        assert_eq!(result, Err(MergeError::ChunkCorrupted { index: 0 }));
+    }
+
+    #[tokio::test]
+    async fn test_errors_implement_error() {
+        let error: Box<dyn Error> = Box::new(SplitError::InFileNotFound);
+
+        assert_eq!(error.to_string(), SplitError::InFileNotFound.as_message());
+
+        let error: RechunkError = RechunkError::from(SplitError::OutDirNotDir);
+
+        assert_eq!(error.to_string(), SplitError::OutDirNotDir.as_message());
+        assert_eq!(
+            error.source().map(|e| e.to_string()),
+            Some(SplitError::OutDirNotDir.to_message())
+        );
+        assert!(RechunkError::InDirNotSet.source().is_none());
+        assert_eq!(
+            format!("{}", MergeError::ChunkCorrupted { index: 1 }),
+            MergeError::ChunkCorrupted { index: 1 }.as_message()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_heterogeneous_chunk_sizes() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("varying");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("varying")
+            .join("output.bin");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+        fs::create_dir_all(&cache_dir).unwrap();
+
+        let content: Vec<u8> = (0..315).map(|i: usize| i as u8).collect();
+        let mut chunks: Vec<ManifestChunk> = Vec::new();
+        let mut offset: usize = 0;
+
+        for (index, size) in [10, 300, 5].into_iter().enumerate() {
+            fs::write(
+                cache_dir.join(index.to_string()),
+                &content[offset..offset + size],
+            )
+            .unwrap();
+
+            chunks.push(ManifestChunk {
+                index,
+                name: index.to_string(),
+                size,
+                sha256: None,
+                blake3: None,
+            });
+
+            offset += size;
+        }
+
+        Manifest {
+            version: 1,
+            file_size: content.len(),
+            chunk_size: 300,
+            total_chunks: chunks.len(),
+            chunks,
+            metadata: BTreeMap::new(),
+        }
+        .write(cache_dir.join(MANIFEST_FILE_NAME))
+        .unwrap();
+
+        let check: Check = Check::new()
+            .in_dir(&cache_dir)
+            .manifest(cache_dir.join(MANIFEST_FILE_NAME));
+
+        assert!(check.run().unwrap().success);
+
+        let merge_result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .unwrap();
+
+        assert_eq!(merge_result.file_size, content.len());
+        assert_eq!(fs::read(&output_path).unwrap(), content);
+
+        // move a byte to the next chunk without changing the total size
+        fs::write(cache_dir.join("0"), &content[..9]).unwrap();
+        fs::write(cache_dir.join("1"), &content[9..310]).unwrap();
+
+        let error: CheckResultError = check.run().unwrap().error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Size);
+        assert_eq!(error.mismatched, Some(vec![0, 1]));
+    }
+
+    #[tokio::test]
+    async fn test_error_keeps_path_and_source() {
+        let (root, cache_dir, _, _) = setup("error_path");
+
+        // a file where the parent directory of the output should be
+        let parent: PathBuf =
+            root.join("assets").join("test.jpg").join("output");
+
+        let error: MergeError = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(parent.join("test.jpg"))
+            .run()
+            .unwrap_err();
+
+        assert!(matches!(error, MergeError::OutDirNotCreated { .. }));
+        assert_eq!(error.as_code(), "out_dir_not_created");
+        assert_eq!(error.path(), Some(parent.as_path()));
+        assert!(error.source().is_some());
+        assert!(error.to_string().contains(&parent.display().to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_unified_error() {
+        let (_, cache_dir, output_path, split_result) = setup("unified_error");
+
+        let run = || -> Result<MergeResult, filego::Error> {
+            Check::new()
+                .in_dir(&cache_dir)
+                .file_size(split_result.file_size)
+                .total_chunks(split_result.total_chunks)
+                .run()?;
+
+            Ok(Merge::new().in_dir(&cache_dir).out_file(&output_path).run()?)
+        };
+
+        assert!(run().is_ok());
+
+        let error: filego::Error = Split::new().run().unwrap_err().into();
+
+        assert_eq!(error, filego::Error::Split(SplitError::InFileNotSet));
+        assert_eq!(error.as_code(), "in_file_not_set");
+        assert_eq!(error.to_string(), SplitError::InFileNotSet.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_serde_results_and_errors() {
+        let (root, cache_dir, _, split_result) = setup("serde");
+
+        let json: String = serde_json::to_string(&split_result).unwrap();
+        let parsed: SplitResult = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.file_size, split_result.file_size);
+        assert_eq!(parsed.total_chunks, split_result.total_chunks);
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks + 1)
+            .run()
+            .unwrap();
+
+        let json: String = serde_json::to_string(&check_result).unwrap();
+
+        assert!(json.contains("\"error_type\":\"missing\""));
+
+        assert_eq!(
+            serde_json::to_string(&SplitError::InFileNotSet).unwrap(),
+            "\"in_file_not_set\""
+        );
+
+        let error: MergeError = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(root.join("assets").join("test.jpg").join("output"))
+            .run()
+            .unwrap_err();
+
+        assert!(error.path().is_some());
+
+        let error: filego::Error = error.into();
+        let json: String = serde_json::to_string(&error).unwrap();
+        let parsed: filego::Error = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed, error);
+    }
+
+    #[tokio::test]
+    async fn test_manifest_metadata() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("metadata");
+
+        Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .with_manifest(true)
+            .metadata("uploader", "alice")
+            .metadata("content_type", "image/jpeg")
+            .run()
+            .unwrap();
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        assert_eq!(
+            manifest.metadata.get("uploader").map(String::as_str),
+            Some("alice")
+        );
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .manifest(cache_dir.join(MANIFEST_FILE_NAME))
+            .run()
+            .unwrap();
+
+        assert!(check_result.success);
+        assert_eq!(check_result.metadata, manifest.metadata);
+        assert_eq!(
+            check_result.metadata.get("content_type").map(String::as_str),
+            Some("image/jpeg")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chunk_info() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("chunk_info");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        let infos: Vec<ChunkInfo> = manifest.chunk_infos();
+
+        assert_eq!(infos.len(), split_result.total_chunks);
+        assert_eq!(infos[1].offset, 1024 * 1024);
+        assert_eq!(infos.last().unwrap().range().end, split_result.file_size);
+
+        let planned: Vec<ChunkInfo> =
+            plan::plan_split(split_result.file_size, 1024 * 1024)
+                .unwrap()
+                .map(|chunk| chunk.info(&Gnu::new().numeric(true)))
+                .collect();
+
+        assert_eq!(planned[1].name, "x01");
+        assert_eq!(planned[1].range(), infos[1].range());
+        assert_eq!(planned[1].hash, None);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_capacity_from_memory() {
+        let capacity: usize = memory::buffer_capacity(4);
+
+        assert!(capacity >= memory::BUFFER_CAPACITY_MIN);
+        assert!(capacity <= BUFFER_CAPACITY_MAX_DEFAULT);
+
+        if memory::available().is_none() {
+            assert_eq!(capacity, BUFFER_CAPACITY_MAX_DEFAULT);
+        }
+
+        let split: Split = Split::new().max_buffer_capacity_auto(4);
+
+        assert!(split.cap_max >= memory::BUFFER_CAPACITY_MIN);
+        assert!(split.cap_max <= BUFFER_CAPACITY_MAX_DEFAULT);
+
+        let merge: Merge = Merge::new().max_buffer_capacity_auto(4);
+
+        assert!(merge.cap_max >= memory::BUFFER_CAPACITY_MIN);
+        assert!(merge.cap_max <= BUFFER_CAPACITY_MAX_DEFAULT);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_cancel_on_signal() {
+        let control: Control = Control::new();
+
+        // the handler found before is restored once the guard is dropped
+        let previous: libc::sighandler_t =
+            unsafe { libc::signal(libc::SIGTERM, libc::SIG_IGN) };
+
+        let guard: SignalGuard = control.cancel_on_signal().unwrap();
+
+        assert!(!control.is_cancelled());
+
+        unsafe { libc::raise(libc::SIGTERM) };
+
+        for _ in 0..100 {
+            if control.is_cancelled() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        assert!(control.is_cancelled());
+
+        drop(guard);
+
+        let handler: libc::sighandler_t =
+            unsafe { libc::signal(libc::SIGTERM, previous) };
+
+        assert_eq!(handler, libc::SIG_IGN);
+    }
+
+    #[tokio::test]
+    async fn test_check_cancelled() {
+        let (_, cache_dir, _, split_result) = setup("check_cancelled");
+
+        let control: Control = Control::new();
+
+        control.cancel();
+
+        let result: Result<CheckResult, CheckError> = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .control(&control)
+            .run();
+
+        assert_eq!(result.unwrap_err(), CheckError::Cancelled);
+        assert_eq!(control.status(), Status::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_merge_verifies_sample() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("verify_sample");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("verify_sample")
+            .join("test.jpg");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .hash(true)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .verify_manifest(&manifest)
+            .verify_sample(0);
+
+        // a chunk out of the sample is not verified
+        let chunk_path: PathBuf = cache_dir.join("2");
+        let mut content: Vec<u8> = fs::read(&chunk_path).unwrap();
+        content[100] ^= 1;
+        fs::write(&chunk_path, content).unwrap();
+
+        merge.run().unwrap();
+
+        // the last chunk is always verified, before the output is touched
+        let _ = fs::remove_file(&output_path);
+
+        let last: usize = split_result.total_chunks - 1;
+        let chunk_path: PathBuf = cache_dir.join(last.to_string());
+        let mut content: Vec<u8> = fs::read(&chunk_path).unwrap();
+        content[0] ^= 1;
+        fs::write(&chunk_path, content).unwrap();
+
+        assert_eq!(
+            merge.run(),
+            Err(MergeError::ChunkCorrupted { index: last })
+        );
+        assert!(!output_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_throttle() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("throttle");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("throttle")
+            .join("test.jpg");
+
+        // one second of burst, then one second of waiting
+        let rate: u64 = fs::metadata(&in_file).unwrap().len() / 2;
+
+        let started: Instant = Instant::now();
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .throttle_bytes_per_sec(rate)
+            .run()
+            .unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(800));
+
+        let started: Instant = Instant::now();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .throttle_bytes_per_sec(rate)
+            .run()
+            .unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(800));
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_clock() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let base_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("mock_clock");
+
+        let _ = fs::remove_dir_all(&base_dir);
+
+        let clock: MockClock = MockClock::new();
+        let started: Instant = clock.now();
+
+        // throttled to 1 byte per second without waiting
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(base_dir.join("chunks"))
+            .chunk_size(1024 * 1024)
+            .throttle_bytes_per_sec(1)
+            .clock(clock.clone())
+            .run()
+            .unwrap();
+
+        let file_size: u64 = fs::metadata(&in_file).unwrap().len();
+
+        assert!(clock.now() - started >= Duration::from_secs(file_size - 1));
+
+        // aged without waiting
+        let clock: MockClock = MockClock::new();
+        let temp: PathBuf = cleanup::temp_path(base_dir.join("file"));
+
+        fs::write(&temp, b"partial").unwrap();
+
+        let older_than: Duration = Duration::from_secs(3600);
+
+        assert_eq!(
+            cleanup::cleanup_orphans_with_clock(&base_dir, older_than, &clock)
+                .unwrap(),
+            0
+        );
+
+        clock.advance(older_than);
+
+        assert_eq!(
+            cleanup::cleanup_orphans_with_clock(&base_dir, older_than, &clock)
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_fill_buffer() {
+        let (root, cache_dir, output_path, _) = setup("merge_fill_buffer");
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .max_buffer_capacity(1000)
+            .fill_buffer(true)
+            .hash(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.jpg")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_parallel() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("parallel");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("parallel")
+            .join("test.jpg");
+
+        let split: Split =
+            Split::new().in_file(&in_file).chunk_size(1024 * 1024).hash(true);
+
+        let expected: SplitResult = Split::from(split.clone())
+            .out_dir(
+                root.join(".media")
+                    .join("cache")
+                    .join("std")
+                    .join("parallel_sequential"),
+            )
+            .run()
+            .unwrap();
+
+        let split_result: SplitResult =
+            split.out_dir(&cache_dir).concurrency(4).run().unwrap();
+
+        assert_eq!(split_result.total_chunks, expected.total_chunks);
+        assert_eq!(split_result.chunk_sizes, expected.chunk_sizes);
+        assert_eq!(split_result.hashes, expected.hashes);
+        assert!(split_result.chunk_paths.iter().all(|p| p.is_file()));
+
+        Merge::new().in_dir(&cache_dir).out_file(&output_path).run().unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_parallel() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("std")
+            .join("merge_parallel");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("merge_parallel")
+            .join("test.jpg");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .hash(true)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        let events: Arc<Mutex<Vec<Progress>>> =
+            Arc::new(Mutex::new(Vec::new()));
+        let recorded: Arc<Mutex<Vec<Progress>>> = Arc::clone(&events);
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .verify_manifest(&manifest)
+            .concurrency(4)
+            .on_progress(move |p| recorded.lock().unwrap().push(*p));
+
+        let merge_result: MergeResult = merge.run().unwrap();
+
+        assert_eq!(merge_result.file_size, split_result.file_size);
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+        assert_eq!(events.lock().unwrap().len(), split_result.total_chunks);
+
+        // flip a bit without changing the size
+        let chunk_path: PathBuf = cache_dir.join("5");
+        let mut content: Vec<u8> = fs::read(&chunk_path).unwrap();
+        content[100] ^= 1;
+        fs::write(&chunk_path, content).unwrap();
+
+        assert_eq!(merge.run(), Err(MergeError::ChunkCorrupted { index: 5 }));
+        assert!(!output_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_mmap() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("mmap");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("mmap")
+            .join("test.jpg");
+
+        let split: Split = Split::new()
+            .in_file(&in_file)
+            .chunk_size(1024 * 1024)
+            .hash(true)
+            .hash_algorithm(HashAlgorithm::Sha256);
+
+        let expected: SplitResult = Split::from(split.clone())
+            .out_dir(
+                root.join(".media")
+                    .join("cache")
+                    .join("std")
+                    .join("mmap_buffered"),
+            )
+            .run()
+            .unwrap();
+
+        let split_result: SplitResult =
+            split.out_dir(&cache_dir).mmap(true).run().unwrap();
+
+        assert_eq!(split_result.chunk_sizes, expected.chunk_sizes);
+        assert_eq!(split_result.hashes, expected.hashes);
+        assert_eq!(split_result.file_hash, expected.file_hash);
+
+        let merge: Merge =
+            Merge::new().in_dir(&cache_dir).out_file(&output_path).hash(true);
+
+        let expected: MergeResult = merge.run().unwrap();
+        let merge_result: MergeResult = merge.mmap(true).run().unwrap();
+
+        assert_eq!(merge_result, expected);
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_guards_input() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("guarded.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("guarded");
+
+        fs::create_dir_all(in_file.parent().unwrap()).unwrap();
+        fs::write(&in_file, [0u8; 1000]).unwrap();
+
+        let split: Split = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(100)
+            .hash_algorithm(HashAlgorithm::Sha256)
+            .guard_input(true);
+
+        assert!(split.run().is_ok(), "Split should succeed.");
+
+        let control: Control = Control::new();
+
+        control.pause();
+
+        let paused: Split = Split::from(split).control(&control);
+
+        let handle = thread::spawn(move || paused.run());
+
+        thread::sleep(Duration::from_millis(100));
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&in_file)
+            .unwrap()
+            .write_all(&[1u8; 10])
+            .unwrap();
+
+        control.resume();
+
+        assert_eq!(
+            handle.join().unwrap().unwrap_err(),
+            SplitError::InFileChanged
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_merged_output() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("check_output");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("check_output")
+            .join("test.jpg");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .hash(true)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let check: Check = Check::new()
+            .in_dir(&cache_dir)
+            .file_size(split_result.file_size)
+            .total_chunks(split_result.total_chunks)
+            .out_file(&output_path);
+
+        let _ = fs::remove_file(&output_path);
+
+        let error: CheckResultError = check.run().unwrap().error.unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Output);
+
+        Merge::new().in_dir(&cache_dir).out_file(&output_path).run().unwrap();
+
+        assert!(check.run().unwrap().success, "Check should succeed.");
+
+        // flip a bit without changing the size
+        let mut content: Vec<u8> = fs::read(&output_path).unwrap();
+        content[3 * 1024 * 1024 + 100] ^= 1;
+        fs::write(&output_path, content).unwrap();
+
+        for check in
+            [check.clone(), check.manifest(cache_dir.join(MANIFEST_FILE_NAME))]
+        {
+            let error: CheckResultError = check.run().unwrap().error.unwrap();
+
+            assert_eq!(error.error_type, CheckResultErrorType::Output);
+            assert_eq!(error.mismatched, Some(vec![3]));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge_skips_merged() {
+        let (root, cache_dir, output_path, _) = setup("merge_skips_merged");
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .hash(true)
+            .skip_merged(true);
+
+        let _ = fs::remove_file(&output_path);
+
+        let merged: MergeResult = merge.run().unwrap();
+        let skipped: MergeResult = merge.run().unwrap();
+
+        assert!(!merged.skipped);
+        assert!(skipped.skipped);
+        assert_eq!(skipped.sha256, merged.sha256);
+        assert_eq!(skipped.file_size, merged.file_size);
+
+        // flip a bit without changing the size
+        let mut content: Vec<u8> = fs::read(&output_path).unwrap();
+        content[100] ^= 1;
+        fs::write(&output_path, content).unwrap();
+
+        assert!(!merge.run().unwrap().skipped);
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.jpg")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_copies_ranges() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("ranges.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("ranges");
+
+        let content: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+
+        fs::create_dir_all(in_file.parent().unwrap()).unwrap();
+        fs::write(&in_file, &content).unwrap();
+
+        // not aligned to the block size, so the ranges cannot be cloned
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1000)
+            .run()
+            .unwrap();
+
+        assert_eq!(split_result.total_chunks, 10);
+
+        for (i, path) in split_result.chunk_paths.iter().enumerate() {
+            assert_eq!(
+                fs::read(path).unwrap(),
+                content[i * 1000..(i + 1) * 1000]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_spawns_channel() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let vector: TestVector = test_vector();
+
+        let pipeline: Pipeline = Pipeline::new()
+            .in_file(&in_file)
+            .chunk_size(vector.chunk_size)
+            .max_buffers(1)
+            .hash(true);
+
+        let mut receiver: ChunkReceiver = pipeline.spawn().unwrap();
+
+        let chunks: Vec<Chunk> = receiver.by_ref().collect();
+
+        let split_result: SplitResult = receiver.finish().unwrap();
+
+        assert_eq!(split_result.total_chunks, vector.chunks.len());
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| c.sha256.clone().unwrap())
+                .collect::<Vec<_>>(),
+            vector.chunks.iter().map(|c| c.sha256.clone()).collect::<Vec<_>>()
+        );
+
+        // stop consuming while the reading waits for the channel
+        let receiver: ChunkReceiver = pipeline.spawn().unwrap();
+
+        assert_eq!(receiver.recv().unwrap().index, 0);
+        assert_eq!(receiver.recv().unwrap().index, 1);
+        assert_eq!(
+            receiver.finish().unwrap_err(),
+            PipelineError::ChunkNotUploaded
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_without_kernel_copy() {
+        let (root, cache_dir, output_path, _) =
+            setup("merge_without_kernel_copy");
+
+        for enabled in [true, false] {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .kernel_copy(enabled)
+                .run()
+                .unwrap();
+
+            assert_eq!(
+                fs::read(&output_path).unwrap(),
+                fs::read(root.join("assets").join("test.jpg")).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_portable_names() {
+        let (root, cache_dir, output_path, _) = setup("portable_names");
+
+        assert!(naming::is_portable("part0"));
+        assert!(naming::is_portable("console"));
+        assert!(!naming::is_portable("NUL"));
+        assert!(!naming::is_portable("com1.txt"));
+        assert!(!naming::is_portable("part0."));
+        assert!(!naming::is_portable("part0 "));
+        assert!(!naming::is_portable("part:0"));
+        assert!(!naming::is_portable(".."));
+        assert!(!naming::is_portable(""));
+
+        let out_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("std")
+            .join("portable_names_invalid");
+
+        let _ = fs::remove_dir_all(&out_dir);
+
+        let result: Result<SplitResult, SplitError> = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&out_dir)
+            .chunk_size(1024 * 1024)
+            .namer(Prefixed::new("nul."))
+            .portable_names(true)
+            .run();
+
+        assert_eq!(result.unwrap_err(), SplitError::ChunkNameInvalid);
+        assert_eq!(
+            fs::read_dir(&out_dir).unwrap().count(),
+            0,
+            "No chunk should be written."
+        );
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(output_path.with_file_name("aux.jpg"))
+            .portable_names(true)
+            .run();
+
+        assert_eq!(result.unwrap_err(), MergeError::OutFileNameInvalid);
+    }
+
+    #[tokio::test]
+    async fn test_case_insensitive_names() {
+        // names `c0`, `C0`, `c1`, `C1`, ...
+        #[derive(Debug)]
+        struct Cased;
+
+        impl ChunkNamer for Cased {
+            fn name(
+                &self,
+                index: usize,
+            ) -> String {
+                let prefix: &str =
+                    if index.is_multiple_of(2) { "c" } else { "C" };
+
+                format!("{}{}", prefix, index / 2)
+            }
+
+            fn parse(
+                &self,
+                name: &str,
+            ) -> Option<usize> {
+                let (odd, index) = match name.strip_prefix('c') {
+                    | Some(index) => (0, index),
+                    | None => (1, name.strip_prefix('C')?),
+                };
+
+                Some(plan::parse_chunk_name(index)? * 2 + odd)
+            }
+        }
+
+        let (root, cache_dir, _, split_result) =
+            setup("case_insensitive_names");
+
+        assert_eq!(naming::find_collision(["c0", "c1", "C2"]), None);
+        assert_eq!(naming::find_collision(["c0", "c1", "C0"]), Some("C0"));
+
+        let out_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("std")
+            .join("case_insensitive_names_collision");
+
+        let _ = fs::remove_dir_all(&out_dir);
+
+        let result: Result<SplitResult, SplitError> = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&out_dir)
+            .chunk_size(1024 * 1024)
+            .namer(Cased)
+            .case_insensitive(true)
+            .run();
+
+        assert_eq!(result.unwrap_err(), SplitError::ChunkNameCollision);
+        assert_eq!(
+            fs::read_dir(&out_dir).unwrap().count(),
+            0,
+            "No chunk should be written."
+        );
+
+        Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let mut manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        assert!(split_result.total_chunks > 1);
+        assert_eq!(manifest.find_collision(), None);
+
+        manifest.chunks[0].name = "part".to_string();
+        manifest.chunks[1].name = "PART".to_string();
+
+        assert_eq!(manifest.find_collision(), Some("PART"));
+    }
+
+    #[tokio::test]
+    async fn test_merge_vectored_buffers() {
+        let (root, cache_dir, output_path, _) = setup("merge_vectored_buffers");
+
+        for count in [1, 3, 16] {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .max_buffer_capacity(4096)
+                .kernel_copy(false)
+                .vectored_buffers(count)
+                .run()
+                .unwrap();
+
+            assert_eq!(
+                fs::read(&output_path).unwrap(),
+                fs::read(root.join("assets").join("test.jpg")).unwrap(),
+                "The output should be equal the input with {} buffers.",
+                count
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge_preallocate() {
+        let (root, cache_dir, output_path, split_result) =
+            setup("merge_preallocate");
+
+        for enabled in [true, false] {
+            let result: MergeResult = Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .kernel_copy(false)
+                .preallocate(enabled)
+                .run()
+                .unwrap();
+
+            assert_eq!(result.file_size, split_result.file_size);
+            assert_eq!(
+                fs::metadata(&output_path).unwrap().len() as usize,
+                split_result.file_size,
+                "The reserved space should not change the size."
+            );
+            assert_eq!(
+                fs::read(&output_path).unwrap(),
+                fs::read(root.join("assets").join("test.jpg")).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrate_naming() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("migrate");
+        let out_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("std")
+            .join("migrate_padded");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("migrate")
+            .join("test.jpg");
+
+        let _ = fs::remove_dir_all(&in_dir);
+        let _ = fs::remove_dir_all(&out_dir);
+
+        let split_result: SplitResult = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&in_dir)
+            .chunk_size(1024 * 1024)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        // an interrupted run already moved the first chunk
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::rename(in_dir.join("0"), out_dir.join("000")).unwrap();
+
+        let result: MigrateResult = Migrate::new()
+            .in_dir(&in_dir)
+            .out_dir(&out_dir)
+            .to(Padded::new(3))
+            .run()
+            .unwrap();
+
+        assert_eq!(result.total_chunks, split_result.total_chunks);
+        assert_eq!(result.migrated, split_result.total_chunks - 1);
+        assert_eq!(result.chunk_paths[1], out_dir.join("001"));
+
+        let manifest: Manifest =
+            Manifest::read(out_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        assert_eq!(manifest.chunks[1].name, "001");
+
+        Merge::new()
+            .in_dir(&out_dir)
+            .out_file(&output_path)
+            .namer(Padded::new(3))
+            .run()
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.jpg")).unwrap()
+        );
+
+        // migrated again in place
+        let result: MigrateResult = Migrate::new()
+            .in_dir(&out_dir)
+            .from(Padded::new(3))
+            .to(Gnu::new())
+            .run()
+            .unwrap();
+
+        assert_eq!(result.migrated, split_result.total_chunks);
+        assert_eq!(result.chunk_paths[0], out_dir.join("xaa"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_hints() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("cache_hints");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("cache_hints")
+            .join("test.jpg");
+
+        Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .cache_hints(true)
+            .run()
+            .unwrap();
+
+        for copy in [true, false] {
+            Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .kernel_copy(copy)
+                .cache_hints(true)
+                .run()
+                .unwrap();
+
+            assert_eq!(
+                fs::read(&output_path).unwrap(),
+                fs::read(root.join("assets").join("test.jpg")).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_direct_io() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("direct_io");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("direct_io")
+            .join("test.jpg");
+
+        // the second size is not aligned, so the page cache is used
+        for chunk_size in [1024 * 1024, 1000 * 1000] {
+            let _ = fs::remove_dir_all(&cache_dir);
+
+            let split_result: SplitResult = Split::new()
+                .in_file(root.join("assets").join("test.jpg"))
+                .out_dir(&cache_dir)
+                .chunk_size(chunk_size)
+                .direct_io(true)
+                .run()
+                .unwrap();
+
+            let result: MergeResult = Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .max_buffer_capacity(64 * 1024)
+                .direct_io(true)
+                .run()
+                .unwrap();
+
+            assert_eq!(result.file_size, split_result.file_size);
+            assert_eq!(
+                fs::read(&output_path).unwrap(),
+                fs::read(root.join("assets").join("test.jpg")).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_deadline() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+
+        let in_flight: AtomicUsize = AtomicUsize::new(0);
+        let peak: AtomicUsize = AtomicUsize::new(0);
+
+        // too slow with a single worker, fast enough with more
+        let result: SplitResult = Pipeline::new()
+            .in_file(&in_file)
+            .chunk_size(512 * 1024)
+            .concurrency(1)
+            .max_buffers(4)
+            .deadline(Instant::now() + Duration::from_millis(600))
+            .run(|_| {
+                let current: usize = in_flight.fetch_add(1, Ordering::SeqCst);
+
+                peak.fetch_max(current + 1, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(result.total_chunks, 16);
+        assert!(peak.load(Ordering::SeqCst) > 1);
+
+        let uploaded: AtomicUsize = AtomicUsize::new(0);
+
+        // too slow even with all the workers, so it stops early
+        let result: Result<SplitResult, PipelineError> = Pipeline::new()
+            .in_file(&in_file)
+            .chunk_size(512 * 1024)
+            .concurrency(1)
+            .max_buffers(2)
+            .deadline(Instant::now() + Duration::from_millis(150))
+            .run(|_| {
+                thread::sleep(Duration::from_millis(50));
+                uploaded.fetch_add(1, Ordering::SeqCst);
+
+                Ok(())
+            });
+
+        assert_eq!(result.unwrap_err(), PipelineError::DeadlineExceeded);
+        assert!(uploaded.load(Ordering::SeqCst) < 16);
+
+        let result: Result<SplitResult, PipelineError> = Pipeline::new()
+            .in_file(&in_file)
+            .deadline(Instant::now())
+            .run(|_| Ok(()));
+
+        assert_eq!(result.unwrap_err(), PipelineError::DeadlineExceeded);
+    }
+
+    #[tokio::test]
+    async fn test_durability() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+
+        assert_eq!(Split::new().durability, Durability::Flush);
+        assert_eq!(Merge::new().durability, Durability::Flush);
+
+        for durability in
+            [Durability::Flush, Durability::Fsync, Durability::FsyncDir]
+        {
+            let name: String = format!("durability_{:?}", durability);
+
+            let cache_dir: PathBuf =
+                root.join(".media").join("cache").join("std").join(&name);
+            let output_path: PathBuf = root
+                .join(".media")
+                .join("output")
+                .join("std")
+                .join(&name)
+                .join("test.jpg");
+
+            let split_result: SplitResult = Split::new()
+                .in_file(&in_file)
+                .out_dir(&cache_dir)
+                .chunk_size(1024 * 1024)
+                .with_manifest(true)
+                .durability(durability)
+                .run()
+                .unwrap();
+
+            assert_eq!(split_result.total_chunks, 8);
+
+            let merge_result: MergeResult = Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .durability(durability)
+                .run()
+                .unwrap();
+
+            assert_eq!(merge_result.file_size, split_result.file_size);
+            assert_eq!(
+                fs::read(&output_path).unwrap(),
+                fs::read(&in_file).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_io() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("slow_io");
+
+        let clock: MockClock = MockClock::new();
+        let started: Instant = clock.now();
+
+        // the bucket starts with one second of bytes
+        let mut writer: SlowIo<Vec<u8>> = SlowIo::new(Vec::new())
+            .latency(Duration::from_millis(10))
+            .bandwidth(1024)
+            .clock(clock.clone());
+
+        writer.write_all(&[1; 4096]).unwrap();
+
+        assert_eq!(writer.get_ref().len(), 4096);
+        assert_eq!(
+            clock.now() - started,
+            Duration::from_millis(10) + Duration::from_secs(3)
+        );
+
+        let clock: MockClock = MockClock::new();
+        let started: Instant = clock.now();
+
+        let reader: SlowIo<fs::File> =
+            SlowIo::new(fs::File::open(&in_file).unwrap())
+                .bandwidth(1024 * 1024)
+                .clock(clock.clone());
+
+        let split_result: SplitResult = Split::new()
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .run_reader(reader)
+            .unwrap();
+
+        assert_eq!(split_result.total_chunks, 8);
+        assert!(clock.now() - started >= Duration::from_secs(6));
+
+        test_util::assert_file_eq_chunks(&in_file, &cache_dir);
+    }
+
+    #[tokio::test]
+    async fn test_check_space() {
+        let (root, cache_dir, output_path, split_result) = setup("check_space");
+
+        let merge_result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .check_space(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(merge_result.file_size, split_result.file_size);
+
+        if cfg!(not(target_os = "linux")) {
+            return;
+        }
+
+        // a sparse file larger than the free space
+        let huge_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("std")
+            .join("check_space_huge");
+        let huge_file: PathBuf = huge_dir.join("0");
+
+        let _ = fs::remove_dir_all(&huge_dir);
+        fs::create_dir_all(&huge_dir).unwrap();
+        fs::File::create(&huge_file)
+            .unwrap()
+            .set_len(15 * 1024 * 1024 * 1024 * 1024)
+            .unwrap();
+
+        let result: Result<SplitResult, SplitError> = Split::new()
+            .in_file(&huge_file)
+            .out_dir(huge_dir.join("chunks"))
+            .check_space(true)
+            .run();
+
+        assert_eq!(result.unwrap_err(), SplitError::InsufficientSpace);
+        assert!(!huge_dir.join("chunks").join("0").exists());
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&huge_dir)
+            .out_file(&output_path)
+            .check_space(true)
+            .run();
+
+        assert_eq!(result.unwrap_err(), MergeError::InsufficientSpace);
+        assert!(output_path.exists(), "The output should not be removed.");
+
+        fs::remove_dir_all(&huge_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scope_cancels_on_failure() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("scope");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let value: usize = filego::scope(|s| {
+            for name in ["a", "b"] {
+                let out_dir: PathBuf = cache_dir.join(name);
+
+                s.cleanup(&out_dir);
+                s.spawn(|control| {
+                    Split::new()
+                        .in_file(&in_file)
+                        .out_dir(out_dir)
+                        .control(control)
+                        .run()?;
+
+                    Ok(())
+                });
+            }
+
+            2
+        })
+        .unwrap();
+
+        assert_eq!(value, 2);
+        test_util::assert_file_eq_chunks(&in_file, cache_dir.join("a"));
+        test_util::assert_file_eq_chunks(&in_file, cache_dir.join("b"));
+
+        let started: Instant = Instant::now();
+
+        let result: Result<(), filego::Error> = filego::scope(|s| {
+            let slow_dir: PathBuf = cache_dir.join("slow");
+
+            s.cleanup(&slow_dir);
+            s.spawn(|control| {
+                // takes seconds unless cancelled
+                Split::new()
+                    .in_file(&in_file)
+                    .out_dir(slow_dir)
+                    .throttle_bytes_per_sec(1024 * 1024)
+                    .control(control)
+                    .run()?;
+
+                Ok(())
+            });
+            s.spawn(|_| {
+                Split::new()
+                    .in_file(cache_dir.join("missing"))
+                    .out_dir(cache_dir.join("missing_chunks"))
+                    .run()?;
+
+                Ok(())
+            });
+        });
+
+        assert_eq!(
+            result.unwrap_err(),
+            filego::Error::Split(SplitError::InFileNotFound)
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!cache_dir.join("slow").exists());
+    }
+
+    #[tokio::test]
+    async fn test_merge_if_exists() {
+        let (_, cache_dir, output_path, split_result) = setup("if_exists");
+
+        fs::create_dir_all(output_path.parent().unwrap()).unwrap();
+        fs::write(&output_path, b"existing").unwrap();
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .if_exists(IfExists::Error)
+            .run();
+
+        assert_eq!(result.unwrap_err(), MergeError::OutFileExists);
+        assert_eq!(fs::read(&output_path).unwrap(), b"existing");
+
+        let result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .if_exists(IfExists::Skip)
+            .run()
+            .unwrap();
+
+        assert!(result.skipped);
+        assert_eq!(result.file_size, 8);
+        assert_eq!(result.total_chunks, split_result.total_chunks);
+        assert_eq!(fs::read(&output_path).unwrap(), b"existing");
+
+        let result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run()
+            .unwrap();
+
+        assert!(!result.skipped);
+        assert_eq!(result.file_size, split_result.file_size);
+
+        // a directory is never removed
+        fs::remove_file(&output_path).unwrap();
+        fs::create_dir_all(output_path.join("kept")).unwrap();
+
+        let result: Result<MergeResult, MergeError> =
+            Merge::new().in_dir(&cache_dir).out_file(&output_path).run();
+
+        assert_eq!(result.unwrap_err(), MergeError::OutFileExists);
+        assert!(output_path.join("kept").is_dir());
+
+        fs::remove_dir_all(&output_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_id() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("run_id");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("run_id")
+            .join("test.jpg");
+
+        assert_ne!(Split::new().run_id, Split::new().run_id);
+        assert_ne!(Merge::new().run_id, Merge::new().run_id);
+
+        let id: RunId = "00000000000000000000000000c0ffee".parse().unwrap();
+
+        assert_eq!(id, RunId::new(0xc0ffee));
+        assert_eq!(id.to_string(), "00000000000000000000000000c0ffee");
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .with_manifest(true)
+            .run_id(id)
+            .run()
+            .unwrap();
+
+        let manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        assert_eq!(manifest.run_id(), Some(id));
+
+        let ids: Arc<Mutex<Vec<RunId>>> = Arc::new(Mutex::new(Vec::new()));
+        let recorded: Arc<Mutex<Vec<RunId>>> = Arc::clone(&ids);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run_id(id)
+            .on_progress(move |p| recorded.lock().unwrap().push(p.run_id))
+            .run()
+            .unwrap();
+
+        let ids: Vec<RunId> = ids.lock().unwrap().clone();
+
+        assert_eq!(ids.len(), 8);
+        assert!(ids.iter().all(|i| *i == id));
+    }
+
+    #[tokio::test]
+    async fn test_merge_tee() {
+        let (root, cache_dir, output_path, split_result) = setup("tee");
+
+        let sink_path: PathBuf = output_path.with_extension("sink");
+
+        fs::create_dir_all(sink_path.parent().unwrap()).unwrap();
+
+        let result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .tee(fs::File::create(&sink_path).unwrap())
+            .run()
+            .unwrap();
+
+        let expected: Vec<u8> =
+            fs::read(root.join("assets").join("test.jpg")).unwrap();
+
+        assert_eq!(result.file_size, split_result.file_size);
+        assert_eq!(fs::read(&output_path).unwrap(), expected);
+        assert_eq!(fs::read(&sink_path).unwrap(), expected);
+
+        struct Broken;
+
+        impl std::io::Write for Broken {
+            fn write(
+                &mut self,
+                _: &[u8],
+            ) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken"))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .tee(std::io::sink())
+            .tee(Broken)
+            .run();
+
+        let err: MergeError = result.unwrap_err();
+
+        assert!(matches!(err, MergeError::SinkNotWritten { index: 1, .. }));
+        assert_eq!(err.to_code(), "sink_not_written");
+        assert!(err.source().is_some());
+
+        fs::remove_file(&sink_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_split_if_not_empty() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let out_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("if_not_empty");
+        let stale: PathBuf = out_dir.join("stale");
+
+        fs::create_dir_all(out_dir.join("nested")).unwrap();
+        fs::write(&stale, b"stale").unwrap();
+
+        let split: Split = Split::new()
+            .in_file(&in_file)
+            .out_dir(&out_dir)
+            .chunk_size(1024 * 1024);
+
+        let result: Result<SplitResult, SplitError> =
+            split.clone().if_not_empty(IfNotEmpty::Error).run();
+
+        assert_eq!(result.unwrap_err(), SplitError::OutDirNotEmpty);
+        assert!(!out_dir.join("0").exists());
+
+        // the entries are kept by default
+        split.clone().run().unwrap();
+
+        assert!(stale.exists());
+
+        let result: SplitResult =
+            split.if_not_empty(IfNotEmpty::Clean).run().unwrap();
+
+        assert!(!stale.exists());
+        assert!(!out_dir.join("nested").exists());
+        assert_eq!(
+            fs::read_dir(&out_dir).unwrap().count(),
+            result.total_chunks
+        );
+
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_manifest_data() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("manifest_data");
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .with_manifest(true)
+            .metadata("uploader", "sender")
+            .run()
+            .unwrap();
+
+        // the manifest published by the sender, not next to the chunks
+        let json: String =
+            fs::read_to_string(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        fs::remove_file(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        let mut manifest: Manifest = Manifest::from_json(&json).unwrap();
+
+        let result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .manifest(cache_dir.join(MANIFEST_FILE_NAME))
+            .manifest_data(&manifest)
+            .run()
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.metadata.get("uploader").unwrap(), "sender");
+
+        manifest.chunks[1].size -= 1;
+
+        let error: CheckResultError = Check::new()
+            .in_dir(&cache_dir)
+            .manifest_data(&manifest)
+            .run()
+            .unwrap()
+            .error
+            .unwrap();
+
+        assert_eq!(error.error_type, CheckResultErrorType::Size);
+        assert_eq!(error.mismatched, Some(vec![1]));
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_split_resume() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let out_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("resume");
+
+        let split: Split = Split::new()
+            .in_file(&in_file)
+            .out_dir(&out_dir)
+            .chunk_size(1024 * 1024)
+            .hash(true);
+
+        let expected: SplitResult = split.run().unwrap();
+
+        // an interrupted run, with a chunk changed in place
+        let chunk: Vec<u8> = fs::read(out_dir.join("2")).unwrap();
+
+        fs::write(out_dir.join("2"), vec![0; chunk.len()]).unwrap();
+        fs::write(out_dir.join("5"), b"partial").unwrap();
+        fs::remove_file(out_dir.join("6")).unwrap();
+        fs::remove_file(out_dir.join("7")).unwrap();
+
+        let control: Control = Control::new();
+
+        let result: SplitResult = split
+            .clone()
+            .resume(ResumeCheck::Size)
+            .control(&control)
+            .run()
+            .unwrap();
+
+        assert_eq!(result.total_chunks, expected.total_chunks);
+        assert_eq!(result.chunk_sizes, expected.chunk_sizes);
+        assert_eq!(control.partial().chunks_done, expected.total_chunks);
+        assert_eq!(fs::read(out_dir.join("2")).unwrap(), vec![0; chunk.len()]);
+        assert_ne!(
+            result.hashes.as_ref().unwrap()[2],
+            expected.hashes.as_ref().unwrap()[2]
+        );
+        assert_eq!(
+            result.hashes.as_ref().unwrap()[5..],
+            expected.hashes.as_ref().unwrap()[5..]
+        );
+
+        let result: SplitResult =
+            split.clone().resume(ResumeCheck::Hash).run().unwrap();
+
+        assert_eq!(fs::read(out_dir.join("2")).unwrap(), chunk);
+        assert_eq!(result.hashes, expected.hashes);
+
+        // nothing is kept, and the input is split from its start
+        let first: Vec<u8> = fs::read(out_dir.join("0")).unwrap();
+
+        fs::write(out_dir.join("0"), vec![0; first.len()]).unwrap();
+
+        let result: SplitResult =
+            split.resume(ResumeCheck::Hash).run().unwrap();
+
+        assert_eq!(fs::read(out_dir.join("0")).unwrap(), first);
+        assert_eq!(result.hashes, expected.hashes);
+
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_naming_template() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("template");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("template")
+            .join("test.jpg");
+
+        let template: &str = "{stem}.{index:06}.{hash8}.part";
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .namer(Template::new(template).unwrap().file(&in_file))
+            .with_manifest(true)
+            .hash(true)
+            .run()
+            .unwrap();
+
+        let hashes: Vec<String> = split_result.hashes.clone().unwrap();
+
+        for (index, path) in split_result.chunk_paths.iter().enumerate() {
+            assert_eq!(
+                path.file_name().unwrap(),
+                format!("test.{:06}.{}.part", index, &hashes[index][..8])
+                    .as_str()
+            );
+        }
+
+        // the hashes are found again by a new template
+        let namer: Template = Template::new(template).unwrap().file(&in_file);
+
+        assert_eq!(namer.parse("test.000001.0123abcd.part"), Some(1));
+        assert_eq!(namer.parse("test.000001.0123ABCD.part"), None);
+        assert_eq!(namer.parse("other.000001.0123abcd.part"), None);
+        assert_eq!(namer.parse("test.1.0123abcd.part"), None);
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .namer(Template::new(template).unwrap().file(&in_file))
+            .manifest(cache_dir.join(MANIFEST_FILE_NAME))
+            .run()
+            .unwrap();
+
+        assert!(check_result.success);
+
+        let merge_result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .namer(Template::new(template).unwrap().file(&output_path))
+            .run()
+            .unwrap();
+
+        assert_eq!(merge_result.total_chunks, split_result.total_chunks);
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+        fs::remove_file(&output_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_resume() {
+        let (root, cache_dir, output_path, split_result) =
+            setup("merge_resume");
+
+        let expected: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .hash(true)
+            .run()
+            .unwrap();
+
+        let content: Vec<u8> =
+            fs::read(root.join("assets").join("test.jpg")).unwrap();
+
+        // an interrupted merge, with the kept content marked
+        let mut partial: Vec<u8> = content[..3 * 1024 * 1024 + 100].to_vec();
+        partial[0] ^= 0xff;
+
+        fs::write(&output_path, &partial).unwrap();
+
+        let control: Control = Control::new();
+
+        let result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .resume(true)
+            .control(&control)
+            .run()
+            .unwrap();
+
+        let merged: Vec<u8> = fs::read(&output_path).unwrap();
+
+        assert_eq!(result.file_size, split_result.file_size);
+        assert_eq!(result.total_chunks, split_result.total_chunks);
+        assert_eq!(control.partial().chunks_done, split_result.total_chunks);
+        assert_eq!(merged[0], partial[0]);
+        assert_eq!(merged[1..], content[1..]);
+
+        // the kept content is hashed as well
+        fs::write(&output_path, &content[..5 * 1024 * 1024]).unwrap();
+
+        let result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .resume(true)
+            .hash(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(result.sha256, expected.sha256);
+        assert_eq!(fs::read(&output_path).unwrap(), content);
+
+        // an output longer than the chunks is replaced
+        let mut longer: Vec<u8> = content.clone();
+        longer.push(0);
+
+        fs::write(&output_path, &longer).unwrap();
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .resume(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(fs::read(&output_path).unwrap(), content);
+
+        fs::remove_file(&output_path).unwrap();
+    }
+
+    #[test]
+    fn test_naming_template_stale() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("stale");
+        let in_file: PathBuf = root
+            .join(".media")
+            .join("input")
+            .join("std")
+            .join("stale")
+            .join("data.bin");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("stale")
+            .join("data.bin");
+
+        let template: &str = "{stem}.{index}.{hash8}.part";
+
+        fs::create_dir_all(in_file.parent().unwrap()).unwrap();
+        fs::write(&in_file, vec![1u8; 3000]).unwrap();
+
+        let split = || {
+            Split::new()
+                .in_file(&in_file)
+                .out_dir(&cache_dir)
+                .chunk_size(1024)
+                .namer(Template::new(template).unwrap().file(&in_file))
+                .run()
+                .unwrap()
+        };
+
+        split();
+
+        // the chunks of the changed file are written next to the stale ones
+        fs::write(&in_file, vec![2u8; 3000]).unwrap();
+        split();
+
+        let merge_error: MergeError = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .namer(Template::new(template).unwrap().file(&output_path))
+            .run()
+            .unwrap_err();
+
+        assert_eq!(merge_error, MergeError::InDirDuplicateIndex { index: 0 });
+        assert!(!output_path.exists());
+
+        // a template without the index would write every chunk over the last
+        let split_error: SplitError = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .namer(Template::new("{stem}.part").unwrap().file(&in_file))
+            .run()
+            .unwrap_err();
+
+        assert_eq!(split_error, SplitError::ChunkNameNotUnique);
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+        fs::remove_file(&in_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_max_manifest_size() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("std")
+            .join("max_manifest_size");
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let manifest_path: PathBuf = cache_dir.join(MANIFEST_FILE_NAME);
+
+        assert_eq!(
+            Manifest::read_max(&manifest_path, 16).unwrap_err().kind(),
+            ErrorKind::FileTooLarge
+        );
+
+        let check_error: CheckError = Check::new()
+            .in_dir(&cache_dir)
+            .manifest(&manifest_path)
+            .max_manifest_size(16)
+            .run()
+            .unwrap_err();
+
+        assert_eq!(check_error, CheckError::ManifestTooLarge);
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .manifest(&manifest_path)
+            .run()
+            .unwrap();
+
+        assert!(check_result.success);
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_split_blake3_large_chunks() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("blake3_large.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("blake3_large");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let block: Vec<u8> = (0..251).collect();
+
+        test_util::generate_file(
+            &in_file,
+            3 * 1024 * 1024 + 7,
+            &Pattern::Repeat(block),
+        )
+        .unwrap();
+
+        // each chunk is hashed in parallel parts, and checked sequentially
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(2 * 1024 * 1024)
+            .hash(true)
+            .hash_workers(4)
+            .hash_algorithm(HashAlgorithm::Blake3)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        // known answers of a reference implementation, both chunks are
+        // large enough to be split across the threads
+        assert_eq!(
+            split_result.hashes,
+            Some(vec![
+                "96fbba37478c16b7614c890b26832f67b541cf14e69ab8ebf0c739818588c9f1"
+                    .to_string(),
+                "2eb4d55dab5341a3aa4bc2d7704ac8e1053f76c81d7c597bec0fce7d75ff4b5e"
+                    .to_string(),
+            ])
+        );
+        assert_eq!(
+            split_result.file_hash.as_deref(),
+            Some(
+                "8f3f67e881a256c8a2cc45cce1a0b500a1dd0500623fe5363fe7f77518267c5a"
+            )
+        );
+
+        let check_result: CheckResult = Check::new()
+            .in_dir(&cache_dir)
+            .manifest(cache_dir.join(MANIFEST_FILE_NAME))
+            .run()
+            .unwrap();
+
+        assert!(check_result.success);
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+        fs::remove_file(&in_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_direct_io_cancelled() {
+        let (_, cache_dir, output_path, _) = setup("direct_io_cancelled");
+
+        let control: Control = Control::new();
+        let canceller: Control = control.clone();
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .direct_io(true)
+            .control(&control)
+            .on_progress(move |p| {
+                if p.chunks_done == 2 {
+                    canceller.cancel();
+                }
+            })
+            .run();
+
+        assert_eq!(result, Err(MergeError::Cancelled));
+        assert!(!output_path.exists(), "Partial output should be removed.");
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_parallel_leaves_no_partial_output() {
+        let (root, cache_dir, output_path, _) = setup("parallel_partial");
+
+        for mmap in [false, true] {
+            let control: Control = Control::new();
+            let canceller: Control = control.clone();
+
+            let result: Result<MergeResult, MergeError> = Merge::new()
+                .in_dir(&cache_dir)
+                .out_file(&output_path)
+                .concurrency(2)
+                .mmap(mmap)
+                .control(&control)
+                .on_progress(move |p| {
+                    if p.chunks_done == 2 {
+                        canceller.cancel();
+                    }
+                })
+                .run();
+
+            assert_eq!(result, Err(MergeError::Cancelled));
+            assert!(!output_path.exists());
+
+            // nothing is left to be resumed from
+            let leftovers: usize = fs::read_dir(output_path.parent().unwrap())
+                .map_or(0, |entries| {
+                    entries
+                        .filter_map(Result::ok)
+                        .filter(|e| {
+                            e.file_name()
+                                .to_str()
+                                .is_some_and(cleanup::is_temp_name)
+                        })
+                        .count()
+                });
+
+            assert_eq!(leftovers, 0);
+        }
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .concurrency(2)
+            .resume(true)
+            .run()
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(root.join("assets").join("test.jpg")).unwrap()
+        );
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+        fs::remove_file(&output_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_verify_manifest_mixed_algorithms() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("mixed_hashes.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("std").join("mixed_hashes");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("std")
+            .join("mixed_hashes")
+            .join("output.bin");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let block: Vec<u8> = (0..251).collect();
+
+        test_util::generate_file(&in_file, 1025, &Pattern::Repeat(block))
+            .unwrap();
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024)
+            .hash(true)
+            .with_manifest(true)
+            .run()
+            .unwrap();
+
+        let mut manifest: Manifest =
+            Manifest::read(cache_dir.join(MANIFEST_FILE_NAME)).unwrap();
+
+        // the last chunk is hashed with another algorithm than the others
+        manifest.chunks[1].sha256 = None;
+        manifest.chunks[1].blake3 = Some(
+            "ca9fba296cab1ffbd9597311a1d79a06dfaf0909995a00a98a7e361fb67158e9"
+                .to_string(),
+        );
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .verify_manifest(&manifest);
+
+        merge.run().unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).unwrap(),
+            fs::read(&in_file).unwrap()
+        );
+
+        fs::write(cache_dir.join("1"), [0]).unwrap();
+
+        assert_eq!(merge.run(), Err(MergeError::ChunkCorrupted { index: 1 }));
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+        fs::remove_file(&in_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_split_concurrency_runs_on_pool() {
+        // records the threads naming the chunks
+        #[derive(Debug)]
+        struct Recording(Arc<Mutex<Vec<thread::ThreadId>>>);
+
+        impl ChunkNamer for Recording {
+            fn name(
+                &self,
+                index: usize,
+            ) -> String {
+                self.0.lock().unwrap().push(thread::current().id());
+
+                index.to_string()
+            }
+
+            fn parse(
+                &self,
+                name: &str,
+            ) -> Option<usize> {
+                plan::parse_chunk_name(name)
+            }
+        }
+
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("std")
+            .join("concurrency_runs_on_pool");
+
+        let _ = fs::remove_dir_all(&cache_dir);
+
+        let threads: Arc<Mutex<Vec<thread::ThreadId>>> =
+            Arc::new(Mutex::new(Vec::new()));
+
+        Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .namer(Recording(threads.clone()))
+            .concurrency(4)
+            .run()
+            .unwrap();
+
+        // the chunks are not copied within the file system on this thread
+        let current: thread::ThreadId = thread::current().id();
+
+        assert!(
+            threads.lock().unwrap().iter().any(|t| *t != current),
+            "The chunks should be written from the thread pool."
+        );
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_priority_error_keeps_source() {
+        let denied =
+            || std::io::Error::from(ErrorKind::PermissionDenied).into();
+
+        let split: SplitError = SplitError::PriorityNotSet { source: denied() };
+        let merge: MergeError = MergeError::PriorityNotSet { source: denied() };
+
+        assert_eq!(split.as_code(), "priority_not_set");
+        assert_eq!(merge.as_code(), "priority_not_set");
+
+        for source in [split.source(), merge.source()] {
+            let source: &std::io::Error =
+                source.unwrap().downcast_ref().unwrap();
+
+            assert_eq!(source.kind(), ErrorKind::PermissionDenied);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrate_error_keeps_path_and_source() {
+        let (root, cache_dir, _, _) = setup("migrate_error_path");
+
+        // a file where the parent directory of the output should be
+        let out_dir: PathBuf =
+            root.join("assets").join("test.jpg").join("migrated");
+
+        let error: MigrateError = Migrate::new()
+            .in_dir(&cache_dir)
+            .out_dir(&out_dir)
+            .to(Padded::new(3))
+            .run()
+            .unwrap_err();
+
+        assert!(matches!(error, MigrateError::OutDirNotCreated { .. }));
+        assert_eq!(error.as_code(), "out_dir_not_created");
+        assert_eq!(error.path(), Some(out_dir.as_path()));
+        assert!(error.source().is_some());
+        assert!(error.to_string().contains(&out_dir.display().to_string()));
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_template_rejects_unknown_placeholder() {
+        assert_eq!(
+            Template::new("{stem}.{index}.{hsah8}.part").unwrap_err(),
+            TemplateError::PlaceholderUnknown {
+                placeholder: "hsah8".to_string()
+            }
+        );
+        assert_eq!(
+            Template::new("{stem}.{index:0x}").unwrap_err().as_code(),
+            "placeholder_unknown"
+        );
+        assert_eq!(
+            Template::new("{stem}.{index").unwrap_err(),
+            TemplateError::PlaceholderNotClosed
+        );
+
+        let namer: Template = Template::new("part}.{index:03}").unwrap();
+
+        assert_eq!(namer.name(7), "part}.007");
+        assert_eq!(namer.parse("part}.007"), Some(7));
+    }
 }
@@ -1,14 +1,20 @@
 #[cfg(test)]
 mod tests {
-    use std::{env, path::PathBuf};
+    use std::{collections::HashMap, env, path::PathBuf, time::Duration};
 
-    use tokio::fs::{self, ReadDir};
+    use tokio::{
+        fs::{self, ReadDir},
+        task::JoinHandle,
+    };
 
     use filego::{
         check::{
             Check, CheckResult, CheckResultErrorType, tokio::CheckAsyncExt as _,
         },
-        merge::{Merge, tokio::MergeAsyncExt as _},
+        follow::Follow,
+        hash::HashAlgorithm,
+        merge::{Merge, MergeError, MergeResult, tokio::MergeAsyncExt as _},
+        naming::Template,
         split::{Split, SplitResult, tokio::SplitAsyncExt as _},
     };
 
@@ -46,7 +52,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_split_file_creates_chunks() {
-        let (_, cache_dir, _, _) = setup("split_file_creates_chunks").await;
+        let (_, cache_dir, _, split_result) =
+            setup("split_file_creates_chunks").await;
 
         let mut read_dir: ReadDir = fs::read_dir(&cache_dir).await.unwrap();
 
@@ -57,6 +64,13 @@ mod tests {
         }
 
         assert!(chunk_count > 0, "No chunks were created.");
+
+        assert_eq!(split_result.chunk_paths.len(), split_result.total_chunks);
+        assert_eq!(
+            split_result.chunk_sizes.iter().sum::<usize>(),
+            split_result.file_size
+        );
+        assert!(split_result.chunk_paths.iter().all(|p| p.is_file()));
     }
 
     #[tokio::test]
@@ -117,10 +131,7 @@ mod tests {
             .await
             .unwrap();
 
-        assert!(
-            check_result.success == true,
-            "Check should succeed with no errors."
-        );
+        assert!(check_result.success, "Check should succeed with no errors.");
     }
 
     #[tokio::test]
@@ -166,4 +177,275 @@ mod tests {
             "Merge should fail with an empty cache directory."
         );
     }
+
+    #[tokio::test]
+    async fn test_split_with_hashes() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let split: Split = Split::new()
+            .in_file(root.join("assets").join("test.jpg"))
+            .chunk_size(1024 * 1024)
+            .hash(true)
+            .hash_workers(2)
+            .hash_algorithm(HashAlgorithm::Sha256);
+
+        let expected: SplitResult = Split::from(split.clone())
+            .out_dir(
+                root.join(".media")
+                    .join("cache")
+                    .join("tokio")
+                    .join("hashes_sync"),
+            )
+            .run()
+            .unwrap();
+
+        let split_result: SplitResult = split
+            .out_dir(
+                root.join(".media").join("cache").join("tokio").join("hashes"),
+            )
+            .run_async()
+            .await
+            .unwrap();
+
+        assert_eq!(split_result.total_chunks, expected.total_chunks);
+        assert_eq!(split_result.hashes, expected.hashes);
+        assert_eq!(split_result.file_hash, expected.file_hash);
+        assert!(split_result.file_hash.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_split_follows_growing_file() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf =
+            root.join(".media").join("generated").join("growing_tokio.bin");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("tokio").join("growing");
+
+        fs::create_dir_all(in_file.parent().unwrap()).await.unwrap();
+        fs::write(&in_file, [0u8; 100]).await.unwrap();
+
+        let follow: Follow = Follow::new().interval(Duration::from_millis(5));
+
+        let split: Split = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(64)
+            .follow(&follow);
+
+        let handle: JoinHandle<SplitResult> =
+            tokio::spawn(async move { split.run_async().await.unwrap() });
+
+        for _ in 0..3 {
+            tokio::task::yield_now().await;
+
+            let mut content: Vec<u8> = fs::read(&in_file).await.unwrap();
+            content.extend([1u8; 100]);
+            fs::write(&in_file, content).await.unwrap();
+        }
+
+        follow.end();
+
+        let split_result: SplitResult = handle.await.unwrap();
+
+        assert_eq!(split_result.file_size, 400);
+        assert_eq!(split_result.total_chunks, 7);
+    }
+
+    #[tokio::test]
+    async fn test_merge_pre_finalize_rejects() {
+        let (_, cache_dir, output_path, split_result) =
+            setup("merge_pre_finalize_rejects").await;
+
+        let file_size: u64 = split_result.file_size as u64;
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .pre_finalize(move |reader| reader.len() != file_size)
+            .run_async()
+            .await;
+
+        assert_eq!(result, Err(MergeError::ContentRejected));
+    }
+
+    #[tokio::test]
+    async fn test_split_from_reader() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let content: Vec<u8> =
+            fs::read(root.join("assets").join("test.jpg")).await.unwrap();
+
+        let split_result: SplitResult = Split::new()
+            .out_dir(
+                root.join(".media").join("cache").join("tokio").join("reader"),
+            )
+            .chunk_size(1024 * 1024)
+            .run_reader_async(&content[..])
+            .await
+            .unwrap();
+
+        assert_eq!(split_result.file_size, content.len());
+        assert_eq!(split_result.total_chunks, 8);
+    }
+
+    #[tokio::test]
+    async fn test_merge_verifies_chunks() {
+        let (_, cache_dir, output_path, _) = setup("merge_verify").await;
+
+        let result: Result<MergeResult, MergeError> = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .verify(HashMap::from([(2, [0; 32])]))
+            .run_async()
+            .await;
+
+        assert_eq!(result, Err(MergeError::ChunkCorrupted { index: 2 }));
+        assert!(!output_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_split_with_concurrency() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("tokio").join("concurrency");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("tokio")
+            .join("concurrency")
+            .join("test.jpg");
+
+        let split_result: SplitResult = Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(1024 * 1024)
+            .concurrency(4)
+            .run_async()
+            .await
+            .unwrap();
+
+        assert_eq!(split_result.chunk_paths.len(), split_result.total_chunks);
+
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .run_async()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).await.unwrap(),
+            fs::read(&in_file).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_skips_merged() {
+        let (_, cache_dir, output_path, _) = setup("merge_skips_merged").await;
+
+        let merge: Merge = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .skip_merged(true);
+
+        let _ = fs::remove_file(&output_path).await;
+
+        let merged: MergeResult = merge.run_async().await.unwrap();
+        let skipped: MergeResult = merge.run_async().await.unwrap();
+
+        assert!(!merged.skipped);
+        assert!(skipped.skipped);
+        assert_eq!(skipped.file_size, merged.file_size);
+    }
+
+    #[tokio::test]
+    async fn test_merge_large_buffer_hashed() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf = root
+            .join(".media")
+            .join("cache")
+            .join("tokio")
+            .join("merge_large_buffer_hashed");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("tokio")
+            .join("merge_large_buffer_hashed")
+            .join("output.jpg");
+
+        let _ = fs::remove_dir_all(&cache_dir).await;
+
+        // larger than a single write to a tokio file
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .chunk_size(4 * 1024 * 1024)
+            .run_async()
+            .await
+            .unwrap();
+
+        let merge_result: MergeResult = Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .fill_buffer(true)
+            .hash(true)
+            .run_async()
+            .await
+            .unwrap();
+
+        let content: Vec<u8> = fs::read(&in_file).await.unwrap();
+
+        assert_eq!(merge_result.file_size, content.len());
+        assert_eq!(fs::read(&output_path).await.unwrap(), content);
+
+        fs::remove_dir_all(&cache_dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_template() {
+        let root: PathBuf = env::current_dir().unwrap();
+
+        let in_file: PathBuf = root.join("assets").join("test.jpg");
+        let cache_dir: PathBuf =
+            root.join(".media").join("cache").join("tokio").join("template");
+        let output_path: PathBuf = root
+            .join(".media")
+            .join("output")
+            .join("tokio")
+            .join("template")
+            .join("test.jpg");
+
+        let _ = fs::remove_dir_all(&cache_dir).await;
+
+        let template: &str = "{stem}.{index:06}.{hash8}.part";
+
+        Split::new()
+            .in_file(&in_file)
+            .out_dir(&cache_dir)
+            .namer(Template::new(template).unwrap().file(&in_file))
+            .run_async()
+            .await
+            .unwrap();
+
+        // the hashes in the names are only known by scanning the chunks
+        Merge::new()
+            .in_dir(&cache_dir)
+            .out_file(&output_path)
+            .namer(Template::new(template).unwrap().file(&in_file))
+            .run_async()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(&output_path).await.unwrap(),
+            fs::read(&in_file).await.unwrap()
+        );
+
+        fs::remove_dir_all(&cache_dir).await.unwrap();
+    }
 }